@@ -30,6 +30,7 @@ fn main() -> std::io::Result<()> {
                 "protos/google/api/field_behavior.proto",
                 "protos/google/api/resource.proto",
                 "protos/google/rpc/status.proto",
+                "protos/google/rpc/error_details.proto",
             ],
             &["protos"],
         )
@@ -13,25 +13,22 @@
 // limitations under the License.
 
 //! End to end example of how to use VizierClient to run a study.
-use std::collections::HashMap;
 use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
-use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
-    DoubleValueSpec, ParameterValueSpec, ScaleType,
-};
+use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::study_spec::parameter_spec::ScaleType;
 use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::study_spec::{
-    Algorithm, MeasurementSelectionType, MetricSpec, ObservationNoise, ParameterSpec,
+    Algorithm, MeasurementSelectionType, ObservationNoise,
 };
 use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::trial::State;
-use gcp_vertex_ai_vizier::google::cloud::aiplatform::v1::{measurement, Measurement, Trial};
-use gcp_vertex_ai_vizier::model::study::spec::StudySpecBuilder;
+use gcp_vertex_ai_vizier::model::study::spec::{double_param, metric_spec, StudySpecBuilder};
 use gcp_vertex_ai_vizier::model::study::ToStudyName;
 use gcp_vertex_ai_vizier::model::trial::complete::FinalMeasurementOrReason;
+use gcp_vertex_ai_vizier::model::trial::measurement::MeasurementBuilder;
+use gcp_vertex_ai_vizier::model::trial::params::parameters;
 use gcp_vertex_ai_vizier::model::trial::ToTrialName;
-use gcp_vertex_ai_vizier::prost_types::value::Kind;
-use gcp_vertex_ai_vizier::VizierClient;
+use gcp_vertex_ai_vizier::{routed, VizierClient};
 
 /// Hammelblau's function
 fn f(x: f64, y: f64) -> f64 {
@@ -51,33 +48,13 @@ async fn main() {
         ObservationNoise::Low,
         MeasurementSelectionType::LastMeasurement,
     )
-    .with_metric_specs(vec![MetricSpec {
-        metric_id: "m".to_string(),
-        goal: GoalType::Minimize as i32,
-    }])
+    .with_metric_specs(vec![metric_spec("m", GoalType::Minimize).build()])
     .with_parameters(vec![
-        ParameterSpec {
-            parameter_id: "x".to_string(),
-            scale_type: ScaleType::Unspecified as i32,
-            conditional_parameter_specs: vec![],
-            parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
-                min_value: -5.0,
-                max_value: 5.0,
-                default_value: Some(0.0),
-            })),
-        },
-        ParameterSpec {
-            parameter_id: "y".to_string(),
-            scale_type: ScaleType::Unspecified as i32,
-            conditional_parameter_specs: vec![],
-            parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
-                min_value: -5.0,
-                max_value: 5.0,
-                default_value: Some(0.0),
-            })),
-        },
+        double_param("x", -5.0, 5.0, Some(0.0), ScaleType::Unspecified),
+        double_param("y", -5.0, 5.0, Some(0.0), ScaleType::Unspecified),
     ])
-    .build();
+    .build()
+    .unwrap();
 
     let epoch = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -92,7 +69,7 @@ async fn main() {
         .build()
         .unwrap();
 
-    match client.service.create_study(request).await {
+    match client.service.create_study(routed(request)).await {
         Ok(study_response) => {
             let study = study_response.get_ref();
             dbg!(&study);
@@ -113,50 +90,48 @@ async fn main() {
                 for trial in resp.trials.iter() {
                     dbg!(&trial);
 
-                    let parameters = extract_parameters(trial);
+                    let parameters = parameters(trial);
                     dbg!(&parameters);
 
                     let start = SystemTime::now();
 
-                    let x = parameters["x"];
-                    let y = parameters["y"];
+                    let x = parameters.get_f64("x").unwrap();
+                    let y = parameters.get_f64("y").unwrap();
 
                     let value = f(x, y);
 
                     let elapsed_duration = start.elapsed().unwrap();
                     dbg!(&value);
 
-                    let final_measurement_or_reason =
-                        FinalMeasurementOrReason::FinalMeasurement(Measurement {
-                            elapsed_duration: Some(elapsed_duration.try_into().unwrap()),
-                            step_count: 14,
-                            metrics: vec![measurement::Metric {
-                                metric_id: "m".to_string(),
-                                value,
-                            }],
-                        });
+                    let final_measurement_or_reason = FinalMeasurementOrReason::FinalMeasurement(
+                        MeasurementBuilder::new()
+                            .with_elapsed_duration(elapsed_duration)
+                            .with_step_count(14)
+                            .add_metric("m", value)
+                            .build(),
+                    );
 
                     let request = client.mk_complete_trial_request(
                         trial.to_trial_name(),
                         final_measurement_or_reason,
                     );
 
-                    let trial = client.service.complete_trial(request).await.unwrap();
+                    let trial = client
+                        .service
+                        .complete_trial(routed(request))
+                        .await
+                        .unwrap();
                     let trial = trial.get_ref();
                     dbg!(State::from_i32(trial.state).unwrap());
                 }
             }
 
             // get the best trials
-            let request = client.mk_list_optimal_trials_request(study_name.clone());
-
-            let resp = client.service.list_optimal_trials(request).await.unwrap();
-            let optimal_trials = &resp.get_ref().optimal_trials;
+            let optimal_trials = client.optimal_trials(study_name.clone()).await.unwrap();
             for t in optimal_trials {
                 dbg!(&t.name);
-                dbg!(&t.final_measurement.as_ref().map(|x| x.metrics.clone()));
-                let parameters = extract_parameters(t);
-                dbg!(&parameters);
+                dbg!(&t.metrics);
+                dbg!(&t.parameters);
             }
         }
         Err(e) => {
@@ -164,16 +139,3 @@ async fn main() {
         }
     }
 }
-
-fn extract_parameters(trial: &Trial) -> HashMap<String, f64> {
-    let mut parameters = HashMap::new();
-    for p in trial.parameters.iter() {
-        let p_id = p.parameter_id.clone();
-        if let Some(p) = &p.value {
-            if let Some(Kind::NumberValue(v)) = p.kind {
-                parameters.insert(p_id, v);
-            }
-        }
-    }
-    parameters
-}
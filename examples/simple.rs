@@ -16,7 +16,7 @@
 
 use std::env;
 
-use gcp_vertex_ai_vizier::VizierClient;
+use gcp_vertex_ai_vizier::{routed, VizierClient};
 
 #[tokio::main]
 async fn main() {
@@ -33,7 +33,7 @@ async fn main() {
         .with_page_size(2)
         .build();
 
-    let studies = client.service.list_studies(request).await.unwrap();
+    let studies = client.service.list_studies(routed(request)).await.unwrap();
     let study_list = &studies.get_ref().studies;
     for t in study_list {
         println!("- {}", &t.display_name);
@@ -49,7 +49,7 @@ async fn main() {
                 .with_page_size(2)
                 .build();
 
-            let studies = client.service.list_studies(request).await.unwrap();
+            let studies = client.service.list_studies(routed(request)).await.unwrap();
             let study_list = &studies.get_ref().studies;
             for t in study_list {
                 println!("- {}", &t.display_name);
@@ -0,0 +1,143 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authentication of the clients.
+//!
+//! By default, the credentials are found as described by
+//! [Application Default Credentials](https://cloud.google.com/docs/authentication/application-default-credentials).
+//! A [ServiceAccountKey] can be given to [crate::builder::VizierClientBuilder] instead -
+//! to serve several tenants from the same process, for instance.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use google_authz::{Credentials, CredentialsError};
+
+/// Errors that can occur when resolving the credentials of a client.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The credentials could not be read or parsed.
+    #[error("invalid credentials - {0}")]
+    Credentials(#[from] CredentialsError),
+}
+
+/// Resolves the credentials from `key`, or from the Application Default Credentials if
+/// `None`.
+pub(crate) async fn credentials(key: Option<&ServiceAccountKey>) -> Result<Credentials, Error> {
+    match key {
+        Some(key) => key.credentials().await,
+        None => Ok(Credentials::builder().build().await?),
+    }
+}
+
+/// JSON key of a service account - or of any account accepted by the Application
+/// Default Credentials.
+#[derive(Clone, PartialEq, Eq)]
+pub enum ServiceAccountKey {
+    /// Path of the key file.
+    File(PathBuf),
+    /// Content of the key file.
+    Json(String),
+}
+
+impl ServiceAccountKey {
+    /// Creates a key from either the content of a key file (when it looks like a JSON
+    /// object) or its path.
+    pub fn new(path_or_json: impl Into<String>) -> Self {
+        let path_or_json = path_or_json.into();
+        if path_or_json.trim_start().starts_with('{') {
+            ServiceAccountKey::Json(path_or_json)
+        } else {
+            ServiceAccountKey::File(path_or_json.into())
+        }
+    }
+
+    /// Reads the credentials of the key.
+    pub(crate) async fn credentials(&self) -> Result<Credentials, Error> {
+        let builder = Credentials::builder();
+        let credentials = match self {
+            ServiceAccountKey::File(path) => builder.json_file(path).build().await?,
+            ServiceAccountKey::Json(json) => builder.json(json.as_bytes()).build().await?,
+        };
+
+        Ok(credentials)
+    }
+}
+
+impl From<String> for ServiceAccountKey {
+    fn from(path_or_json: String) -> Self {
+        ServiceAccountKey::new(path_or_json)
+    }
+}
+
+impl From<&str> for ServiceAccountKey {
+    fn from(path_or_json: &str) -> Self {
+        ServiceAccountKey::new(path_or_json)
+    }
+}
+
+/// Does not show the content of the key.
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceAccountKey::File(path) => f.debug_tuple("File").field(path).finish(),
+            ServiceAccountKey::Json(_) => f.debug_tuple("Json").field(&"<redacted>").finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_tells_paths_from_json() {
+        assert_eq!(
+            ServiceAccountKey::new("/etc/vizier/key.json"),
+            ServiceAccountKey::File("/etc/vizier/key.json".into())
+        );
+        assert_eq!(
+            ServiceAccountKey::new("  {\"type\": \"service_account\"}"),
+            ServiceAccountKey::Json("  {\"type\": \"service_account\"}".to_string())
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                ServiceAccountKey::new("{\"private_key\": \"secret\"}")
+            ),
+            "Json(\"<redacted>\")"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_rejects_malformed_keys() {
+        let result = ServiceAccountKey::new("{\"type\": \"service_account\"}")
+            .credentials()
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Credentials(
+                CredentialsError::CredentialsFormat { .. }
+            ))
+        ));
+
+        let result = ServiceAccountKey::new("/nonexistent/key.json")
+            .credentials()
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Credentials(CredentialsError::CredentialsFile(_)))
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Concurrent requests.
+
+use std::future::Future;
+
+use futures::{Stream, StreamExt, TryStreamExt};
+
+use crate::Error;
+
+/// Maximum number of requests in flight for a batch.
+pub(crate) const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// Runs `requests` concurrently, at most [MAX_CONCURRENT_REQUESTS] at once, and returns
+/// their outputs in the order of `requests`.
+pub(crate) async fn in_order<F: Future>(requests: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    futures::stream::iter(requests)
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .collect()
+        .await
+}
+
+/// Lists the items of `items`, then deletes them with `delete` concurrently, at most
+/// [MAX_CONCURRENT_REQUESTS] at once. Returns the number of items deleted.
+///
+/// The listing completes before the first deletion so that the deletions do not shift
+/// the pages being listed. An item already deleted ([tonic::Code::NotFound]) is
+/// skipped; the first other error aborts the remaining deletions.
+pub(crate) async fn delete_all<T, F, Fut>(
+    items: impl Stream<Item = Result<T, Error>>,
+    mut delete: F,
+) -> Result<usize, Error>
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let items: Vec<T> = items.try_collect().await?;
+
+    let deletions = items.into_iter().map(|item| {
+        let deletion = delete(item);
+        async move {
+            match deletion.await {
+                Ok(()) => Ok(1),
+                Err(Error::Status(status)) if status.code() == tonic::Code::NotFound => Ok(0),
+                Err(e) => Err(e),
+            }
+        }
+    });
+
+    futures::stream::iter(deletions)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_fold(0, |deleted, d| async move { Ok(deleted + d) })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::{sleep, Instant};
+    use tonic::Status;
+
+    use super::*;
+    use crate::paging::paginate;
+
+    #[tokio::test(start_paused = true)]
+    async fn it_preserves_the_order() {
+        let start = Instant::now();
+
+        let delays = [3, 1, 2, 0];
+        let outputs = in_order(delays.map(|delay| async move {
+            sleep(Duration::from_secs(delay)).await;
+            delay
+        }))
+        .await;
+
+        assert_eq!(outputs, delays);
+        // The requests ran concurrently.
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn it_deletes_all_the_items_of_all_the_pages() {
+        let pages = paginate(|page_token| async move {
+            Ok(match page_token.as_str() {
+                "" => (vec![1, 2, 3], "a".to_string()),
+                _ => (vec![4, 5], String::new()),
+            })
+        });
+
+        let mut deleted_items = vec![];
+        let deleted = delete_all(pages, |item| {
+            deleted_items.push(item);
+            async move {
+                match item {
+                    // already deleted
+                    2 => Err(Status::not_found("gone").into()),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(deleted, 4);
+        assert_eq!(deleted_items, [1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn it_fails_on_other_errors() {
+        let pages = paginate(|_| async { Ok((vec![1, 2], String::new())) });
+
+        let result = delete_all(pages, |item| async move {
+            match item {
+                2 => Err(Status::permission_denied("denied").into()),
+                _ => Ok(()),
+            }
+        })
+        .await;
+
+        assert!(
+            matches!(result, Err(Error::Status(status)) if status.code() == tonic::Code::PermissionDenied)
+        );
+    }
+}
@@ -0,0 +1,536 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! [VizierClient] builder.
+
+use std::future::Future;
+use std::time::Duration;
+
+use regex::Regex;
+
+use crate::auth::{self, ServiceAccountKey};
+use crate::google::cloud::aiplatform::v1::vizier_service_client::VizierServiceClient;
+use crate::google::longrunning::operations_client::OperationsClient;
+use crate::metadata::MetadataInterceptor;
+use crate::polling::DEFAULT_POLL_INTERVAL;
+use crate::retry::RetryConfig;
+use crate::{Error, VizierClient};
+
+/// Returns true if `endpoint` is a valid host name with an optional port.
+fn is_valid_endpoint(endpoint: &str) -> bool {
+    let re =
+        Regex::new(r"^[a-z0-9]([a-z0-9-]*[a-z0-9])?(\.[a-z0-9]([a-z0-9-]*[a-z0-9])?)*(:\d{1,5})?$")
+            .unwrap();
+    re.is_match(endpoint)
+}
+
+/// User agent of the crate.
+const USER_AGENT: &str = "github.com/ssoudan/gcp-vertex-ai-vizier";
+
+/// Configuration of the channels of a [VizierClient].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChannelConfig {
+    /// Whether HTTP/2 adaptive flow control is enabled.
+    pub(crate) http2_adaptive_window: bool,
+    /// PEM bundle of the CA certificates to trust instead of the bundled roots.
+    pub(crate) ca_certificate: Option<String>,
+    /// Product token prepended to the user agent of the crate.
+    pub(crate) user_agent: Option<String>,
+    /// Timeout to connect to the endpoint.
+    pub(crate) connect_timeout: Option<Duration>,
+    /// Timeout of each request sent over the channel.
+    pub(crate) request_timeout: Option<Duration>,
+}
+
+impl ChannelConfig {
+    /// Returns the user agent of the channel.
+    pub(crate) fn user_agent(&self) -> String {
+        match &self.user_agent {
+            Some(user_agent) => format!("{user_agent} {USER_AGENT}"),
+            None => USER_AGENT.to_string(),
+        }
+    }
+}
+
+/// Builds the channels to `endpoint` and to `operations_endpoint` with `build` - a
+/// single one, shared by both, when the endpoints are the same and there is no
+/// `request_timeout`.
+///
+/// `build` is given the request timeout of the channel: `request_timeout` for the
+/// channel of the Vizier service, none for the channel of the operations, whose
+/// `WaitOperation` long-polls are bounded by their own timeout.
+async fn build_channels<C, F, Fut>(
+    endpoint: String,
+    operations_endpoint: String,
+    request_timeout: Option<Duration>,
+    mut build: F,
+) -> Result<(C, C), Error>
+where
+    C: Clone,
+    F: FnMut(String, Option<Duration>) -> Fut,
+    Fut: Future<Output = Result<C, Error>>,
+{
+    if endpoint == operations_endpoint && request_timeout.is_none() {
+        let channel = build(endpoint, None).await?;
+        return Ok((channel.clone(), channel));
+    }
+
+    Ok((
+        build(endpoint, request_timeout).await?,
+        build(operations_endpoint, None).await?,
+    ))
+}
+
+/// [VizierClient] builder.
+///
+/// By default, both the Vizier service and the operations are reached at
+/// `{location}-aiplatform.googleapis.com`.
+pub struct VizierClientBuilder {
+    project: String,
+    location: String,
+    endpoint: Option<String>,
+    operations_endpoint: Option<String>,
+    retry_config: RetryConfig,
+    request_timeout: Option<Duration>,
+    poll_interval: Duration,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+    channel_config: ChannelConfig,
+    service_account_key: Option<ServiceAccountKey>,
+    metadata: Vec<(String, String)>,
+}
+
+impl VizierClientBuilder {
+    /// Creates a new instance of [VizierClient] builder.
+    /// # Arguments
+    /// * `project` - The project id.
+    /// * `location` - The location id.
+    pub fn new(project: String, location: String) -> Self {
+        VizierClientBuilder {
+            project,
+            location,
+            endpoint: None,
+            operations_endpoint: None,
+            retry_config: RetryConfig::default(),
+            request_timeout: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+            channel_config: ChannelConfig::default(),
+            service_account_key: None,
+            metadata: vec![],
+        }
+    }
+
+    /// Sets the host name (with an optional port) of the Vizier service - and of the
+    /// operations, unless [VizierClientBuilder::with_operations_endpoint] is used.
+    ///
+    /// The location is then not required to look like a region - an emulator can use any.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Sets the host name (with an optional port) serving the long-running operations,
+    /// for setups where they are not served by the same endpoint as the Vizier
+    /// service.
+    pub fn with_operations_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.operations_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Sets the [RetryConfig] of the client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// Sets the maximum size of a decoded message - 4MB by default.
+    ///
+    /// Responses larger than this fail to decode: raise it when listing studies with
+    /// many large trials (many measurements or parameters) or when using big pages.
+    pub fn with_max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Sets the maximum size of an encoded message - unlimited by default.
+    pub fn with_max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    /// Enables HTTP/2 adaptive flow control (BDP-based window sizing) - disabled by
+    /// default. Helps throughput of large responses on high-latency links.
+    pub fn with_http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.channel_config.http2_adaptive_window = enabled;
+        self
+    }
+
+    /// Sets the CA certificates (a PEM bundle) trusted to verify the endpoints - such as
+    /// the CA of a TLS-intercepting proxy. The bundled root certificates are used by
+    /// default.
+    pub fn with_ca_certificate(mut self, pem: impl Into<String>) -> Self {
+        self.channel_config.ca_certificate = Some(pem.into());
+        self
+    }
+
+    /// Sets a product token (such as `my-tuner/1.2`) prepended to the user agent of the
+    /// requests.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.channel_config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sets the timeout to establish the connections - none by default.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.channel_config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of each request made by the methods of the client - none by
+    /// default. See [VizierClient::with_request_timeout].
+    ///
+    /// The timeout is also set on the channel of the Vizier service, so that the calls
+    /// made directly through [VizierClient::service] are bounded too - they fail with a
+    /// [tonic::Status] then. The channel of the operations is not bounded, for the
+    /// `WaitOperation` long-polls of [VizierClient::wait_for_operation] to last as long
+    /// as their own timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Authenticates with a service account key - either the path of the key file or
+    /// its JSON content - instead of the Application Default Credentials.
+    ///
+    /// The key is read by [VizierClientBuilder::build], which fails with [Error::Auth]
+    /// if it cannot be read or parsed.
+    pub fn with_service_account_key(mut self, key: impl Into<ServiceAccountKey>) -> Self {
+        self.service_account_key = Some(key.into());
+        self
+    }
+
+    /// Adds the metadata `key: value` to every request of the client - such as
+    /// `x-goog-user-project` to attribute the quota to another project.
+    ///
+    /// The requests made by the methods of the client also carry the
+    /// `x-goog-request-params` header the service routes them with.
+    /// [VizierClientBuilder::build] fails with [Error::Metadata] if the key or the
+    /// value is not valid ASCII metadata.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the interval between two polls of the long-running operations - 100ms by
+    /// default. See [VizierClient::with_poll_interval].
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Returns the endpoints of the Vizier service and of the operations.
+    ///
+    /// The location is only checked when the endpoint is derived from it.
+    #[allow(clippy::result_large_err)]
+    fn endpoints(&self) -> Result<(String, String), Error> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => {
+                let re = Regex::new(r"^[a-z]+-[a-z]+\d+$").unwrap();
+                if !re.is_match(&self.location) {
+                    return Err(Error::UnknownLocation(self.location.clone()));
+                }
+
+                format!(
+                    "{location}-aiplatform.googleapis.com",
+                    location = self.location
+                )
+            }
+        };
+        let operations_endpoint = self
+            .operations_endpoint
+            .clone()
+            .unwrap_or_else(|| endpoint.clone());
+
+        for e in [&endpoint, &operations_endpoint] {
+            if !is_valid_endpoint(e) {
+                return Err(Error::InvalidEndpoint(e.clone()));
+            }
+        }
+
+        Ok((endpoint, operations_endpoint))
+    }
+
+    /// Builds the [VizierClient].
+    ///
+    /// Fails with [Error::UnknownLocation] if the location does not look like a region -
+    /// only checked when the endpoint is derived from it, see
+    /// [VizierClientBuilder::with_endpoint] -, with [Error::InvalidEndpoint] if an
+    /// endpoint is not a valid host name (with an optional port), and with
+    /// [Error::Auth] if the credentials cannot be resolved - an invalid service
+    /// account key or no Application Default Credentials, for instance.
+    pub async fn build(self) -> Result<VizierClient, Error> {
+        let (endpoint, operations_endpoint) = self.endpoints()?;
+        let interceptor = MetadataInterceptor::new(self.metadata.iter().map(|(k, v)| (k, v)))?;
+
+        let channel_config = &self.channel_config;
+        let service_account_key = self.service_account_key.as_ref();
+        let (channel, operations_channel) = build_channels(
+            endpoint,
+            operations_endpoint,
+            self.request_timeout,
+            |endpoint, request_timeout| async move {
+                let config = ChannelConfig {
+                    request_timeout,
+                    ..channel_config.clone()
+                };
+                let credentials = auth::credentials(service_account_key).await?;
+                VizierClient::build_channel(endpoint, &config, credentials).await
+            },
+        )
+        .await?;
+
+        let service = {
+            let mut service = VizierServiceClient::with_interceptor(channel, interceptor.clone());
+            if let Some(limit) = self.max_decoding_message_size {
+                service = service.max_decoding_message_size(limit);
+            }
+            if let Some(limit) = self.max_encoding_message_size {
+                service = service.max_encoding_message_size(limit);
+            }
+            service
+        };
+
+        let operation_service = {
+            let mut operation_service =
+                OperationsClient::with_interceptor(operations_channel, interceptor);
+            if let Some(limit) = self.max_decoding_message_size {
+                operation_service = operation_service.max_decoding_message_size(limit);
+            }
+            if let Some(limit) = self.max_encoding_message_size {
+                operation_service = operation_service.max_encoding_message_size(limit);
+            }
+            operation_service
+        };
+
+        Ok(VizierClient {
+            project: self.project,
+            location: self.location,
+            service,
+            operation_service,
+            retry_config: self.retry_config,
+            request_timeout: self.request_timeout,
+            poll_interval: self.poll_interval,
+            service_account_key: self.service_account_key,
+            metadata: self.metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::transport::{Certificate, ClientTlsConfig};
+
+    use super::*;
+
+    #[test]
+    fn it_validates_endpoints() {
+        assert!(is_valid_endpoint("us-central1-aiplatform.googleapis.com"));
+        assert!(is_valid_endpoint("localhost"));
+        assert!(!is_valid_endpoint(""));
+        assert!(!is_valid_endpoint("https://example.com"));
+        assert!(!is_valid_endpoint("example..com"));
+        assert!(!is_valid_endpoint("-example.com"));
+        assert!(is_valid_endpoint("localhost:8080"));
+        assert!(!is_valid_endpoint("localhost:"));
+        assert!(!is_valid_endpoint("localhost:port"));
+    }
+
+    #[test]
+    fn it_overrides_the_endpoints() {
+        let builder = VizierClientBuilder::new("project".to_string(), "us-central1".to_string());
+        assert_eq!(
+            builder.endpoints().unwrap(),
+            (
+                "us-central1-aiplatform.googleapis.com".to_string(),
+                "us-central1-aiplatform.googleapis.com".to_string()
+            )
+        );
+
+        let builder = builder.with_endpoint("vizier.internal:8443");
+        let (endpoint, operations_endpoint) = builder.endpoints().unwrap();
+        assert_eq!(endpoint, "vizier.internal:8443");
+        assert_eq!(operations_endpoint, "vizier.internal:8443");
+
+        let channel_endpoint =
+            VizierClient::mk_channel_endpoint(&endpoint, &builder.channel_config).unwrap();
+        assert_eq!(channel_endpoint.uri().host(), Some("vizier.internal"));
+        assert_eq!(channel_endpoint.uri().port_u16(), Some(8443));
+        assert_eq!(channel_endpoint.uri().scheme_str(), Some("https"));
+    }
+
+    #[test]
+    fn it_only_checks_the_location_of_derived_endpoints() {
+        let builder = VizierClientBuilder::new("project".to_string(), "local".to_string());
+        assert!(matches!(
+            builder.endpoints(),
+            Err(Error::UnknownLocation(l)) if l == "local"
+        ));
+
+        let builder = builder.with_endpoint("localhost:8080");
+        assert_eq!(
+            builder.endpoints().unwrap(),
+            ("localhost:8080".to_string(), "localhost:8080".to_string())
+        );
+    }
+
+    #[test]
+    fn it_uses_the_ca_certificate() {
+        const PEM: &str = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+        let builder = VizierClientBuilder::new("project".to_string(), "us-central1".to_string())
+            .with_endpoint("proxy.corp:443")
+            .with_ca_certificate(PEM);
+        assert_eq!(builder.channel_config.ca_certificate.as_deref(), Some(PEM));
+
+        let tls_config = VizierClient::mk_tls_config(
+            "proxy.corp",
+            builder.channel_config.ca_certificate.as_deref(),
+        );
+        let expected = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(PEM))
+            .domain_name("proxy.corp");
+        assert_eq!(format!("{tls_config:?}"), format!("{expected:?}"));
+
+        let bundled = VizierClient::mk_tls_config("proxy.corp", None);
+        assert_ne!(format!("{bundled:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn it_sets_the_user_agent() {
+        let builder = VizierClientBuilder::new("project".to_string(), "us-central1".to_string());
+        assert_eq!(builder.channel_config.user_agent(), USER_AGENT);
+
+        let builder = builder
+            .with_user_agent("my-tuner/1.2")
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_timeout(Duration::from_secs(30));
+        assert_eq!(
+            builder.channel_config.user_agent(),
+            "my-tuner/1.2 github.com/ssoudan/gcp-vertex-ai-vizier"
+        );
+        assert!(VizierClient::mk_channel_endpoint(
+            "us-central1-aiplatform.googleapis.com",
+            &builder.channel_config
+        )
+        .is_ok());
+
+        let builder = builder.with_user_agent("my-tuner\n");
+        assert!(matches!(
+            VizierClient::mk_channel_endpoint(
+                "us-central1-aiplatform.googleapis.com",
+                &builder.channel_config
+            ),
+            Err(Error::Tonic(_))
+        ));
+    }
+
+    #[test]
+    fn it_sets_the_poll_interval() {
+        let builder = VizierClientBuilder::new("project".to_string(), "us-central1".to_string());
+        assert_eq!(builder.poll_interval, Duration::from_millis(100));
+
+        let builder = builder.with_poll_interval(Duration::from_secs(5));
+        assert_eq!(builder.poll_interval, Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_a_malformed_service_account_key() {
+        let result = VizierClientBuilder::new("project".to_string(), "us-central1".to_string())
+            .with_service_account_key(r#"{"type": "service_account", "client_email": 42}"#)
+            .build()
+            .await;
+
+        assert!(matches!(result, Err(Error::Auth(_))));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_invalid_operations_endpoints() {
+        let result = VizierClientBuilder::new("project".to_string(), "us-central1".to_string())
+            .with_operations_endpoint("ops endpoint")
+            .build()
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidEndpoint(e)) if e == "ops endpoint"));
+    }
+
+    #[tokio::test]
+    async fn it_shares_the_channel_of_a_single_endpoint() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let built = AtomicUsize::new(0);
+        let build = |endpoint: String, request_timeout: Option<Duration>| {
+            built.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Arc::new((endpoint, request_timeout))) }
+        };
+
+        let (channel, operations_channel) =
+            build_channels("vizier".to_string(), "vizier".to_string(), None, build)
+                .await
+                .unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+        assert!(Arc::ptr_eq(&channel, &operations_channel));
+
+        let (channel, operations_channel) =
+            build_channels("vizier".to_string(), "operations".to_string(), None, build)
+                .await
+                .unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 3);
+        assert_eq!(*channel, ("vizier".to_string(), None));
+        assert_eq!(*operations_channel, ("operations".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn it_only_bounds_the_requests_of_the_vizier_service_channel() {
+        let timeout = Some(Duration::from_secs(30));
+        let build = |endpoint: String, request_timeout: Option<Duration>| async move {
+            Ok((endpoint, request_timeout))
+        };
+
+        // The channel is not shared, the operations one is not bounded.
+        let (channel, operations_channel) =
+            build_channels("vizier".to_string(), "vizier".to_string(), timeout, build)
+                .await
+                .unwrap();
+        assert_eq!(channel, ("vizier".to_string(), timeout));
+        assert_eq!(operations_channel, ("vizier".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_invalid_metadata() {
+        let result = VizierClientBuilder::new("project".to_string(), "us-central1".to_string())
+            .with_metadata("x-goog-user-project", "billing-project")
+            .with_metadata("x goog", "v")
+            .build()
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Metadata(crate::metadata::Error::InvalidKey(k))) if k == "x goog"
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diagnostics for transport failures.
+//!
+//! Transport errors coming from [tonic] are opaque. This module inspects the chain of
+//! errors to give a hint about the most common causes of failures: a wrong location
+//! (the derived domain name does not exist), TLS issues or an unreachable endpoint.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+/// Likely cause of a transport failure.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Diagnostic {
+    /// The domain name could not be resolved - usually a typo in the location.
+    UnknownHost,
+    /// The TLS handshake failed.
+    Tls,
+    /// The endpoint refused the connection.
+    ConnectionRefused,
+    /// The connection timed out.
+    Timeout,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Diagnostic::UnknownHost => {
+                "the endpoint could not be resolved - check the location is a valid region \
+                 (e.g. us-central1)"
+            }
+            Diagnostic::Tls => {
+                "the TLS handshake failed - check the CA certificates and the domain name"
+            }
+            Diagnostic::ConnectionRefused => {
+                "the connection was refused - check the endpoint and any proxy configuration"
+            }
+            Diagnostic::Timeout => "the connection timed out - check the network connectivity",
+        };
+
+        f.write_str(msg)
+    }
+}
+
+/// Inspects an error and its sources to find the likely cause of a transport failure.
+pub fn diagnose(err: &(dyn StdError + 'static)) -> Option<Diagnostic> {
+    let mut current = Some(err);
+
+    while let Some(err) = current {
+        if let Some(diagnostic) = diagnose_message(&err.to_string()) {
+            return Some(diagnostic);
+        }
+        current = err.source();
+    }
+
+    None
+}
+
+fn diagnose_message(msg: &str) -> Option<Diagnostic> {
+    let msg = msg.to_lowercase();
+
+    if msg.contains("dns error")
+        || msg.contains("failed to lookup address")
+        || msg.contains("name or service not known")
+    {
+        Some(Diagnostic::UnknownHost)
+    } else if msg.contains("tls handshake")
+        || msg.contains("handshakefailure")
+        || msg.contains("invalid certificate")
+        || msg.contains("invalid peer certificate")
+    {
+        Some(Diagnostic::Tls)
+    } else if msg.contains("connection refused") {
+        Some(Diagnostic::ConnectionRefused)
+    } else if msg.contains("timed out") {
+        Some(Diagnostic::Timeout)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, VizierClient};
+
+    #[tokio::test]
+    async fn it_rejects_a_location_with_a_typo() {
+        let res = VizierClient::new("project".to_string(), "us-centrall".to_string()).await;
+
+        assert!(matches!(res, Err(Error::UnknownLocation(l)) if l == "us-centrall"));
+    }
+
+    #[test]
+    fn it_diagnoses_an_unknown_host() {
+        let err = Error::Status(tonic::Status::unavailable(
+            "error trying to connect: dns error: failed to lookup address information: Name \
+             or service not known",
+        ));
+
+        assert_eq!(err.diagnostic(), Some(Diagnostic::UnknownHost));
+    }
+
+    #[test]
+    fn it_diagnoses_tls_failures() {
+        let err = Error::Status(tonic::Status::unavailable(
+            "error trying to connect: invalid peer certificate: UnknownIssuer",
+        ));
+        assert_eq!(err.diagnostic(), Some(Diagnostic::Tls));
+
+        let err = Error::Status(tonic::Status::unavailable(
+            "error trying to connect: tls handshake eof",
+        ));
+        assert_eq!(err.diagnostic(), Some(Diagnostic::Tls));
+
+        // Mentioning TLS is not enough.
+        let err = Error::Status(tonic::Status::invalid_argument(
+            "the tls_config field is not supported",
+        ));
+        assert_eq!(err.diagnostic(), None);
+    }
+
+    #[test]
+    fn it_diagnoses_refused_connections() {
+        let err = Error::Status(tonic::Status::unavailable(
+            "error trying to connect: tcp connect error: Connection refused (os error 111)",
+        ));
+
+        assert_eq!(err.diagnostic(), Some(Diagnostic::ConnectionRefused));
+    }
+
+    #[test]
+    fn it_diagnoses_timeouts() {
+        let err = Error::Status(tonic::Status::unavailable(
+            "error trying to connect: tcp connect error: Connection timed out (os error 110)",
+        ));
+
+        assert_eq!(err.diagnostic(), Some(Diagnostic::Timeout));
+    }
+
+    #[test]
+    fn it_does_not_diagnose_service_errors() {
+        let err = Error::Status(tonic::Status::not_found("study not found"));
+
+        assert_eq!(err.diagnostic(), None);
+    }
+}
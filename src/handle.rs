@@ -0,0 +1,164 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Handle on a study caching its spec.
+
+use crate::google::cloud::aiplatform::v1::{Measurement, Study, StudySpec, Trial};
+use crate::metadata::routed;
+use crate::study::validate::validate_measurement;
+use crate::study::{StudyName, ToStudyName};
+use crate::trial::add_measurement::AddedMeasurement;
+use crate::trial::complete::{CompletedTrial, FinalMeasurementOrReason};
+use crate::trial::TrialName;
+use crate::{Error, VizierClient};
+
+/// Handle on a study - returned by [VizierClient::open_study] and
+/// [VizierClient::find_or_create_study].
+///
+/// The [Study] (and its [StudySpec]) is fetched once and cached: the measurements
+/// reported through the handle are validated against the cached spec before being sent
+/// (see [validate_measurement]) and rejected with [Error::Invalid] if they do not
+/// conform.
+///
+/// The cache is never invalidated automatically. As the spec of a study cannot be
+/// changed, this only matters for the other fields of the [Study] (e.g. its `state`):
+/// call [StudyHandle::refresh] to fetch them again.
+#[derive(Clone)]
+pub struct StudyHandle {
+    client: VizierClient,
+    study: Study,
+    spec: StudySpec,
+}
+
+impl StudyHandle {
+    fn new(client: VizierClient, study: Study) -> Self {
+        let spec = study.study_spec.clone().unwrap_or_default();
+        StudyHandle {
+            client,
+            study,
+            spec,
+        }
+    }
+
+    /// Returns the cached [Study].
+    pub fn study(&self) -> &Study {
+        &self.study
+    }
+
+    /// Returns the cached [StudySpec].
+    pub fn spec(&self) -> &StudySpec {
+        &self.spec
+    }
+
+    /// Returns the [StudyName] of the study.
+    pub fn name(&self) -> StudyName {
+        self.study.to_study_name()
+    }
+
+    /// Returns the [TrialName] of the trial `trial` of the study.
+    pub fn trial_name(&self, trial: impl Into<String>) -> TrialName {
+        TrialName::from_study(&self.name(), trial.into())
+    }
+
+    /// Fetches the [Study] again.
+    pub async fn refresh(&mut self) -> Result<(), Error> {
+        let request = self.client.mk_get_study_request(self.name());
+        let study = self
+            .client
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_study(routed(request)).await
+            })
+            .await?
+            .into_inner();
+        *self = StudyHandle::new(self.client.clone(), study);
+
+        Ok(())
+    }
+
+    /// Suggests `suggestion_count` trials for `client_id`.
+    pub async fn suggest(
+        &mut self,
+        suggestion_count: i32,
+        client_id: String,
+    ) -> Result<Vec<Trial>, Error> {
+        let request =
+            self.client
+                .mk_suggest_trials_request(self.name(), suggestion_count, client_id);
+
+        Ok(self.client.suggest_trials(request).await?.trials)
+    }
+
+    /// Adds a measurement to a trial of the study after validating it - see
+    /// [VizierClient::add_trial_measurement_checked].
+    pub async fn add_measurement(
+        &mut self,
+        trial_name: TrialName,
+        measurement: Measurement,
+    ) -> Result<AddedMeasurement, Error> {
+        self.client
+            .add_trial_measurement_checked(trial_name, measurement, &self.spec)
+            .await
+    }
+
+    /// Completes a trial of the study after validating its final measurement, if any -
+    /// see [VizierClient::complete_trial].
+    pub async fn complete(
+        &mut self,
+        trial_name: TrialName,
+        final_measurement: FinalMeasurementOrReason,
+    ) -> Result<CompletedTrial, Error> {
+        if let FinalMeasurementOrReason::FinalMeasurement(m) = &final_measurement {
+            validate_measurement(self.spec(), m).map_err(Error::Invalid)?;
+        }
+
+        self.client
+            .complete_trial(trial_name, final_measurement)
+            .await
+    }
+
+    /// Lists all the trials of the study.
+    pub async fn list_trials(&mut self) -> Result<Vec<Trial>, Error> {
+        let study_name = self.name();
+        self.client.collect_trials(study_name).await
+    }
+}
+
+impl VizierClient {
+    /// Opens an existing study and returns a [StudyHandle] on it.
+    pub async fn open_study(&mut self, study_name: StudyName) -> Result<StudyHandle, Error> {
+        let request = self.mk_get_study_request(study_name);
+        let study = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_study(routed(request)).await
+            })
+            .await?
+            .into_inner();
+
+        Ok(StudyHandle::new(self.clone(), study))
+    }
+
+    /// Returns a [StudyHandle] on the study with the given display name, creating it with
+    /// `study_spec` if it does not exist.
+    ///
+    /// See [VizierClient::get_or_create_study].
+    pub async fn find_or_create_study(
+        &mut self,
+        display_name: impl Into<String>,
+        study_spec: StudySpec,
+    ) -> Result<StudyHandle, Error> {
+        let study = self.get_or_create_study(display_name, study_spec).await?;
+
+        Ok(StudyHandle::new(self.clone(), study))
+    }
+}
@@ -15,6 +15,8 @@
 //! Unofficial GCP Vertex AI Vizier Client API.
 //!
 //! ```
+//! use gcp_vertex_ai_vizier::{routed, VizierClient};
+//!
 //! let project = env::var("GOOGLE_CLOUD_PROJECT").unwrap();
 //! let location = "us-central1".to_string();
 //!
@@ -27,36 +29,66 @@
 //!     .with_page_size(2)
 //!     .build();
 //!
-//! let studies = client.service.list_studies(request).await.unwrap();
+//! let studies = client.service.list_studies(routed(request)).await.unwrap();
 //! let study_list = &studies.get_ref().studies;
 //! for t in study_list {
 //!     println!("- {}", &t.display_name);
 //! }
 //! ```
 
+use std::collections::HashMap;
+use std::future::Future;
 use std::time::Duration;
 
+use futures::{Stream, StreamExt, TryStreamExt};
 use google::cloud::aiplatform::v1::vizier_service_client::VizierServiceClient;
-use google_authz::GoogleAuthz;
+use google_authz::{Credentials, GoogleAuthz};
 pub use prost_types;
-use tokio::time::sleep;
 use tonic::codegen::http::uri::InvalidUri;
-use tonic::transport::{Certificate, Channel, ClientTlsConfig};
-
+use tonic::codegen::InterceptedService;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+use tonic::Response;
+
+use crate::auth::ServiceAccountKey;
+use crate::builder::{ChannelConfig, VizierClientBuilder};
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+use crate::google::cloud::aiplatform::v1::trial::State;
 use crate::google::cloud::aiplatform::v1::{
-    AddTrialMeasurementRequest, CheckTrialEarlyStoppingStateRequest, CompleteTrialRequest,
-    CreateTrialRequest, DeleteStudyRequest, DeleteTrialRequest, GetStudyRequest, GetTrialRequest,
-    ListOptimalTrialsRequest, LookupStudyRequest, Measurement, StopTrialRequest,
+    AddTrialMeasurementRequest, CheckTrialEarlyStoppingStateRequest,
+    CheckTrialEarlyStoppingStateResponse, CompleteTrialRequest, CreateTrialRequest,
+    DeleteStudyRequest, DeleteTrialRequest, GetStudyRequest, GetTrialRequest,
+    ListOptimalTrialsRequest, LookupStudyRequest, Measurement, StopTrialRequest, Study, StudySpec,
     SuggestTrialsRequest, SuggestTrialsResponse, Trial,
 };
 use crate::google::longrunning::operations_client::OperationsClient;
 use crate::google::longrunning::{operation, GetOperationRequest, Operation, WaitOperationRequest};
+use crate::metadata::MetadataInterceptor;
 use crate::model::{study, trial};
-use crate::study::StudyName;
-use crate::trial::complete::FinalMeasurementOrReason;
-use crate::trial::{early_stopping, optimal, stop, TrialName};
+use crate::retry::RetryConfig;
+use crate::study::list::StudyOrder;
+use crate::study::{StudyName, ToStudyName};
+use crate::trial::add_measurement::AddedMeasurement;
+use crate::trial::analysis::EmaConvergence;
+use crate::trial::complete::{CompletedTrial, FinalMeasurementOrReason};
+use crate::trial::early_stopping::StopDecision;
+use crate::trial::measurement::{IntoMetrics, MergeConflict};
+use crate::trial::optimal::OptimalTrial;
+use crate::trial::{
+    add_measurement, analysis, complete, early_stopping, optimal, stop, ToTrialName, TrialName,
+};
 
+pub mod auth;
+mod batch;
+pub mod builder;
+pub mod diagnostic;
+pub mod handle;
+pub mod metadata;
+pub use metadata::routed;
 pub mod model;
+mod paging;
+mod polling;
+pub mod retry;
+mod timeout;
 pub mod util;
 
 /// google protos.
@@ -96,15 +128,34 @@ pub mod google {
     }
 }
 
+/// The channel of the clients - authenticated, and adding the metadata of the client to
+/// the requests (see [VizierClientBuilder::with_metadata]).
+pub type AuthChannel = InterceptedService<GoogleAuthz<Channel>, MetadataInterceptor>;
+
 /// Vizier client.
+///
+/// The convenience methods return the decoded payload (or a type of this crate) rather
+/// than a [tonic::Response]. When the server response metadata is needed, use the
+/// `*_raw` variant of the method - [VizierClient::get_operation_raw] and
+/// [VizierClient::suggest_trials_raw] - which keeps the [tonic::Response] wrapper.
+/// The raw gRPC calls remain available through [VizierClient::service] and
+/// [VizierClient::operation_service].
 #[derive(Clone)]
 pub struct VizierClient {
     location: String,
     project: String,
     /// The Vizier service client.
-    pub service: VizierServiceClient<GoogleAuthz<Channel>>,
-    /// The longrunning operations (to deal with [Operation]) client.
-    pub operation_service: OperationsClient<GoogleAuthz<Channel>>,
+    ///
+    /// Wrap the request messages with [routed] to send the routing header the service
+    /// expects.
+    pub service: VizierServiceClient<AuthChannel>,
+    /// The longrunning operations (to deal with [Operation]) client - see [routed].
+    pub operation_service: OperationsClient<AuthChannel>,
+    retry_config: RetryConfig,
+    request_timeout: Option<Duration>,
+    poll_interval: Duration,
+    service_account_key: Option<ServiceAccountKey>,
+    metadata: Vec<(String, String)>,
 }
 
 /// Errors that can occur when using [VizierClient].
@@ -113,6 +164,12 @@ pub enum Error {
     /// Transport error
     #[error("tonic transport error - {0}")]
     Tonic(#[from] tonic::transport::Error),
+    /// Authentication error - the credentials could not be resolved.
+    #[error("authentication error - {0}")]
+    Auth(#[from] auth::Error),
+    /// Invalid request metadata.
+    #[error("{0}")]
+    Metadata(#[from] metadata::Error),
     /// Invalid URI.
     #[error("{0}")]
     InvalidUri(#[from] InvalidUri),
@@ -122,10 +179,93 @@ pub enum Error {
     /// Vizier service error.
     #[error("Status: {}", .0.message())]
     Status(#[from] tonic::Status),
+    /// The location does not look like a GCP region.
+    #[error("unknown location '{0}' - expected a region such as us-central1")]
+    UnknownLocation(String),
+    /// Invalid study creation request.
+    #[error("{0}")]
+    CreateStudy(#[from] study::create::Error),
+    /// The request does not conform to the study spec.
+    #[error("invalid request - {0:?}")]
+    Invalid(Vec<study::validate::Violation>),
+    /// Measurement error.
+    #[error("{0}")]
+    Measurement(#[from] trial::measurement::Error),
+    /// ListStudies request error.
+    #[error("{0}")]
+    ListStudies(#[from] study::list::Error),
+    /// ListTrials request error.
+    #[error("{0}")]
+    ListTrials(#[from] trial::list::Error),
+    /// Error decoding the trial returned when adding a measurement.
+    #[error("{0}")]
+    AddMeasurement(#[from] trial::add_measurement::Error),
+    /// A long-running operation is done but carries neither a response nor an error.
+    #[error("operation {operation_name} done without result")]
+    OperationWithoutResult {
+        /// The name of the operation.
+        operation_name: String,
+    },
+    /// A request did not complete within the request timeout of the client.
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
+    /// A round of a [model::optimize::Optimizer] did not complete within its timeout.
+    #[error("round timed out after {timeout:?} in the {phase} phase")]
+    RoundTimeout {
+        /// The phase running at the deadline.
+        phase: model::optimize::Phase,
+        /// The timeout of the round.
+        timeout: Duration,
+    },
+    /// The endpoint is not a valid host name with an optional port.
+    #[error("invalid endpoint '{0}' - expected a host name with an optional port")]
+    InvalidEndpoint(String),
+    /// The resource name is malformed.
+    #[error("invalid resource name {0}")]
+    InvalidResourceName(String),
+    /// [VizierClient::new_blocking] was called from within an async runtime.
+    #[cfg(feature = "blocking")]
+    #[error("new_blocking cannot be called from within an async runtime - use new().await")]
+    BlockingInAsyncContext,
+}
+
+impl Error {
+    /// Returns the likely cause of a transport failure, if it can be inferred.
+    ///
+    /// Only transport errors and [tonic::Code::Unavailable] statuses are inspected.
+    pub fn diagnostic(&self) -> Option<diagnostic::Diagnostic> {
+        match self {
+            Error::Tonic(err) => diagnostic::diagnose(err),
+            Error::Status(status) if status.code() == tonic::Code::Unavailable => {
+                diagnostic::diagnose(status)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if the service reported that the resource does not exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::Status(status) if status.code() == tonic::Code::NotFound)
+    }
+}
+
+/// Maps a [tonic::Code::NotFound] [Error::Status] to `Ok(None)`.
+#[allow(clippy::result_large_err)]
+fn not_found_as_none<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e),
+    }
 }
 
 const CERTIFICATES: &str = include_str!("../certs/roots.pem");
 
+/// Runtime driving the connections of the clients created with
+/// [VizierClient::new_blocking].
+#[cfg(feature = "blocking")]
+static BLOCKING_RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+
 impl VizierClient {
     /// Creates a new VizierClient.
     ///
@@ -133,9 +273,18 @@ impl VizierClient {
     /// * `project` - The project id.
     /// * `location` - The location id. See https://cloud.google.com/functions/docs/reference/rpc/google.cloud.location
     ///
+    /// See [VizierClientBuilder] for more settings.
+    ///
+    /// Fails with [Error::UnknownLocation] if `location` does not look like a region, and
+    /// with [Error::Auth] if the Application Default Credentials cannot be resolved. As
+    /// the connection is established lazily, other transport failures surface on the
+    /// first call; see [Error::diagnostic] to get a hint about their cause.
+    ///
     /// # Example
     ///
     /// ```
+    /// use gcp_vertex_ai_vizier::{routed, VizierClient};
+    ///
     /// let project = env::var("GOOGLE_CLOUD_PROJECT").unwrap();
     /// let location = "us-central1".to_string();
     ///
@@ -148,46 +297,160 @@ impl VizierClient {
     ///     .with_page_size(2)
     ///     .build();
     ///
-    /// let studies = client.service.list_studies(request).await.unwrap();
+    /// // A direct call to the service: it has no timeout, unless the client is built with
+    /// // `VizierClientBuilder::with_timeout`.
+    /// let studies = client.service.list_studies(routed(request)).await.unwrap();
     /// let study_list = &studies.get_ref().studies;
     /// for t in study_list {
     ///     println!("- {}", &t.display_name);
     /// }
     /// ```
     pub async fn new(project: String, location: String) -> Result<Self, Error> {
-        let domain_name = format!("{location}-aiplatform.googleapis.com", location = location);
+        VizierClientBuilder::new(project, location).build().await
+    }
 
-        let service = {
-            let channel = Self::build_channel(domain_name.clone()).await?;
-            VizierServiceClient::new(channel)
-        };
+    /// Creates a new VizierClient reaching the Vizier service (and the operations) at
+    /// `endpoint` rather than at `{location}-aiplatform.googleapis.com`.
+    ///
+    /// `endpoint` is a host name with an optional port - such as a private service
+    /// connect endpoint or an emulator. TLS and authentication are still used. See
+    /// [VizierClientBuilder] for more options.
+    pub async fn with_endpoint(
+        project: String,
+        location: String,
+        endpoint: impl Into<String>,
+    ) -> Result<Self, Error> {
+        VizierClientBuilder::new(project, location)
+            .with_endpoint(endpoint)
+            .build()
+            .await
+    }
 
-        let operation_service = {
-            let channel = Self::build_channel(domain_name).await?;
-            OperationsClient::new(channel)
-        };
+    /// Creates a new VizierClient from synchronous code - such as a non-async `main`.
+    ///
+    /// Same as [VizierClient::new] but blocks until the client is created. The
+    /// connections of the client are driven by a dedicated background runtime shared by
+    /// all the clients created this way, so the client can be used afterwards from any
+    /// runtime.
+    ///
+    /// Fails with [Error::BlockingInAsyncContext] when called from within an async
+    /// runtime, where blocking would stall the runtime - use [VizierClient::new] there.
+    ///
+    /// Requires the `blocking` feature.
+    #[cfg(feature = "blocking")]
+    #[allow(clippy::result_large_err)]
+    pub fn new_blocking(project: String, location: String) -> Result<Self, Error> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(Error::BlockingInAsyncContext);
+        }
 
-        Ok(Self {
-            project,
-            location,
-            service,
-            operation_service,
-        })
+        let runtime = BLOCKING_RUNTIME.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("vizier-client")
+                .enable_all()
+                .build()
+                .expect("failed to create the runtime of the blocking client")
+        });
+
+        runtime.block_on(Self::new(project, location))
+    }
+
+    /// Creates a new [VizierClient] for the same project in another location.
+    ///
+    /// The settings of this client (its [RetryConfig], request timeout, poll interval and
+    /// request metadata) are carried over. The credentials are resolved again - from the
+    /// [ServiceAccountKey] of this client if any, from the environment otherwise, the
+    /// same way [VizierClient::new] does.
+    pub async fn for_location(&self, location: impl Into<String>) -> Result<VizierClient, Error> {
+        let mut builder = VizierClientBuilder::new(self.project.clone(), location.into());
+        if let Some(key) = &self.service_account_key {
+            builder = builder.with_service_account_key(key.clone());
+        }
+        for (key, value) in &self.metadata {
+            builder = builder.with_metadata(key, value);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.with_timeout(timeout);
+        }
+        let client = builder.build().await?;
+
+        Ok(client
+            .with_retry_config(self.retry_config.clone())
+            .with_poll_interval(self.poll_interval))
+    }
+
+    /// Sets the [RetryConfig] used for the retries of the calls made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// Sets the timeout of the requests made by the methods of this client - `None` (the
+    /// default) to wait indefinitely.
+    ///
+    /// A request not completed in time fails with [Error::Timeout]. A call retried as
+    /// configured by the [RetryConfig] is bounded as a whole. This does not apply to
+    /// [VizierClient::wait_for_operation], which is bounded by its own timeout, nor to
+    /// the calls made directly through [VizierClient::service] and
+    /// [VizierClient::operation_service] - use [VizierClientBuilder::with_timeout] to
+    /// bound the calls made through [VizierClient::service] too.
+    pub fn with_request_timeout(mut self, request_timeout: Option<Duration>) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Sets the interval between two polls of the long-running operations awaited by
+    /// this client - 100ms by default. A few seconds is more sensible for operations
+    /// known to be long, such as suggestions of Bayesian optimization studies.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
     }
 
-    async fn build_channel(domain_name: String) -> Result<GoogleAuthz<Channel>, Error> {
-        let tls_config = ClientTlsConfig::new()
-            .ca_certificate(Certificate::from_pem(CERTIFICATES))
-            .domain_name(&domain_name);
+    /// Creates the TLS configuration to reach `domain_name`, trusting `ca_certificate` (a
+    /// PEM bundle) or the bundled root certificates.
+    fn mk_tls_config(domain_name: &str, ca_certificate: Option<&str>) -> ClientTlsConfig {
+        let ca_certificate = ca_certificate.unwrap_or(CERTIFICATES);
 
-        let endpoint = format!("https://{endpoint}", endpoint = domain_name);
+        ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(ca_certificate))
+            .domain_name(domain_name)
+    }
+
+    /// Creates the (unauthenticated) [Endpoint] of the channel to `endpoint` - a host
+    /// name with an optional port.
+    #[allow(clippy::result_large_err)]
+    fn mk_channel_endpoint(endpoint: &str, config: &ChannelConfig) -> Result<Endpoint, Error> {
+        let domain_name = endpoint.split(':').next().unwrap_or(endpoint);
+        let tls_config = Self::mk_tls_config(domain_name, config.ca_certificate.as_deref());
 
-        let channel = Channel::from_shared(endpoint)?
-            .user_agent("github.com/ssoudan/gcp-vertex-ai-vizier")?
+        let mut endpoint = Channel::from_shared(format!("https://{endpoint}"))?
+            .user_agent(config.user_agent())?
             .tls_config(tls_config)?
-            .connect_lazy();
+            .http2_adaptive_window(config.http2_adaptive_window);
+        if let Some(timeout) = config.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        if let Some(timeout) = config.request_timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+
+        Ok(endpoint)
+    }
 
-        let channel = GoogleAuthz::new(channel).await;
+    /// Builds the channel to `endpoint`, authenticated with `credentials`.
+    async fn build_channel(
+        endpoint: String,
+        config: &ChannelConfig,
+        credentials: Credentials,
+    ) -> Result<GoogleAuthz<Channel>, Error> {
+        let channel = Self::mk_channel_endpoint(&endpoint, config)?.connect_lazy();
+
+        let channel = GoogleAuthz::builder(channel)
+            .credentials(credentials)
+            .build()
+            .await;
 
         Ok(channel)
     }
@@ -208,7 +471,7 @@ impl VizierClient {
     }
 
     /// Creates a new [LookupStudyRequest].
-    pub fn mk_lookup_study_request(&self, display_name: String) -> LookupStudyRequest {
+    pub fn mk_lookup_study_request(&self, display_name: impl Into<String>) -> LookupStudyRequest {
         study::lookup::RequestBuilder::new(
             self.project.clone(),
             self.location.clone(),
@@ -228,15 +491,24 @@ impl VizierClient {
     }
 
     /// Creates a new [SuggestTrialsRequest].
+    ///
+    /// If `client_id` is empty, [VizierClient::default_client_id] is used.
     pub fn mk_suggest_trials_request(
         &self,
         study_name: StudyName,
         suggestion_count: i32,
-        client_id: String,
+        client_id: impl Into<String>,
     ) -> SuggestTrialsRequest {
         trial::suggest::RequestBuilder::new(study_name, suggestion_count, client_id).build()
     }
 
+    /// Returns a client id unique to the current process, of the form `{hostname}-{pid}`.
+    ///
+    /// It is used by the suggest methods when no explicit `client_id` is provided.
+    pub fn default_client_id() -> String {
+        trial::suggest::default_client_id()
+    }
+
     /// Creates a new [CreateTrialRequest].
     pub fn mk_create_trial_request(
         &self,
@@ -303,8 +575,13 @@ impl VizierClient {
     /// Arguments
     /// * `study` - The study number - {study} in the pattern.
     /// * `trial` - The trial number - {trial} in the pattern.
-    pub fn trial_name(&self, study: String, trial: String) -> TrialName {
-        TrialName::new(self.project.clone(), self.location.clone(), study, trial)
+    pub fn trial_name(&self, study: impl Into<String>, trial: impl Into<String>) -> TrialName {
+        TrialName::new(
+            self.project.clone(),
+            self.location.clone(),
+            study.into(),
+            trial.into(),
+        )
     }
 
     /// Creates a [TrialName] from a [StudyName] and trial number.
@@ -328,7 +605,9 @@ impl VizierClient {
     }
 
     /// Waits for an operation to be completed.
-    /// Makes 3 attempts and return the error if it still fails.
+    /// The failed calls are retried as configured by the [RetryConfig] of the client (3
+    /// retries with an exponential backoff by default) and the error is returned if it
+    /// still fails.
     /// # Arguments
     /// * `operation` - The operation to wait for.
     /// * `timeout` - The timeout for each call to
@@ -339,51 +618,875 @@ impl VizierClient {
         timeout: Option<Duration>,
     ) -> Result<Option<operation::Result>, Error> {
         while !operation.done {
-            let mut retries = 3;
-            let mut wait_ms = 500;
-            let resp = loop {
-                match self
-                    .operation_service
-                    .wait_operation(WaitOperationRequest {
-                        name: operation.name.clone(),
-                        timeout: timeout.map(|d| d.try_into().unwrap()),
+            log::debug!("waiting for operation {}", operation.name);
+            let operation_service = &self.operation_service;
+            let resp = retry::retry(&self.retry_config, || {
+                let mut operation_service = operation_service.clone();
+                let request = WaitOperationRequest {
+                    name: operation.name.clone(),
+                    timeout: timeout.map(|d| d.try_into().unwrap()),
+                };
+                async move { operation_service.wait_operation(routed(request)).await }
+            })
+            .await?;
+
+            operation = resp.into_inner();
+            polling::log_operation(&operation);
+        }
+
+        Ok(operation.result)
+    }
+
+    /// Waits for a suggestion [Operation] - e.g. returned by
+    /// [VizierClient::suggest_trials_operation] - with [VizierClient::wait_for_operation]
+    /// and decodes its result.
+    ///
+    /// Fails with [Error::DecodingError] if the operation failed, and with
+    /// [Error::OperationWithoutResult] if it is done without result.
+    pub async fn wait_for_suggestions(
+        &mut self,
+        operation: Operation,
+        timeout: Option<Duration>,
+    ) -> Result<SuggestTrialsResponse, Error> {
+        let operation_name = operation.name.clone();
+        let result = self.wait_for_operation(operation, timeout).await?;
+
+        polling::decode_result(&operation_name, result)
+    }
+
+    /// Formats the trials of a study as a human-readable table sorted by the primary
+    /// objective - see [study::results::format_results_table].
+    pub async fn format_results_table(&mut self, study_name: StudyName) -> Result<String, Error> {
+        let request = self.mk_get_study_request(study_name.clone());
+        let study = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_study(routed(request)).await
+            })
+            .await?
+            .into_inner();
+        let study_spec = study.study_spec.unwrap_or_default();
+
+        let trials = self.collect_trials(study_name).await?;
+
+        Ok(study::results::format_results_table(&study_spec, &trials))
+    }
+
+    /// Waits for a study to converge and returns the best trial.
+    ///
+    /// The optimal trials are polled every `poll_interval` and the best value of the
+    /// metric `metric_id` among them is tracked with an exponential moving average (EMA),
+    /// see [EmaConvergence]. The EMA smoothing factor is `2 / (window + 1)` and
+    /// convergence is declared when the EMA changed by less than `tolerance` over the
+    /// last `window` polls. This is less sensitive to noise than waiting for the set of
+    /// optimal trials to stop changing.
+    ///
+    /// Polls without any optimal trial reporting the metric are not counted.
+    /// # Arguments
+    /// * `study_name` - The study to watch.
+    /// * `metric_id` - The metric to track.
+    /// * `goal` - Whether the metric is maximized or minimized.
+    /// * `tolerance` - The maximum change of the EMA over `window` polls.
+    /// * `window` - The number of polls considered.
+    /// * `poll_interval` - The time between two polls.
+    pub async fn wait_for_convergence(
+        &mut self,
+        study_name: StudyName,
+        metric_id: &str,
+        goal: GoalType,
+        tolerance: f64,
+        window: usize,
+        poll_interval: Duration,
+    ) -> Result<Trial, Error> {
+        let mut convergence = EmaConvergence::new(tolerance, window);
+
+        polling::poll_until(
+            poll_interval,
+            || {
+                let client = self.clone();
+                let request = self.mk_list_optimal_trials_request(study_name.clone());
+                async move {
+                    let resp = client
+                        .call_idempotent(request, |mut service, request| async move {
+                            service.list_optimal_trials(routed(request)).await
+                        })
+                        .await?;
+                    Ok(resp.into_inner().optimal_trials)
+                }
+            },
+            |trials| {
+                let best = analysis::best_trial(&trials, metric_id, goal)?;
+                let value = analysis::final_metric_value(best, metric_id).unwrap_or_default();
+                convergence.update(value).then(|| best.clone())
+            },
+        )
+        .await
+    }
+
+    /// Lists the optimal trials of a study - the pareto-optimal ones for a
+    /// multi-objective study - with their parameters and final metrics.
+    pub async fn optimal_trials(
+        &mut self,
+        study_name: StudyName,
+    ) -> Result<Vec<OptimalTrial>, Error> {
+        let request = self.mk_list_optimal_trials_request(study_name);
+        let trials = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.list_optimal_trials(routed(request)).await
+            })
+            .await?
+            .into_inner()
+            .optimal_trials;
+
+        Ok(trials.iter().map(OptimalTrial::from).collect())
+    }
+
+    /// Counts the trials of a study, going through all the pages - the API has no count
+    /// call.
+    ///
+    /// Fails with the first error fetching a page.
+    pub async fn count_trials(&self, study_name: StudyName) -> Result<usize, Error> {
+        paging::count(self.list_all_trials(study_name, None)).await
+    }
+
+    /// Counts the trials of a study in each [State] - see [analysis::count_by_state].
+    pub async fn count_trials_by_state(
+        &mut self,
+        study_name: StudyName,
+    ) -> Result<HashMap<State, usize>, Error> {
+        let trials = self.collect_trials(study_name).await?;
+
+        Ok(analysis::count_by_state(&trials))
+    }
+
+    /// Returns the fraction of a trial `budget` used by the completed trials of a study,
+    /// clamped to 1.0 - see [analysis::progress].
+    ///
+    /// The v1 API has no notion of trial budget: `budget` is the number of trials the
+    /// caller intends to run - e.g. the one given to
+    /// [model::optimize::Optimizer::with_budget].
+    pub async fn study_progress(
+        &mut self,
+        study_name: StudyName,
+        budget: usize,
+    ) -> Result<f32, Error> {
+        let counts = self.count_trials_by_state(study_name).await?;
+
+        Ok(analysis::progress(&counts, budget))
+    }
+
+    /// Returns true if the study has trials and all of them are completed - see
+    /// [analysis::is_complete].
+    ///
+    /// The v1 API has no RPC to update the state of a study (to complete or deactivate
+    /// it): the completion is inferred from the trials.
+    pub async fn study_is_complete(&mut self, study_name: StudyName) -> Result<bool, Error> {
+        let counts = self.count_trials_by_state(study_name).await?;
+
+        Ok(analysis::is_complete(&counts))
+    }
+
+    /// Suggests trials only if the study has capacity for them.
+    ///
+    /// The trials in progress (requested, active or stopping - see
+    /// [analysis::count_in_progress]) are counted and up to `suggestion_count` trials are
+    /// suggested without exceeding `max_active` trials in progress. Returns the suggested
+    /// trials - none if the study is at capacity.
+    ///
+    /// The count and the suggestion are not atomic: workers calling this concurrently can
+    /// briefly exceed `max_active`.
+    pub async fn suggest_if_capacity(
+        &mut self,
+        study_name: StudyName,
+        client_id: impl Into<String>,
+        max_active: usize,
+        suggestion_count: usize,
+    ) -> Result<Vec<Trial>, Error> {
+        let counts = self.count_trials_by_state(study_name.clone()).await?;
+        let capacity = max_active.saturating_sub(analysis::count_in_progress(&counts));
+
+        let count = suggestion_count.min(capacity);
+        if count == 0 {
+            return Ok(vec![]);
+        }
+
+        let request = self.mk_suggest_trials_request(study_name, count as i32, client_id);
+        let resp = self.suggest_trials(request).await?;
+
+        Ok(resp.trials)
+    }
+
+    /// Deletes the studies of the project and location of the client matching
+    /// `predicate` - for instance on a display name prefix or on their age (see
+    /// [Study::age]).
+    ///
+    /// There is deliberately no variant deleting all the studies: `predicate` is the only
+    /// selector. The studies are listed first, then the matching ones are deleted
+    /// concurrently (at most 8 at a time). A study already deleted by someone else
+    /// ([tonic::Code::NotFound]) is skipped. Returns the number of studies deleted by
+    /// this call; the first other error aborts the remaining deletions.
+    pub async fn delete_studies_matching(
+        &mut self,
+        predicate: impl Fn(&Study) -> bool,
+    ) -> Result<usize, Error> {
+        let studies = self.list_studies_filtered(predicate);
+
+        batch::delete_all(studies, |study| {
+            let mut service = self.service.clone();
+            let request = self.mk_delete_study_request(study.to_study_name());
+            let request_timeout = self.request_timeout;
+            async move {
+                timeout::call(request_timeout, service.delete_study(routed(request))).await?;
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Deletes all the trials of a study - going through all the pages - and returns the
+    /// number of trials deleted by this call.
+    ///
+    /// The trials are listed first, then deleted concurrently (at most 8 at a time). A
+    /// trial already deleted ([tonic::Code::NotFound]) is skipped; the first other error
+    /// aborts the remaining deletions.
+    pub async fn delete_all_trials(&mut self, study_name: StudyName) -> Result<usize, Error> {
+        let trials = self.list_all_trials(study_name, None);
+
+        batch::delete_all(trials, |trial| {
+            let mut service = self.service.clone();
+            let request = self.mk_delete_trial_request(trial.to_trial_name());
+            let request_timeout = self.request_timeout;
+            async move {
+                timeout::call(request_timeout, service.delete_trial(routed(request))).await?;
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Deletes a study and waits until it is gone.
+    ///
+    /// `DeleteStudy` does not return an [Operation] to wait for: once it succeeds, the
+    /// study is fetched every second until the service reports it as not found, for at
+    /// most `timeout` - after which this fails with [Error::Timeout]. Deleting a study
+    /// that does not exist is a successful no-op.
+    pub async fn delete_study_and_wait(
+        &mut self,
+        study_name: StudyName,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let request = self.mk_delete_study_request(study_name.clone());
+        match timeout::call(
+            self.request_timeout,
+            self.service.delete_study(routed(request)),
+        )
+        .await
+        {
+            Ok(_) => {}
+            Err(Error::Status(status)) if status.code() == tonic::Code::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        let gone = polling::poll_until(
+            Duration::from_secs(1),
+            || {
+                let client = self.clone();
+                let request = client.mk_get_study_request(study_name.clone());
+                async move {
+                    match client
+                        .call_idempotent(request, |mut service, request| async move {
+                            service.get_study(routed(request)).await
+                        })
+                        .await
+                    {
+                        Ok(_) => Ok(false),
+                        Err(Error::Status(status)) if status.code() == tonic::Code::NotFound => {
+                            Ok(true)
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+            },
+            |gone| gone.then_some(()),
+        );
+
+        timeout::call(Some(timeout), gone).await
+    }
+
+    /// Lists all the studies of the project and location of the client, going through all
+    /// the pages.
+    ///
+    /// The studies are yielded one at a time, the pages of `page_size` studies (the
+    /// default of the service if `None`) being fetched as needed. An error fetching a
+    /// page - including an invalid `page_size` - is yielded by the stream, which then
+    /// ends.
+    pub fn list_all_studies(
+        &self,
+        page_size: Option<i32>,
+    ) -> impl Stream<Item = Result<Study, Error>> {
+        let client = self.clone();
+
+        paging::paginate(move |page_token| {
+            let client = client.clone();
+            let mut request = client
+                .mk_list_studies_request_builder()
+                .with_page_token(page_token);
+            if let Some(page_size) = page_size {
+                request = request.with_page_size(page_size);
+            }
+            let request = request.try_build();
+
+            async move {
+                let request = request?;
+                let resp = client
+                    .call_idempotent(request, |mut service, request| async move {
+                        service.list_studies(routed(request)).await
+                    })
+                    .await?
+                    .into_inner();
+                Ok((resp.studies, resp.next_page_token))
+            }
+        })
+    }
+
+    /// Lists the studies of the project and location of the client for which `predicate`
+    /// returns true, going through all the pages - see [VizierClient::list_all_studies].
+    pub fn list_studies_filtered(
+        &self,
+        predicate: impl Fn(&Study) -> bool,
+    ) -> impl Stream<Item = Result<Study, Error>> {
+        study::list::filter_studies(self.list_all_studies(None), predicate)
+    }
+
+    /// Collects the studies of the project and location of the client for which
+    /// `predicate` returns true - ordered by `sorted_by` if any, in the order of the
+    /// service otherwise.
+    pub async fn collect_studies_filtered(
+        &self,
+        predicate: impl Fn(&Study) -> bool,
+        sorted_by: Option<StudyOrder>,
+    ) -> Result<Vec<Study>, Error> {
+        let mut studies: Vec<Study> = self.list_studies_filtered(predicate).try_collect().await?;
+        if let Some(order) = sorted_by {
+            studies.sort_by(|a, b| order.compare(a, b));
+        }
+
+        Ok(studies)
+    }
+
+    /// Lists all the trials of a study, going through all the pages.
+    ///
+    /// The trials are yielded one at a time, the pages of `page_size` trials (the default
+    /// of the service if `None`) being fetched as needed. An error fetching a page -
+    /// including an invalid `page_size` - is yielded by the stream, which then ends.
+    pub fn list_all_trials(
+        &self,
+        study_name: StudyName,
+        page_size: Option<i32>,
+    ) -> impl Stream<Item = Result<Trial, Error>> {
+        let client = self.clone();
+
+        paging::paginate(move |page_token| {
+            let client = client.clone();
+            let mut request = client
+                .mk_list_trials_request_builder(study_name.clone())
+                .with_page_token(page_token);
+            if let Some(page_size) = page_size {
+                request = request.with_page_size(page_size);
+            }
+            let request = request.try_build();
+
+            async move {
+                let request = request?;
+                let resp = client
+                    .call_idempotent(request, |mut service, request| async move {
+                        service.list_trials(routed(request)).await
+                    })
+                    .await?
+                    .into_inner();
+                Ok((resp.trials, resp.next_page_token))
+            }
+        })
+    }
+
+    /// Lists the trials of a study in `state`, going through all the pages - see
+    /// [VizierClient::list_all_trials].
+    ///
+    /// The trials in a state unknown to this version of the crate are skipped with a
+    /// warning.
+    pub fn list_trials_by_state(
+        &self,
+        study_name: StudyName,
+        state: State,
+    ) -> impl Stream<Item = Result<Trial, Error>> {
+        trial::list::filter_by_state(self.list_all_trials(study_name, None), state)
+    }
+
+    /// Lists all the trials of a study, going through all the pages.
+    async fn collect_trials(&mut self, study_name: StudyName) -> Result<Vec<Trial>, Error> {
+        self.list_all_trials(study_name, None).try_collect().await
+    }
+
+    /// Gets a study - `None` if it does not exist.
+    pub async fn try_get_study(&mut self, study_name: StudyName) -> Result<Option<Study>, Error> {
+        let request = self.mk_get_study_request(study_name);
+        let result = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_study(routed(request)).await
+            })
+            .await;
+
+        not_found_as_none(result.map(Response::into_inner))
+    }
+
+    /// Gets the study with the given display name, creating it with `study_spec` if it
+    /// does not exist.
+    ///
+    /// If the study is created concurrently (the creation fails with
+    /// [tonic::Code::AlreadyExists]), it is looked up again. The spec of an existing
+    /// study is not compared to `study_spec`.
+    pub async fn get_or_create_study(
+        &mut self,
+        display_name: impl Into<String>,
+        study_spec: StudySpec,
+    ) -> Result<Study, Error> {
+        let display_name = display_name.into();
+        let request_timeout = self.request_timeout;
+        let lookup = || {
+            let request = self.mk_lookup_study_request(display_name.clone());
+            let mut service = self.service.clone();
+            async move {
+                timeout::call(request_timeout, service.lookup_study(routed(request)))
+                    .await
+                    .map(Response::into_inner)
+            }
+        };
+        let create = || async {
+            let request = self
+                .mk_study_request_builder()
+                .with_display_name(display_name.clone())
+                .with_study_spec(study_spec)
+                .build()?;
+
+            timeout::call(
+                request_timeout,
+                self.service.clone().create_study(routed(request)),
+            )
+            .await
+            .map(Response::into_inner)
+        };
+
+        study::lookup::get_or_create(lookup, create).await
+    }
+
+    /// Gets a trial - `None` if it does not exist.
+    pub async fn try_get_trial(&mut self, trial_name: TrialName) -> Result<Option<Trial>, Error> {
+        let request = self.mk_get_trial_request(trial_name);
+        let result = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_trial(routed(request)).await
+            })
+            .await;
+
+        not_found_as_none(result.map(Response::into_inner))
+    }
+
+    /// Gets a trial, retrying while the service reports it as not found - for a trial
+    /// that has just been created and may not be visible yet.
+    ///
+    /// The waits between the attempts follow the [RetryConfig] of the client, regardless
+    /// of its `max_retries` and retryable codes, as long as the next attempt starts
+    /// within `max_wait`. Fails with the last [tonic::Code::NotFound] [Error::Status]
+    /// afterwards, and with any other error once each attempt has gone through the
+    /// usual retries of the retryable codes.
+    pub async fn get_trial_eventually(
+        &mut self,
+        trial_name: TrialName,
+        max_wait: Duration,
+    ) -> Result<Trial, Error> {
+        let request = self.mk_get_trial_request(trial_name);
+
+        let trial = retry::retry_while(&self.retry_config, max_wait, Error::is_not_found, || {
+            let client = self.clone();
+            let request = request.clone();
+            async move {
+                client
+                    .call_idempotent(request, |mut service, request| async move {
+                        service.get_trial(routed(request)).await
                     })
                     .await
-                {
-                    Err(_) if retries > 0 => {
-                        retries -= 1;
-                        sleep(Duration::from_millis(wait_ms)).await;
-                        wait_ms *= 2;
-                    }
-                    res => break res,
+            }
+        })
+        .await?
+        .into_inner();
+
+        Ok(trial)
+    }
+
+    /// Watches a trial: gets it every `poll_interval` - the first time immediately - and
+    /// yields it each time.
+    ///
+    /// The stream ends after yielding the trial in a terminal state ([State::Succeeded],
+    /// [State::Infeasible] or [State::Stopping]), or after yielding an error.
+    pub fn watch_trial(
+        &self,
+        trial_name: TrialName,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<Trial, Error>> {
+        let client = self.clone();
+        let request = self.mk_get_trial_request(trial_name);
+
+        polling::watch(
+            poll_interval,
+            move || {
+                let client = client.clone();
+                let request = request.clone();
+                async move {
+                    client
+                        .call_idempotent(request, |mut service, request| async move {
+                            service.get_trial(routed(request)).await
+                        })
+                        .await
+                        .map(Response::into_inner)
                 }
-            }?;
+            },
+            |trial: &Trial| {
+                matches!(
+                    trial.state(),
+                    State::Succeeded | State::Infeasible | State::Stopping
+                )
+            },
+        )
+    }
 
-            operation = resp.into_inner();
+    /// Adds a measurement to a trial.
+    ///
+    /// The behavior of the service when a measurement with an already reported
+    /// `step_count` is added is not documented: it might be recorded, ignored or
+    /// rejected. [AddedMeasurement::appended] tells whether the measurement is the
+    /// last one of the trial returned by the service, so that a measurement lost to a
+    /// step collision can be detected. A repeated identical measurement is reported
+    /// as appended.
+    pub async fn add_trial_measurement(
+        &mut self,
+        trial_name: TrialName,
+        measurement: Measurement,
+    ) -> Result<AddedMeasurement, Error> {
+        let request = self.mk_add_trial_measurement_request(trial_name, measurement.clone());
+
+        let trial = timeout::call(
+            self.request_timeout,
+            self.service.add_trial_measurement(routed(request)),
+        )
+        .await?
+        .into_inner();
+        let appended = add_measurement::is_last_measurement(&trial, &measurement);
+
+        Ok(AddedMeasurement { trial, appended })
+    }
+
+    /// Adds a measurement to a trial, after checking it against the spec of the study.
+    ///
+    /// Same as [VizierClient::add_trial_measurement] but fails with [Error::Invalid],
+    /// before sending anything, if the measurement reports a metric that is not declared
+    /// in `study_spec` (see [study::validate::validate_measurement]).
+    pub async fn add_trial_measurement_checked(
+        &mut self,
+        trial_name: TrialName,
+        measurement: Measurement,
+        study_spec: &StudySpec,
+    ) -> Result<AddedMeasurement, Error> {
+        study::validate::validate_measurement(study_spec, &measurement).map_err(Error::Invalid)?;
+
+        self.add_trial_measurement(trial_name, measurement).await
+    }
+
+    /// Adds a measurement to a trial and returns the trial with its decoded [State] - to
+    /// tell whether the trial is still active or became infeasible, for instance.
+    ///
+    /// Fails with [trial::add_measurement::Error::UnknownState] if the service returns a
+    /// state unknown to this version of the crate.
+    pub async fn add_measurement_and_state(
+        &mut self,
+        trial_name: TrialName,
+        measurement: Measurement,
+    ) -> Result<(Trial, State), Error> {
+        let AddedMeasurement { trial, .. } =
+            self.add_trial_measurement(trial_name, measurement).await?;
+        let state = add_measurement::trial_state(&trial)?;
+
+        Ok((trial, state))
+    }
+
+    /// Adds a measurement to a trial, checks its early stopping state and stops it if the
+    /// service recommends it.
+    ///
+    /// The early stopping state is checked with [VizierClient::check_early_stopping],
+    /// awaited for at most `timeout` - [Error::Timeout] is returned otherwise, the
+    /// measurement being added already.
+    pub async fn report_and_maybe_stop(
+        &mut self,
+        trial_name: TrialName,
+        measurement: Measurement,
+        timeout: Duration,
+    ) -> Result<StopDecision, Error> {
+        self.add_trial_measurement(trial_name.clone(), measurement)
+            .await?;
+
+        let response = self
+            .check_early_stopping(trial_name.clone(), timeout)
+            .await?;
+
+        early_stopping::stop_if_recommended(response, || async {
+            let request = self.mk_stop_trial_request(trial_name);
+            let trial = timeout::call(
+                self.request_timeout,
+                self.service.stop_trial(routed(request)),
+            )
+            .await?
+            .into_inner();
+
+            Ok(trial)
+        })
+        .await
+    }
+
+    /// Checks whether the service recommends to stop a trial.
+    ///
+    /// The operation returned by `CheckTrialEarlyStoppingState` is awaited with
+    /// [VizierClient::wait_for_operation] for at most `timeout`, after which this fails
+    /// with [Error::Timeout]. Its result is then decoded. If the service does not
+    /// implement `WaitOperation`, the operation is polled every poll interval (see
+    /// [VizierClient::with_poll_interval]) instead.
+    pub async fn check_early_stopping(
+        &mut self,
+        trial_name: TrialName,
+        timeout: Duration,
+    ) -> Result<CheckTrialEarlyStoppingStateResponse, Error> {
+        let request = self.mk_check_trial_early_stopping_state_request(trial_name);
+        let operation = timeout::call(
+            self.request_timeout,
+            self.service
+                .check_trial_early_stopping_state(routed(request)),
+        )
+        .await?
+        .into_inner();
+
+        let operation_name = operation.name.clone();
+        let mut client = self.clone();
+        let wait = client.wait_for_operation(operation.clone(), Some(timeout));
+        let result = timeout::call(
+            Some(timeout),
+            polling::wait_or_poll(wait, || self.poll_operation(operation)),
+        )
+        .await?;
+
+        polling::decode_result(&operation_name, result)
+    }
+
+    /// Reports some of the metrics of a trial at step `step_count` - for metrics of the
+    /// same step reported by different sources (e.g. processes of a distributed
+    /// training).
+    ///
+    /// The trial is fetched, its measurements at `step_count` are merged with `metrics`
+    /// (see [trial::measurement::merge_measurements] - `on_conflict` decides what to do
+    /// with a metric reported with another value) and the merged measurement is added
+    /// to the trial.
+    ///
+    /// The fetch and the add are not atomic: if several sources report for the same step
+    /// concurrently, each of them may merge without the metrics of the others and the
+    /// last measurement added then lacks some metrics. Serialize the reporting of a given
+    /// step (e.g. through a single reporter) when this matters.
+    pub async fn report_partial_measurement(
+        &mut self,
+        trial_name: TrialName,
+        step_count: i64,
+        metrics: impl IntoMetrics,
+        on_conflict: MergeConflict,
+    ) -> Result<AddedMeasurement, Error> {
+        let request = self.mk_get_trial_request(trial_name.clone());
+        let trial = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.get_trial(routed(request)).await
+            })
+            .await?
+            .into_inner();
+
+        let mut measurements: Vec<Measurement> = trial
+            .measurements
+            .into_iter()
+            .filter(|m| m.step_count == step_count)
+            .collect();
+        measurements.push(Measurement::from_metrics(step_count, metrics));
+
+        let merged = trial::measurement::merge_measurements(&measurements, on_conflict)?
+            .pop()
+            .unwrap_or_default();
+
+        self.add_trial_measurement(trial_name, merged).await
+    }
+
+    /// Completes a trial.
+    ///
+    /// Completing a trial that has already been completed is rejected by the service with
+    /// [tonic::Code::FailedPrecondition]. In this case, the trial is fetched and, if it
+    /// is indeed in a final state, it is returned with
+    /// [CompletedTrial::already_completed] set - which makes the completion safe to
+    /// retry. Note that the final measurement of the returned trial is the one recorded
+    /// by the first completion. Otherwise, the original error is returned.
+    pub async fn complete_trial(
+        &mut self,
+        trial_name: TrialName,
+        final_measurement: FinalMeasurementOrReason,
+    ) -> Result<CompletedTrial, Error> {
+        let request = self.mk_complete_trial_request(trial_name.clone(), final_measurement);
+
+        match timeout::call(
+            self.request_timeout,
+            self.service.complete_trial(routed(request)),
+        )
+        .await
+        {
+            Ok(resp) => Ok(CompletedTrial {
+                trial: resp.into_inner(),
+                already_completed: false,
+            }),
+            Err(Error::Status(status)) if status.code() == tonic::Code::FailedPrecondition => {
+                let request = self.mk_get_trial_request(trial_name);
+                let trial = self
+                    .call_idempotent(request, |mut service, request| async move {
+                        service.get_trial(routed(request)).await
+                    })
+                    .await?
+                    .into_inner();
+
+                if complete::is_completed(&trial) {
+                    Ok(CompletedTrial {
+                        trial,
+                        already_completed: true,
+                    })
+                } else {
+                    Err(status.into())
+                }
+            }
+            Err(e) => Err(e),
         }
+    }
 
-        Ok(operation.result)
+    /// Completes several trials concurrently - see [VizierClient::complete_trial].
+    ///
+    /// At most 8 completions are in flight at once. The results are in the order of
+    /// `completions`.
+    pub async fn complete_trials(
+        &mut self,
+        completions: Vec<(TrialName, FinalMeasurementOrReason)>,
+    ) -> Vec<Result<Trial, Error>> {
+        let completions = completions
+            .into_iter()
+            .map(|(trial_name, final_measurement)| {
+                let mut client = self.clone();
+                async move {
+                    client
+                        .complete_trial(trial_name, final_measurement)
+                        .await
+                        .map(|completed| completed.trial)
+                }
+            });
+
+        batch::in_order(completions).await
     }
 
     /// Gets the [operation::Result] of an [Operation] specified by its name.
+    ///
+    /// Returns `None` while the operation is running, and fails with
+    /// [Error::OperationWithoutResult] if it is done without result.
+    ///
+    /// The failed calls are retried as configured by the [RetryConfig] of the client -
+    /// this also applies to the polling of [VizierClient::suggest_trials].
     pub async fn get_operation(
         &mut self,
         operation_name: String,
     ) -> Result<Option<operation::Result>, Error> {
-        let resp = self
-            .operation_service
-            .get_operation(GetOperationRequest {
-                name: operation_name,
-            })
-            .await?;
+        let resp = self.get_operation_raw(operation_name).await?;
 
-        let operation = resp.into_inner();
+        Ok(resp.into_inner())
+    }
 
-        if operation.done {
-            Ok(operation.result)
-        } else {
-            Ok(None)
-        }
+    /// Gets the [operation::Result] of an [Operation] specified by its name.
+    ///
+    /// Same as [VizierClient::get_operation] but keeps the response metadata (`x-goog-*`
+    /// headers, ...) returned by the server.
+    pub async fn get_operation_raw(
+        &mut self,
+        operation_name: String,
+    ) -> Result<Response<Option<operation::Result>>, Error> {
+        let operation_service = &self.operation_service;
+        let resp = timeout::call(
+            self.request_timeout,
+            retry::retry(&self.retry_config, || {
+                let mut operation_service = operation_service.clone();
+                let request = GetOperationRequest {
+                    name: operation_name.clone(),
+                };
+                async move { operation_service.get_operation(routed(request)).await }
+            }),
+        )
+        .await?;
+
+        let (metadata, operation, extensions) = resp.into_parts();
+        let result = polling::operation_result(operation)?;
+
+        Ok(Response::from_parts(metadata, result, extensions))
+    }
+
+    /// Waits for several operations to be completed.
+    ///
+    /// The polls are coalesced: the names are deduplicated and, every `poll_interval`,
+    /// the pending operations are polled once, concurrently. Returns the result of each
+    /// operation by name - an operation whose poll failed is not polled anymore and gets
+    /// the error.
+    pub async fn get_operations(
+        &self,
+        operation_names: impl IntoIterator<Item = String>,
+        poll_interval: Duration,
+    ) -> HashMap<String, Result<operation::Result, Error>> {
+        self.poll_operations(operation_names, poll_interval)
+            .collect()
+            .await
+    }
+
+    /// Waits for `operations` for at most `timeout` and yields their results as they
+    /// complete.
+    ///
+    /// The operations already done are yielded first; the others are polled together as
+    /// with [VizierClient::get_operations], every poll interval (see
+    /// [VizierClient::with_poll_interval]). Duplicate operations are awaited once and
+    /// yield a single result. An operation done without result yields
+    /// [Error::OperationWithoutResult], and an operation still running at the deadline
+    /// yields [Error::Timeout].
+    pub fn await_operations(
+        &self,
+        operations: Vec<Operation>,
+        timeout: Duration,
+    ) -> impl Stream<Item = Result<operation::Result, Error>> {
+        let client = self.clone();
+        polling::await_operations(operations, timeout, self.poll_interval, move |name| {
+            let mut client = client.clone();
+            async move { client.get_operation(name).await }
+        })
+    }
+
+    /// Polls the operations together with [VizierClient::get_operation] - see
+    /// [polling::poll_operations].
+    fn poll_operations(
+        &self,
+        operation_names: impl IntoIterator<Item = String>,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = (String, Result<operation::Result, Error>)> {
+        let client = self.clone();
+        polling::poll_operations(operation_names, poll_interval, move |name| {
+            let mut client = client.clone();
+            async move { client.get_operation(name).await }
+        })
     }
 
     /// Suggests trials to a study.
@@ -391,23 +1494,132 @@ impl VizierClient {
         &mut self,
         request: SuggestTrialsRequest,
     ) -> Result<SuggestTrialsResponse, Error> {
-        let trials = self.service.suggest_trials(request).await?;
-        let operation = trials.into_inner();
+        let resp = self.suggest_trials_raw(request).await?;
 
-        let result = loop {
-            if let Some(result) = self.get_operation(operation.name.clone()).await? {
-                break result;
-            }
-            sleep(Duration::from_millis(100)).await;
-        };
+        Ok(resp.into_inner())
+    }
+
+    /// Suggests trials to a study.
+    ///
+    /// Same as [VizierClient::suggest_trials] but keeps the response metadata returned by
+    /// the server for the `SuggestTrials` call. The metadata of the subsequent
+    /// polling of the [Operation] is not exposed.
+    pub async fn suggest_trials_raw(
+        &mut self,
+        request: SuggestTrialsRequest,
+    ) -> Result<Response<SuggestTrialsResponse>, Error> {
+        let trials = timeout::call(
+            self.request_timeout,
+            self.service.suggest_trials(routed(request)),
+        )
+        .await?;
+        let (metadata, operation, extensions) = trials.into_parts();
+
+        let result = self.poll_operation(operation).await?;
+
+        // parse the result into trials
+        let resp: SuggestTrialsResponse = util::decode_operation_result(result)?;
+
+        Ok(Response::from_parts(metadata, resp, extensions))
+    }
+
+    /// Starts suggesting trials to a study and returns the [Operation] without waiting
+    /// for it - see [VizierClient::await_suggest_trials].
+    ///
+    /// The name of the operation can be persisted to resume waiting later - with
+    /// [VizierClient::get_operation] or by passing an [Operation] with this name to
+    /// [VizierClient::await_suggest_trials].
+    pub async fn suggest_trials_operation(
+        &mut self,
+        request: SuggestTrialsRequest,
+    ) -> Result<Operation, Error> {
+        let operation = timeout::call(
+            self.request_timeout,
+            self.service.suggest_trials(routed(request)),
+        )
+        .await?
+        .into_inner();
+
+        Ok(operation)
+    }
+
+    /// Waits, for at most `timeout`, for an [Operation] returned by
+    /// [VizierClient::suggest_trials_operation] and decodes its result.
+    ///
+    /// The operation is polled every poll interval (see
+    /// [VizierClient::with_poll_interval]), unless it is already done. Fails with
+    /// [Error::Timeout] if it is not done in time.
+    pub async fn await_suggest_trials(
+        &mut self,
+        operation: Operation,
+        timeout: Duration,
+    ) -> Result<SuggestTrialsResponse, Error> {
+        let result = timeout::call(Some(timeout), self.poll_operation(operation)).await?;
+
+        Ok(util::decode_operation_result(result)?)
+    }
+
+    /// Calls `rpc` with `request` on the Vizier service, retrying as configured by the
+    /// [RetryConfig] of the client - bounded as a whole by the request timeout.
+    ///
+    /// Only for the idempotent (read-only) calls - see [RetryConfig].
+    async fn call_idempotent<Q, T, F, Fut>(&self, request: Q, rpc: F) -> Result<Response<T>, Error>
+    where
+        Q: Clone,
+        F: Fn(VizierServiceClient<AuthChannel>, Q) -> Fut,
+        Fut: Future<Output = Result<Response<T>, tonic::Status>>,
+    {
+        let service = &self.service;
+        timeout::call(
+            self.request_timeout,
+            retry::retry(&self.retry_config, || rpc(service.clone(), request.clone())),
+        )
+        .await
+    }
+
+    /// Polls `operation` until it is done and returns its result.
+    async fn poll_operation(&self, operation: Operation) -> Result<operation::Result, Error> {
+        polling::poll_operation(operation, self.poll_interval, |name| {
+            let mut client = self.clone();
+            async move { client.get_operation(name).await }
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "blocking"))]
+mod blocking {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_refuses_to_block_in_an_async_context() {
+        let result = VizierClient::new_blocking("project".to_string(), "us-central1".to_string());
 
-        // parse the result into trials
-        let resp: SuggestTrialsResponse = util::decode_operation_result_as(
-            result,
-            "type.googleapis.com/google.cloud.aiplatform.v1.SuggestTrialsResponse",
-        )?;
+        assert!(matches!(result, Err(Error::BlockingInAsyncContext)));
+    }
+}
 
-        Ok(resp)
+#[cfg(test)]
+mod errors {
+    use tonic::Status;
+
+    use super::*;
+
+    #[test]
+    fn it_maps_not_found_to_none() {
+        let found: Result<Option<i32>, Error> = not_found_as_none(Ok(1));
+        assert!(matches!(found, Ok(Some(1))));
+
+        let not_found: Result<Option<i32>, Error> =
+            not_found_as_none(Err(Status::not_found("no such study").into()));
+        assert!(matches!(not_found, Ok(None)));
+
+        let unauthenticated: Result<Option<i32>, Error> =
+            not_found_as_none(Err(Status::unauthenticated("no token").into()));
+        assert!(matches!(
+            unauthenticated,
+            Err(Error::Status(status)) if status.code() == tonic::Code::Unauthenticated
+        ));
     }
 }
 
@@ -415,15 +1627,20 @@ impl VizierClient {
 mod trials {
     use std::time::Duration;
 
+    use futures::TryStreamExt;
     use tonic::Code;
 
     use super::common::test_client;
+    use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
     use crate::google::cloud::aiplatform::v1::{
-        measurement, CheckTrialEarlyStoppingStateResponse, Measurement,
+        measurement, study_spec, trial, CheckTrialEarlyStoppingStateResponse, Measurement,
+        StudySpec,
     };
+    use crate::google::longrunning::Operation;
+    use crate::polling::decode_result;
     use crate::trial::complete::FinalMeasurementOrReason;
-    use crate::util::decode_operation_result_as;
-    use crate::SuggestTrialsResponse;
+    use crate::trial::measurement::{elapsed, MeasurementBuilder};
+    use crate::{study, Error, SuggestTrialsResponse};
 
     #[tokio::test]
     async fn it_can_get_a_trial() {
@@ -481,25 +1698,63 @@ mod trials {
 
         let resp = client.service.suggest_trials(request).await.unwrap();
         let operation = resp.into_inner();
+        let operation_name = operation.name.clone();
 
-        if let Some(result) = client
+        let result = client
             .wait_for_operation(operation, Some(Duration::from_secs(4)))
             .await
-            .unwrap()
-        {
-            // parse the result into trials
-            let resp: SuggestTrialsResponse = decode_operation_result_as(
-                result,
-                "type.googleapis.com/google.cloud.aiplatform.v1.SuggestTrialsResponse",
-            )
             .unwrap();
 
-            dbg!(&resp);
+        // parse the result into trials
+        let resp: SuggestTrialsResponse = decode_result(&operation_name, result).unwrap();
+
+        dbg!(&resp);
+
+        assert_eq!(resp.trials.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_awaits_operations_as_they_complete() {
+        let mut client = test_client().await;
+
+        let study_name = client.study_name("309382936968");
 
-            assert_eq!(resp.trials.len(), 1);
-        } else {
-            panic!("no result");
+        let mut operations = vec![];
+        for client_id in ["it_awaits_operations_1", "it_awaits_operations_2"] {
+            let request =
+                client.mk_suggest_trials_request(study_name.clone(), 1, client_id.to_string());
+            operations.push(client.suggest_trials_operation(request).await.unwrap());
         }
+
+        let results: Vec<_> = client
+            .await_operations(operations, Duration::from_secs(60))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_suggestions() {
+        let mut client = test_client().await;
+
+        let study = "309382936968".to_string();
+
+        let study_name = client.study_name(study);
+
+        let client_id = "it_can_suggest_trials".to_string();
+
+        let request = client.mk_suggest_trials_request(study_name, 1, client_id);
+
+        let operation = client.suggest_trials_operation(request).await.unwrap();
+
+        let resp = client
+            .wait_for_suggestions(operation, Some(Duration::from_secs(4)))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.trials.len(), 1);
     }
 
     #[tokio::test]
@@ -519,36 +1774,67 @@ mod trials {
         dbg!(resp);
     }
 
+    #[tokio::test]
+    async fn it_suggests_trials_and_awaits_later() {
+        let mut client = test_client().await;
+
+        let study = "309382936968".to_string();
+
+        let study_name = client.study_name(study);
+
+        let client_id = "it_can_suggest_trials".to_string();
+
+        let request = client.mk_suggest_trials_request(study_name, 1, client_id);
+
+        let operation = client.suggest_trials_operation(request).await.unwrap();
+
+        // resume from the persisted operation name
+        let operation = Operation {
+            name: operation.name,
+            ..Default::default()
+        };
+        let resp = client
+            .await_suggest_trials(operation, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.trials.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_suggests_trials_with_metadata() {
+        let mut client = test_client().await;
+
+        let study = "309382936968".to_string();
+
+        let study_name = client.study_name(study);
+
+        let client_id = "it_can_suggest_trials".to_string();
+
+        let request = client.mk_suggest_trials_request(study_name, 1, client_id);
+
+        let resp = client.suggest_trials_raw(request).await.unwrap();
+
+        dbg!(resp.metadata());
+        assert_eq!(resp.get_ref().trials.len(), 1);
+    }
+
     // FUTURE(ssoudan) add a test for create_trial
     // #[tokio::test]
     // async fn it_can_create_a_trial() {
     //     let mut client = test_client().await;
     //
     //     let study = "53316451264".to_string();
+    //     let study_name = client.study_name(study);
     //
-    //     let client_id = "it_can_create_a_trial".to_string();
-    //     let parameters = vec![
-    //         trial::Parameter {
-    //             parameter_id: "a".to_string(),
-    //             value: Some(Value {
-    //                 kind: Some(Kind::NumberValue(2.0)),
-    //             }),
-    //         },
-    //         trial::Parameter {
-    //             parameter_id: "b".to_string(),
-    //             value: Some(Value {
-    //                 kind: Some(Kind::NumberValue(9.0)),
-    //             }),
-    //         },
-    //     ];
-    //     let trial = Trial {
-    //         parameters,
-    //         client_id,
-    //         state: trial::State::Active as i32,
-    //         ..Default::default()
-    //     };
+    //     let trial = TrialBuilder::new()
+    //         .add_parameter_double("a", 2.0)
+    //         .add_parameter_double("b", 9.0)
+    //         .with_client_id("it_can_create_a_trial")
+    //         .with_state(trial::State::Active)
+    //         .build();
     //
-    //     let request = client.mk_create_trials_request(study, trial);
+    //     let request = client.mk_create_trial_request(study_name, trial);
     //
     //     let trial = client.service.create_trial(request).await.unwrap();
     //     let trial = trial.get_ref();
@@ -596,6 +1882,33 @@ mod trials {
         }
     }
 
+    #[tokio::test]
+    async fn it_lists_all_trials() {
+        use futures::TryStreamExt;
+
+        let client = test_client().await;
+
+        let study_name = client.study_name("53316451264");
+
+        let trials: Vec<_> = client
+            .list_all_trials(study_name, Some(2))
+            .try_collect()
+            .await
+            .unwrap();
+        dbg!(trials.len());
+    }
+
+    #[tokio::test]
+    async fn it_formats_results_table() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let study_name = client.study_name(study);
+
+        let table = client.format_results_table(study_name).await.unwrap();
+        println!("{}", table);
+    }
+
     #[tokio::test]
     async fn it_can_add_trial_measurement() {
         let mut client = test_client().await;
@@ -607,7 +1920,7 @@ mod trials {
         let trial_name = client.trial_name_from_study(&study_name, trial);
 
         let measurement = Measurement {
-            elapsed_duration: Some(Duration::from_secs(10).try_into().unwrap()),
+            elapsed_duration: Some(elapsed(Duration::from_secs(10))),
             step_count: 13,
             metrics: vec![measurement::Metric {
                 metric_id: "m1".to_string(),
@@ -622,6 +1935,89 @@ mod trials {
         dbg!(trial);
     }
 
+    #[tokio::test]
+    async fn it_gets_a_trial_eventually() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "1".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let trial = client
+            .get_trial_eventually(trial_name.clone(), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        assert_eq!(trial.name, String::from(trial_name));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_a_measurement_of_an_undeclared_metric() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "1".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let study_spec = StudySpec {
+            metrics: vec![study_spec::MetricSpec {
+                metric_id: "m1".to_string(),
+                goal: GoalType::Maximize as i32,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let measurement = Measurement {
+            step_count: 15,
+            metrics: vec![measurement::Metric {
+                metric_id: "m".to_string(),
+                value: 2.3,
+            }],
+            ..Default::default()
+        };
+
+        let err = client
+            .add_trial_measurement_checked(trial_name, measurement, &study_spec)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Invalid(violations)
+                if violations == vec![study::validate::Violation::UnknownMetric("m".to_string())]
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_adds_a_measurement_and_returns_the_state() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "1".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let measurement = Measurement {
+            elapsed_duration: Some(elapsed(Duration::from_secs(10))),
+            step_count: 14,
+            metrics: vec![measurement::Metric {
+                metric_id: "m1".to_string(),
+                value: 2.2,
+            }],
+        };
+
+        let (trial, state) = client
+            .add_measurement_and_state(trial_name, measurement)
+            .await
+            .unwrap();
+        assert_eq!(trial.state, state as i32);
+    }
+
     #[tokio::test]
     async fn it_can_complete_a_trial() {
         let mut client = test_client().await;
@@ -633,7 +2029,7 @@ mod trials {
         let trial_name = client.trial_name_from_study(&study_name, trial);
 
         let final_measurement_or_reason = FinalMeasurementOrReason::FinalMeasurement(Measurement {
-            elapsed_duration: Some(Duration::from_secs(100).try_into().unwrap()),
+            elapsed_duration: Some(elapsed(Duration::from_secs(100))),
             step_count: 14,
             metrics: vec![measurement::Metric {
                 metric_id: "m1".to_string(),
@@ -654,6 +2050,70 @@ mod trials {
         };
     }
 
+    #[tokio::test]
+    async fn it_completes_a_trial_idempotently() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "3".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let completed = client
+            .complete_trial(
+                trial_name,
+                FinalMeasurementOrReason::from_metrics(14, vec![("m1".to_string(), 3.1)]),
+            )
+            .await
+            .unwrap();
+
+        assert!(completed.already_completed);
+    }
+
+    #[tokio::test]
+    async fn it_watches_a_completed_trial() {
+        let client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "3".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let trials: Vec<_> = client
+            .watch_trial(trial_name, Duration::from_secs(1))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(trials.len(), 1);
+        assert_eq!(trials[0].state(), trial::State::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn it_completes_trials_concurrently() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let study_name = client.study_name(study);
+
+        let completions = ["3", "2"]
+            .into_iter()
+            .map(|trial| {
+                (
+                    client.trial_name_from_study(&study_name, trial.to_string()),
+                    FinalMeasurementOrReason::from_metrics(14, vec![("m1".to_string(), 3.1)]),
+                )
+            })
+            .collect();
+
+        let trials = client.complete_trials(completions).await;
+
+        assert_eq!(trials.len(), 2);
+        assert!(trials[0].as_ref().unwrap().name.ends_with("/trials/3"));
+    }
+
     #[tokio::test]
     async fn it_can_check_trial_early_stopping_state() {
         let mut client = test_client().await;
@@ -673,23 +2133,63 @@ mod trials {
             .unwrap();
 
         let operation = resp.into_inner();
+        let operation_name = operation.name.clone();
 
         let result = client
             .wait_for_operation(operation, Some(Duration::from_secs(4)))
             .await
             .unwrap();
 
-        if let Some(result) = result {
-            let resp: CheckTrialEarlyStoppingStateResponse = decode_operation_result_as(
-                result,
-                "type.googleapis.com/google.cloud.aiplatform.v1.CheckTrialEarlyStoppingStateResponse",
-            )
+        let resp: CheckTrialEarlyStoppingStateResponse =
+            decode_result(&operation_name, result).unwrap();
+
+        dbg!(resp);
+    }
+
+    #[tokio::test]
+    async fn it_checks_early_stopping() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "3".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let resp = client
+            .check_early_stopping(trial_name, Duration::from_secs(4))
+            .await
             .unwrap();
 
-            dbg!(resp);
-        } else {
-            panic!("No result");
-        }
+        dbg!(resp.should_stop);
+    }
+
+    #[tokio::test]
+    async fn it_reports_and_maybe_stops_a_trial() {
+        let mut client = test_client().await;
+
+        let study = "53316451264".to_string();
+        let trial = "3".to_string();
+
+        let study_name = client.study_name(study);
+        let trial_name = client.trial_name_from_study(&study_name, trial);
+
+        let measurement = MeasurementBuilder::new()
+            .with_step_count(100)
+            .add_metric("m1", 0.5)
+            .build();
+
+        match client
+            .report_and_maybe_stop(trial_name, measurement, Duration::from_secs(4))
+            .await
+        {
+            Ok(decision) => {
+                dbg!(decision);
+            }
+            Err(err) => {
+                dbg!(err);
+            }
+        };
     }
 
     #[tokio::test]
@@ -787,6 +2287,18 @@ mod studies {
         }
     }
 
+    #[tokio::test]
+    async fn it_lists_all_studies() {
+        use futures::StreamExt;
+
+        let client = test_client().await;
+
+        let mut studies = Box::pin(client.list_all_studies(Some(2)));
+        while let Some(study) = studies.next().await {
+            println!("- {}", &study.unwrap().display_name);
+        }
+    }
+
     #[tokio::test]
     async fn it_creates_studies() {
         let mut client = test_client().await;
@@ -799,6 +2311,7 @@ mod studies {
         .with_metric_specs(vec![MetricSpec {
             metric_id: "m1".to_string(), // FUTURE(ssoudan) unique and w/o whitespaces
             goal: GoalType::Maximize as i32,
+            ..Default::default()
         }])
         .with_parameters(vec![
             ParameterSpec {
@@ -824,7 +2337,8 @@ mod studies {
                 )),
             },
         ])
-        .build();
+        .build()
+        .unwrap();
 
         let request = client
             .mk_study_request_builder()
@@ -844,6 +2358,23 @@ mod studies {
         }
     }
 
+    #[tokio::test]
+    async fn it_lists_studies_in_another_location() {
+        let client = test_client().await;
+
+        let mut client = client.for_location("europe-west4").await.unwrap();
+
+        let request = client
+            .mk_list_studies_request_builder()
+            .with_page_size(2)
+            .build();
+
+        let studies = client.service.list_studies(request).await.unwrap();
+        for t in &studies.get_ref().studies {
+            assert!(t.name.contains("/locations/europe-west4/"));
+        }
+    }
+
     #[tokio::test]
     async fn it_can_get_a_study() {
         let mut client = test_client().await;
@@ -859,6 +2390,19 @@ mod studies {
         dbg!(study);
     }
 
+    #[tokio::test]
+    async fn it_opens_a_study() {
+        let mut client = test_client().await;
+
+        let study_name = client.study_name("53316451264");
+
+        let mut study = client.open_study(study_name.clone()).await.unwrap();
+        assert_eq!(study.name(), study_name);
+
+        let trials = study.list_trials().await.unwrap();
+        dbg!(trials.len());
+    }
+
     #[tokio::test]
     async fn it_finds_a_study_by_name() {
         let mut client = test_client().await;
@@ -891,6 +2435,19 @@ mod studies {
             }
         }
     }
+
+    #[tokio::test]
+    async fn it_deletes_a_missing_study_and_waits() {
+        let mut client = test_client().await;
+
+        let study = "53316451265".to_string();
+        let study_name = client.study_name(study);
+
+        client
+            .delete_study_and_wait(study_name, std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,237 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metadata attached to the requests.
+
+use tonic::metadata::{Ascii, MetadataKey, MetadataMap, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::google::cloud::aiplatform::v1::{
+    AddTrialMeasurementRequest, CheckTrialEarlyStoppingStateRequest, CompleteTrialRequest,
+    CreateStudyRequest, CreateTrialRequest, DeleteStudyRequest, DeleteTrialRequest,
+    GetStudyRequest, GetTrialRequest, ListOptimalTrialsRequest, ListStudiesRequest,
+    ListTrialsRequest, LookupStudyRequest, StopTrialRequest, SuggestTrialsRequest,
+};
+use crate::google::longrunning::{GetOperationRequest, WaitOperationRequest};
+
+/// The header carrying the routing parameters of a request.
+pub const REQUEST_PARAMS_HEADER: &str = "x-goog-request-params";
+
+/// Error returned when building a [MetadataInterceptor].
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    /// The key is not a valid ASCII metadata key.
+    #[error("invalid metadata key '{0}'")]
+    InvalidKey(String),
+    /// The value is not a valid ASCII metadata value.
+    #[error("invalid value for metadata key '{0}'")]
+    InvalidValue(String),
+}
+
+/// [Interceptor] adding a fixed set of metadata - such as `x-goog-user-project` - to
+/// every request.
+///
+/// Metadata already set on a request are left untouched.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataInterceptor {
+    metadata: MetadataMap,
+}
+
+impl MetadataInterceptor {
+    /// Creates an interceptor adding the `(key, value)` pairs of `metadata`.
+    pub fn new<K, V>(metadata: impl IntoIterator<Item = (K, V)>) -> Result<Self, Error>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut map = MetadataMap::new();
+        for (key, value) in metadata {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            let parsed_key = MetadataKey::<Ascii>::from_bytes(key.as_bytes())
+                .map_err(|_| Error::InvalidKey(key.to_string()))?;
+            let parsed_value =
+                MetadataValue::try_from(value).map_err(|_| Error::InvalidValue(key.to_string()))?;
+            map.insert(parsed_key, parsed_value);
+        }
+
+        Ok(MetadataInterceptor { metadata: map })
+    }
+}
+
+impl Interceptor for MetadataInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = request.metadata_mut();
+        for (key, value) in self.metadata.iter().filter_map(|kv| match kv {
+            tonic::metadata::KeyAndValueRef::Ascii(key, value) => Some((key, value)),
+            tonic::metadata::KeyAndValueRef::Binary(..) => None,
+        }) {
+            if !metadata.contains_key(key) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// A request message routed with the resource it targets - see [routed].
+///
+/// Implemented by all the request messages of the Vizier and operations services.
+pub trait RequestParams {
+    /// Returns the name of the field identifying the resource and its value.
+    fn request_params(&self) -> (&'static str, &str);
+}
+
+macro_rules! impl_request_params {
+    ($($request:ty => $field:ident),* $(,)?) => {
+        $(
+            impl RequestParams for $request {
+                fn request_params(&self) -> (&'static str, &str) {
+                    (stringify!($field), &self.$field)
+                }
+            }
+        )*
+    };
+}
+
+impl_request_params!(
+    CreateStudyRequest => parent,
+    GetStudyRequest => name,
+    ListStudiesRequest => parent,
+    DeleteStudyRequest => name,
+    LookupStudyRequest => parent,
+    SuggestTrialsRequest => parent,
+    CreateTrialRequest => parent,
+    GetTrialRequest => name,
+    ListTrialsRequest => parent,
+    AddTrialMeasurementRequest => trial_name,
+    CompleteTrialRequest => name,
+    DeleteTrialRequest => name,
+    CheckTrialEarlyStoppingStateRequest => trial_name,
+    StopTrialRequest => name,
+    ListOptimalTrialsRequest => parent,
+    GetOperationRequest => name,
+    WaitOperationRequest => name,
+);
+
+/// Wraps `message` in a [Request] with the [REQUEST_PARAMS_HEADER] header the service
+/// expects to route it - e.g. `parent=projects%2Fp%2Flocations%2Fus-central1`.
+///
+/// The methods of [crate::VizierClient] route their requests already; use it when calling
+/// [crate::VizierClient::service] or [crate::VizierClient::operation_service] directly,
+/// as the interceptor of the channel only sees the metadata of the requests, not the
+/// resource they target.
+pub fn routed<M: RequestParams>(message: M) -> Request<M> {
+    let (field, value) = message.request_params();
+    let params = format!("{field}={}", percent_encode(value));
+
+    let mut request = Request::new(message);
+    if let Ok(params) = MetadataValue::try_from(params) {
+        request.metadata_mut().insert(REQUEST_PARAMS_HEADER, params);
+    }
+    request
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    use futures::future::{ready, Ready};
+    use tonic::body::BoxBody;
+    use tonic::codegen::http;
+
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::vizier_service_client::VizierServiceClient;
+    use crate::study::list::RequestBuilder;
+
+    /// Captures the headers of the requests and fails them.
+    #[derive(Clone, Default)]
+    struct MockService {
+        headers: Arc<Mutex<Vec<http::HeaderMap>>>,
+    }
+
+    impl tonic::codegen::Service<http::Request<BoxBody>> for MockService {
+        type Error = std::convert::Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        type Response = http::Response<BoxBody>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+            self.headers.lock().unwrap().push(request.headers().clone());
+            ready(Ok(Status::unimplemented("mock").to_http()))
+        }
+    }
+
+    #[test]
+    fn it_percent_encodes_the_request_params() {
+        assert_eq!(
+            percent_encode("projects/p/locations/us-central1"),
+            "projects%2Fp%2Flocations%2Fus-central1"
+        );
+        assert_eq!(percent_encode("a b~c"), "a%20b~c");
+    }
+
+    #[test]
+    fn it_rejects_invalid_metadata() {
+        assert_eq!(
+            MetadataInterceptor::new([("invalid key", "v")]).unwrap_err(),
+            Error::InvalidKey("invalid key".to_string())
+        );
+        assert_eq!(
+            MetadataInterceptor::new([("x-goog-user-project", "p\n")]).unwrap_err(),
+            Error::InvalidValue("x-goog-user-project".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn it_adds_the_metadata_to_list_studies_requests() {
+        let mock = MockService::default();
+        let interceptor = MetadataInterceptor::new([
+            ("x-goog-user-project", "billing-project"),
+            (REQUEST_PARAMS_HEADER, "overridden"),
+        ])
+        .unwrap();
+        let mut service = VizierServiceClient::with_interceptor(mock.clone(), interceptor);
+
+        let request = RequestBuilder::new("p".to_string(), "us-central1".to_string()).build();
+        let status = service.list_studies(routed(request)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+
+        let headers = mock.headers.lock().unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0]["x-goog-user-project"], "billing-project");
+        assert_eq!(
+            headers[0][REQUEST_PARAMS_HEADER],
+            "parent=projects%2Fp%2Flocations%2Fus-central1"
+        );
+    }
+}
@@ -0,0 +1,878 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonical protobuf JSON representation of the studies and trials.
+//!
+//! Follows the [proto3 JSON mapping](https://protobuf.dev/programming-guides/proto3/#json):
+//! fields are named in lowerCamelCase and omitted when they have their default value,
+//! enums are rendered as their proto names, 64-bit integers as strings, and
+//! [Timestamp]s and [Duration]s as RFC 3339 strings and seconds (`"1.5s"`).
+//!
+//! Requires the `json` feature.
+//!
+//! The messages are encoded field by field: a field added to the protos has to be added
+//! to its encoder here too. The round-trip test of the module builds a [StudySpec]
+//! without `..Default::default()`, so that it stops compiling when a field is added.
+
+use prost_types::{value, Duration, Timestamp};
+use serde_json::{Map, Number, Value};
+
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::{GoalType, SafetyMetricConfig};
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::conditional_parameter_spec::ParentValueCondition;
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
+    ConditionalParameterSpec, ParameterValueSpec, ScaleType,
+};
+use crate::google::cloud::aiplatform::v1::study_spec::{
+    Algorithm, AutomatedStoppingSpec, MeasurementSelectionType, MetricSpec, ObservationNoise,
+    ParameterSpec,
+};
+use crate::google::cloud::aiplatform::v1::{measurement, study, trial, Measurement, StudySpec};
+use crate::{Study, Trial};
+
+/// A message with a canonical protobuf JSON representation.
+pub trait ProtoJson {
+    /// Returns the JSON representation of the message.
+    fn to_json_value(&self) -> Value;
+}
+
+/// Returns the canonical protobuf JSON representation of `message` - such as a [Study]
+/// or a [Trial].
+pub fn to_proto_json<M: ProtoJson>(message: &M) -> String {
+    message.to_json_value().to_string()
+}
+
+/// A JSON object omitting the fields with a default value.
+#[derive(Default)]
+struct Object(Map<String, Value>);
+
+impl Object {
+    fn field(mut self, key: &str, value: Option<Value>) -> Self {
+        if let Some(value) = value {
+            self.0.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    fn string(self, key: &str, value: &str) -> Self {
+        self.field(key, (!value.is_empty()).then(|| value.into()))
+    }
+
+    fn double(self, key: &str, value: f64) -> Self {
+        self.field(key, (value != 0.0).then(|| double(value)))
+    }
+
+    fn int64(self, key: &str, value: i64) -> Self {
+        self.field(key, (value != 0).then(|| int64(value)))
+    }
+
+    fn bool(self, key: &str, value: bool) -> Self {
+        self.field(key, value.then_some(Value::Bool(true)))
+    }
+
+    /// Adds the enum `value`, rendered as `name` - or as a number if it is unknown.
+    fn enumeration(self, key: &str, value: i32, name: Option<&'static str>) -> Self {
+        let rendered = name.map_or_else(|| value.into(), Value::from);
+        self.field(key, (value != 0).then_some(rendered))
+    }
+
+    fn message(self, key: &str, value: Option<&impl ProtoJson>) -> Self {
+        self.field(key, value.map(ProtoJson::to_json_value))
+    }
+
+    fn repeated(self, key: &str, values: impl IntoIterator<Item = Value>) -> Self {
+        let values: Vec<Value> = values.into_iter().collect();
+        self.field(key, (!values.is_empty()).then_some(Value::Array(values)))
+    }
+
+    fn build(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+/// Renders a double - as a string for the non-finite values.
+fn double(value: f64) -> Value {
+    match Number::from_f64(value) {
+        Some(n) => Value::Number(n),
+        None if value.is_nan() => "NaN".into(),
+        None if value > 0.0 => "Infinity".into(),
+        None => "-Infinity".into(),
+    }
+}
+
+/// Renders a 64-bit integer - as a string.
+fn int64(value: i64) -> Value {
+    value.to_string().into()
+}
+
+impl ProtoJson for Timestamp {
+    fn to_json_value(&self) -> Value {
+        self.to_string().into()
+    }
+}
+
+impl ProtoJson for Duration {
+    fn to_json_value(&self) -> Value {
+        self.to_string().into()
+    }
+}
+
+impl ProtoJson for prost_types::Value {
+    fn to_json_value(&self) -> Value {
+        match &self.kind {
+            None | Some(value::Kind::NullValue(_)) => Value::Null,
+            Some(value::Kind::NumberValue(n)) => double(*n),
+            Some(value::Kind::StringValue(s)) => s.as_str().into(),
+            Some(value::Kind::BoolValue(b)) => (*b).into(),
+            Some(value::Kind::StructValue(s)) => Value::Object(
+                s.fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_json_value()))
+                    .collect(),
+            ),
+            Some(value::Kind::ListValue(l)) => {
+                Value::Array(l.values.iter().map(ProtoJson::to_json_value).collect())
+            }
+        }
+    }
+}
+
+impl ProtoJson for Study {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .string("name", &self.name)
+            .string("displayName", &self.display_name)
+            .message("studySpec", self.study_spec.as_ref())
+            .enumeration(
+                "state",
+                self.state,
+                study::State::from_i32(self.state).map(|s| s.as_str_name()),
+            )
+            .message("createTime", self.create_time.as_ref())
+            .string("inactiveReason", &self.inactive_reason)
+            .build()
+    }
+}
+
+impl ProtoJson for StudySpec {
+    fn to_json_value(&self) -> Value {
+        let stopping = match &self.automated_stopping_spec {
+            None => Object::default(),
+            Some(AutomatedStoppingSpec::DecayCurveStoppingSpec(s)) => Object::default().field(
+                "decayCurveStoppingSpec",
+                Some(
+                    Object::default()
+                        .bool("useElapsedDuration", s.use_elapsed_duration)
+                        .build(),
+                ),
+            ),
+            Some(AutomatedStoppingSpec::MedianAutomatedStoppingSpec(s)) => Object::default().field(
+                "medianAutomatedStoppingSpec",
+                Some(
+                    Object::default()
+                        .bool("useElapsedDuration", s.use_elapsed_duration)
+                        .build(),
+                ),
+            ),
+            Some(AutomatedStoppingSpec::ConvexAutomatedStoppingSpec(s)) => Object::default().field(
+                "convexAutomatedStoppingSpec",
+                Some(
+                    Object::default()
+                        .int64("maxStepCount", s.max_step_count)
+                        .int64("minStepCount", s.min_step_count)
+                        .int64("minMeasurementCount", s.min_measurement_count)
+                        .string("learningRateParameterName", &s.learning_rate_parameter_name)
+                        .bool("useElapsedDuration", s.use_elapsed_duration)
+                        .build(),
+                ),
+            ),
+        };
+
+        stopping
+            .repeated("metrics", self.metrics.iter().map(ProtoJson::to_json_value))
+            .repeated(
+                "parameters",
+                self.parameters.iter().map(ProtoJson::to_json_value),
+            )
+            .enumeration(
+                "algorithm",
+                self.algorithm,
+                Algorithm::from_i32(self.algorithm).map(|a| a.as_str_name()),
+            )
+            .enumeration(
+                "observationNoise",
+                self.observation_noise,
+                ObservationNoise::from_i32(self.observation_noise).map(|o| o.as_str_name()),
+            )
+            .enumeration(
+                "measurementSelectionType",
+                self.measurement_selection_type,
+                MeasurementSelectionType::from_i32(self.measurement_selection_type)
+                    .map(|m| m.as_str_name()),
+            )
+            .build()
+    }
+}
+
+impl ProtoJson for MetricSpec {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .string("metricId", &self.metric_id)
+            .enumeration(
+                "goal",
+                self.goal,
+                GoalType::from_i32(self.goal).map(|g| g.as_str_name()),
+            )
+            .message("safetyConfig", self.safety_config.as_ref())
+            .build()
+    }
+}
+
+impl ProtoJson for SafetyMetricConfig {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .double("safetyThreshold", self.safety_threshold)
+            .field(
+                "desiredMinSafeTrialsFraction",
+                self.desired_min_safe_trials_fraction.map(double),
+            )
+            .build()
+    }
+}
+
+impl ProtoJson for ParameterSpec {
+    fn to_json_value(&self) -> Value {
+        let (key, spec) = match &self.parameter_value_spec {
+            None => ("", None),
+            Some(ParameterValueSpec::DoubleValueSpec(s)) => (
+                "doubleValueSpec",
+                Some(
+                    Object::default()
+                        .double("minValue", s.min_value)
+                        .double("maxValue", s.max_value)
+                        .field("defaultValue", s.default_value.map(double)),
+                ),
+            ),
+            Some(ParameterValueSpec::IntegerValueSpec(s)) => (
+                "integerValueSpec",
+                Some(
+                    Object::default()
+                        .int64("minValue", s.min_value)
+                        .int64("maxValue", s.max_value)
+                        .field("defaultValue", s.default_value.map(int64)),
+                ),
+            ),
+            Some(ParameterValueSpec::CategoricalValueSpec(s)) => (
+                "categoricalValueSpec",
+                Some(
+                    Object::default()
+                        .repeated("values", s.values.iter().map(|v| v.as_str().into()))
+                        .field("defaultValue", s.default_value.as_deref().map(Value::from)),
+                ),
+            ),
+            Some(ParameterValueSpec::DiscreteValueSpec(s)) => (
+                "discreteValueSpec",
+                Some(
+                    Object::default()
+                        .repeated("values", s.values.iter().copied().map(double))
+                        .field("defaultValue", s.default_value.map(double)),
+                ),
+            ),
+        };
+
+        Object::default()
+            .field(key, spec.map(Object::build))
+            .string("parameterId", &self.parameter_id)
+            .enumeration(
+                "scaleType",
+                self.scale_type,
+                ScaleType::from_i32(self.scale_type).map(|s| s.as_str_name()),
+            )
+            .repeated(
+                "conditionalParameterSpecs",
+                self.conditional_parameter_specs
+                    .iter()
+                    .map(ProtoJson::to_json_value),
+            )
+            .build()
+    }
+}
+
+impl ProtoJson for ConditionalParameterSpec {
+    fn to_json_value(&self) -> Value {
+        let (key, values) = match &self.parent_value_condition {
+            None => ("", None),
+            Some(ParentValueCondition::ParentDiscreteValues(c)) => (
+                "parentDiscreteValues",
+                Some(c.values.iter().copied().map(double).collect::<Vec<_>>()),
+            ),
+            Some(ParentValueCondition::ParentIntValues(c)) => (
+                "parentIntValues",
+                Some(c.values.iter().copied().map(int64).collect()),
+            ),
+            Some(ParentValueCondition::ParentCategoricalValues(c)) => (
+                "parentCategoricalValues",
+                Some(c.values.iter().map(|v| v.as_str().into()).collect()),
+            ),
+        };
+
+        Object::default()
+            .field(
+                key,
+                values.map(|values| Object::default().repeated("values", values).build()),
+            )
+            .message("parameterSpec", self.parameter_spec.as_ref())
+            .build()
+    }
+}
+
+impl ProtoJson for Trial {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .string("name", &self.name)
+            .string("id", &self.id)
+            .enumeration(
+                "state",
+                self.state,
+                trial::State::from_i32(self.state).map(|s| s.as_str_name()),
+            )
+            .repeated(
+                "parameters",
+                self.parameters.iter().map(ProtoJson::to_json_value),
+            )
+            .message("finalMeasurement", self.final_measurement.as_ref())
+            .repeated(
+                "measurements",
+                self.measurements.iter().map(ProtoJson::to_json_value),
+            )
+            .message("startTime", self.start_time.as_ref())
+            .message("endTime", self.end_time.as_ref())
+            .string("clientId", &self.client_id)
+            .string("infeasibleReason", &self.infeasible_reason)
+            .string("customJob", &self.custom_job)
+            .field(
+                "webAccessUris",
+                (!self.web_access_uris.is_empty()).then(|| {
+                    Value::Object(
+                        self.web_access_uris
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.as_str().into()))
+                            .collect(),
+                    )
+                }),
+            )
+            .build()
+    }
+}
+
+impl ProtoJson for trial::Parameter {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .string("parameterId", &self.parameter_id)
+            .message("value", self.value.as_ref())
+            .build()
+    }
+}
+
+impl ProtoJson for Measurement {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .message("elapsedDuration", self.elapsed_duration.as_ref())
+            .int64("stepCount", self.step_count)
+            .repeated("metrics", self.metrics.iter().map(ProtoJson::to_json_value))
+            .build()
+    }
+}
+
+impl ProtoJson for measurement::Metric {
+    fn to_json_value(&self) -> Value {
+        Object::default()
+            .string("metricId", &self.metric_id)
+            .double("value", self.value)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::conditional_parameter_spec::{
+        CategoricalValueCondition, DiscreteValueCondition, IntValueCondition,
+    };
+    use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
+        CategoricalValueSpec, DiscreteValueSpec, DoubleValueSpec, IntegerValueSpec,
+    };
+    use crate::google::cloud::aiplatform::v1::study_spec::{
+        ConvexAutomatedStoppingSpec, DecayCurveAutomatedStoppingSpec, MedianAutomatedStoppingSpec,
+    };
+    use crate::study::spec::{
+        categorical_param, double_param, integer_param, metric_spec, AutomatedStoppingSpecBuilder,
+        StudySpecBuilder,
+    };
+    use crate::trial::measurement::MeasurementBuilder;
+
+    #[test]
+    fn it_renders_studies_as_proto_json() {
+        let study_spec = StudySpecBuilder::new(
+            Algorithm::RandomSearch,
+            ObservationNoise::Unspecified,
+            MeasurementSelectionType::LastMeasurement,
+        )
+        .with_metric_specs(vec![
+            metric_spec("accuracy", GoalType::Maximize).build(),
+            metric_spec("latency", GoalType::Minimize)
+                .with_safety_threshold(0.5)
+                .build(),
+        ])
+        .with_parameters([
+            double_param("lr", 1e-4, 0.1, None, ScaleType::UnitLogScale),
+            categorical_param("optimizer", ["sgd", "adam"]).with_categorical_child(
+                ["sgd"],
+                integer_param("steps", 0, 10, Some(5), ScaleType::Unspecified),
+            ),
+        ])
+        .with_automated_stopping_spec(AutomatedStoppingSpecBuilder::median_automated().build())
+        .build()
+        .unwrap();
+        let study = Study {
+            name: "projects/p/locations/us-central1/studies/42".to_string(),
+            display_name: "study".to_string(),
+            study_spec: Some(study_spec),
+            state: study::State::Active as i32,
+            create_time: Some(Timestamp {
+                seconds: 1_000,
+                nanos: 500_000_000,
+            }),
+            inactive_reason: String::new(),
+        };
+
+        let json = to_proto_json(&study);
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).unwrap(),
+            json!({
+                "name": "projects/p/locations/us-central1/studies/42",
+                "displayName": "study",
+                "studySpec": {
+                    "metrics": [
+                        {"metricId": "accuracy", "goal": "MAXIMIZE"},
+                        {"metricId": "latency", "goal": "MINIMIZE", "safetyConfig": {"safetyThreshold": 0.5}},
+                    ],
+                    "parameters": [
+                        {
+                            "parameterId": "lr",
+                            "scaleType": "UNIT_LOG_SCALE",
+                            "doubleValueSpec": {"minValue": 0.0001, "maxValue": 0.1},
+                        },
+                        {
+                            "parameterId": "optimizer",
+                            "categoricalValueSpec": {"values": ["sgd", "adam"]},
+                            "conditionalParameterSpecs": [{
+                                "parentCategoricalValues": {"values": ["sgd"]},
+                                "parameterSpec": {
+                                    "parameterId": "steps",
+                                    "integerValueSpec": {"maxValue": "10", "defaultValue": "5"},
+                                },
+                            }],
+                        },
+                    ],
+                    "algorithm": "RANDOM_SEARCH",
+                    "measurementSelectionType": "LAST_MEASUREMENT",
+                    "medianAutomatedStoppingSpec": {},
+                },
+                "state": "ACTIVE",
+                "createTime": "1970-01-01T00:16:40.500Z",
+            })
+        );
+    }
+
+    #[test]
+    fn it_renders_trials_as_proto_json() {
+        let trial = Trial {
+            name: "projects/p/locations/us-central1/studies/42/trials/1".to_string(),
+            id: "1".to_string(),
+            state: trial::State::Succeeded as i32,
+            parameters: vec![
+                trial::Parameter {
+                    parameter_id: "lr".to_string(),
+                    value: Some(prost_types::Value {
+                        kind: Some(value::Kind::NumberValue(0.01)),
+                    }),
+                },
+                trial::Parameter {
+                    parameter_id: "optimizer".to_string(),
+                    value: Some(prost_types::Value {
+                        kind: Some(value::Kind::StringValue("adam".to_string())),
+                    }),
+                },
+            ],
+            final_measurement: Some(
+                MeasurementBuilder::new()
+                    .with_elapsed_duration(std::time::Duration::from_millis(1_500))
+                    .with_step_count(3)
+                    .add_metric("accuracy", f64::NAN)
+                    .build(),
+            ),
+            web_access_uris: HashMap::from([("workerpool0-0".to_string(), "uri".to_string())]),
+            ..Default::default()
+        };
+
+        let json: Value = serde_json::from_str(&to_proto_json(&trial)).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "name": "projects/p/locations/us-central1/studies/42/trials/1",
+                "id": "1",
+                "state": "SUCCEEDED",
+                "parameters": [
+                    {"parameterId": "lr", "value": 0.01},
+                    {"parameterId": "optimizer", "value": "adam"},
+                ],
+                "finalMeasurement": {
+                    "elapsedDuration": "1.500s",
+                    "stepCount": "3",
+                    "metrics": [{"metricId": "accuracy", "value": "NaN"}],
+                },
+                "webAccessUris": {"workerpool0-0": "uri"},
+            })
+        );
+
+        let unknown_state = Trial {
+            state: 42,
+            ..Default::default()
+        };
+        assert_eq!(to_proto_json(&unknown_state), r#"{"state":42}"#);
+    }
+
+    // Decoding of the proto3 JSON mapping - only for the round trips.
+
+    fn string(json: &Value, key: &str) -> String {
+        json.get(key)
+            .map(|v| v.as_str().unwrap().to_string())
+            .unwrap_or_default()
+    }
+
+    fn as_double(json: &Value) -> f64 {
+        match json {
+            Value::String(s) if s == "NaN" => f64::NAN,
+            Value::String(s) if s == "Infinity" => f64::INFINITY,
+            Value::String(s) if s == "-Infinity" => f64::NEG_INFINITY,
+            json => json.as_f64().unwrap(),
+        }
+    }
+
+    fn as_int64(json: &Value) -> i64 {
+        json.as_str().unwrap().parse().unwrap()
+    }
+
+    fn double(json: &Value, key: &str) -> Option<f64> {
+        json.get(key).map(as_double)
+    }
+
+    fn int64(json: &Value, key: &str) -> Option<i64> {
+        json.get(key).map(as_int64)
+    }
+
+    fn boolean(json: &Value, key: &str) -> bool {
+        json.get(key).is_some_and(|v| v.as_bool().unwrap())
+    }
+
+    fn enumeration(json: &Value, key: &str, from_name: impl Fn(&str) -> Option<i32>) -> i32 {
+        json.get(key)
+            .map_or(0, |v| from_name(v.as_str().unwrap()).unwrap())
+    }
+
+    fn repeated<'a>(json: &'a Value, key: &str) -> &'a [Value] {
+        json.get(key).map_or(&[], |v| v.as_array().unwrap())
+    }
+
+    fn decode_study_spec(json: &Value) -> StudySpec {
+        let automated_stopping_spec = if let Some(s) = json.get("decayCurveStoppingSpec") {
+            Some(AutomatedStoppingSpec::DecayCurveStoppingSpec(
+                DecayCurveAutomatedStoppingSpec {
+                    use_elapsed_duration: boolean(s, "useElapsedDuration"),
+                },
+            ))
+        } else if let Some(s) = json.get("medianAutomatedStoppingSpec") {
+            Some(AutomatedStoppingSpec::MedianAutomatedStoppingSpec(
+                MedianAutomatedStoppingSpec {
+                    use_elapsed_duration: boolean(s, "useElapsedDuration"),
+                },
+            ))
+        } else {
+            json.get("convexAutomatedStoppingSpec").map(|s| {
+                AutomatedStoppingSpec::ConvexAutomatedStoppingSpec(ConvexAutomatedStoppingSpec {
+                    max_step_count: int64(s, "maxStepCount").unwrap_or_default(),
+                    min_step_count: int64(s, "minStepCount").unwrap_or_default(),
+                    min_measurement_count: int64(s, "minMeasurementCount").unwrap_or_default(),
+                    learning_rate_parameter_name: string(s, "learningRateParameterName"),
+                    use_elapsed_duration: boolean(s, "useElapsedDuration"),
+                })
+            })
+        };
+
+        StudySpec {
+            metrics: repeated(json, "metrics")
+                .iter()
+                .map(decode_metric_spec)
+                .collect(),
+            parameters: repeated(json, "parameters")
+                .iter()
+                .map(decode_parameter_spec)
+                .collect(),
+            algorithm: enumeration(json, "algorithm", |n| {
+                Algorithm::from_str_name(n).map(|a| a as i32)
+            }),
+            observation_noise: enumeration(json, "observationNoise", |n| {
+                ObservationNoise::from_str_name(n).map(|o| o as i32)
+            }),
+            measurement_selection_type: enumeration(json, "measurementSelectionType", |n| {
+                MeasurementSelectionType::from_str_name(n).map(|m| m as i32)
+            }),
+            automated_stopping_spec,
+        }
+    }
+
+    fn decode_metric_spec(json: &Value) -> MetricSpec {
+        MetricSpec {
+            metric_id: string(json, "metricId"),
+            goal: enumeration(json, "goal", |n| {
+                GoalType::from_str_name(n).map(|g| g as i32)
+            }),
+            safety_config: json.get("safetyConfig").map(|c| SafetyMetricConfig {
+                safety_threshold: double(c, "safetyThreshold").unwrap_or_default(),
+                desired_min_safe_trials_fraction: double(c, "desiredMinSafeTrialsFraction"),
+            }),
+        }
+    }
+
+    fn decode_parameter_spec(json: &Value) -> ParameterSpec {
+        let parameter_value_spec = if let Some(s) = json.get("doubleValueSpec") {
+            Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+                min_value: double(s, "minValue").unwrap_or_default(),
+                max_value: double(s, "maxValue").unwrap_or_default(),
+                default_value: double(s, "defaultValue"),
+            }))
+        } else if let Some(s) = json.get("integerValueSpec") {
+            Some(ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+                min_value: int64(s, "minValue").unwrap_or_default(),
+                max_value: int64(s, "maxValue").unwrap_or_default(),
+                default_value: int64(s, "defaultValue"),
+            }))
+        } else if let Some(s) = json.get("categoricalValueSpec") {
+            Some(ParameterValueSpec::CategoricalValueSpec(
+                CategoricalValueSpec {
+                    values: repeated(s, "values")
+                        .iter()
+                        .map(|v| v.as_str().unwrap().to_string())
+                        .collect(),
+                    default_value: s.get("defaultValue").map(|_| string(s, "defaultValue")),
+                },
+            ))
+        } else {
+            json.get("discreteValueSpec").map(|s| {
+                ParameterValueSpec::DiscreteValueSpec(DiscreteValueSpec {
+                    values: repeated(s, "values").iter().map(as_double).collect(),
+                    default_value: double(s, "defaultValue"),
+                })
+            })
+        };
+
+        ParameterSpec {
+            parameter_id: string(json, "parameterId"),
+            scale_type: enumeration(json, "scaleType", |n| {
+                ScaleType::from_str_name(n).map(|s| s as i32)
+            }),
+            conditional_parameter_specs: repeated(json, "conditionalParameterSpecs")
+                .iter()
+                .map(decode_conditional_parameter_spec)
+                .collect(),
+            parameter_value_spec,
+        }
+    }
+
+    fn decode_conditional_parameter_spec(json: &Value) -> ConditionalParameterSpec {
+        let parent_value_condition = if let Some(c) = json.get("parentDiscreteValues") {
+            Some(ParentValueCondition::ParentDiscreteValues(
+                DiscreteValueCondition {
+                    values: repeated(c, "values").iter().map(as_double).collect(),
+                },
+            ))
+        } else if let Some(c) = json.get("parentIntValues") {
+            Some(ParentValueCondition::ParentIntValues(IntValueCondition {
+                values: repeated(c, "values").iter().map(as_int64).collect(),
+            }))
+        } else {
+            json.get("parentCategoricalValues").map(|c| {
+                ParentValueCondition::ParentCategoricalValues(CategoricalValueCondition {
+                    values: repeated(c, "values")
+                        .iter()
+                        .map(|v| v.as_str().unwrap().to_string())
+                        .collect(),
+                })
+            })
+        };
+
+        ConditionalParameterSpec {
+            parameter_spec: json.get("parameterSpec").map(decode_parameter_spec),
+            parent_value_condition,
+        }
+    }
+
+    /// A [ParameterSpec] with every field set - but the conditional specs.
+    fn parameter_spec(
+        parameter_id: &str,
+        parameter_value_spec: ParameterValueSpec,
+    ) -> ParameterSpec {
+        ParameterSpec {
+            parameter_id: parameter_id.to_string(),
+            scale_type: ScaleType::UnitReverseLogScale as i32,
+            conditional_parameter_specs: vec![],
+            parameter_value_spec: Some(parameter_value_spec),
+        }
+    }
+
+    #[test]
+    fn it_round_trips_a_fully_populated_study_spec() {
+        // No `..Default::default()` here: a field added to the protos must be set, and
+        // encoded, for the round trip to pass.
+        let mut study_spec = StudySpec {
+            metrics: vec![MetricSpec {
+                metric_id: "latency".to_string(),
+                goal: GoalType::Minimize as i32,
+                safety_config: Some(SafetyMetricConfig {
+                    safety_threshold: 0.5,
+                    desired_min_safe_trials_fraction: Some(0.9),
+                }),
+            }],
+            parameters: vec![
+                parameter_spec(
+                    "lr",
+                    ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+                        min_value: 1e-4,
+                        max_value: 0.1,
+                        default_value: Some(0.01),
+                    }),
+                ),
+                parameter_spec(
+                    "layers",
+                    ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+                        min_value: 1,
+                        max_value: 8,
+                        default_value: Some(2),
+                    }),
+                ),
+                parameter_spec(
+                    "batch",
+                    ParameterValueSpec::DiscreteValueSpec(DiscreteValueSpec {
+                        values: vec![16.0, 32.0],
+                        default_value: Some(32.0),
+                    }),
+                ),
+                ParameterSpec {
+                    conditional_parameter_specs: vec![
+                        ConditionalParameterSpec {
+                            parameter_spec: Some(parameter_spec(
+                                "momentum",
+                                ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+                                    min_value: 0.1,
+                                    max_value: 0.9,
+                                    default_value: None,
+                                }),
+                            )),
+                            parent_value_condition: Some(
+                                ParentValueCondition::ParentCategoricalValues(
+                                    CategoricalValueCondition {
+                                        values: vec!["sgd".to_string()],
+                                    },
+                                ),
+                            ),
+                        },
+                        ConditionalParameterSpec {
+                            parameter_spec: Some(parameter_spec(
+                                "decay",
+                                ParameterValueSpec::DiscreteValueSpec(DiscreteValueSpec {
+                                    values: vec![0.1, 0.2],
+                                    default_value: None,
+                                }),
+                            )),
+                            parent_value_condition: Some(
+                                ParentValueCondition::ParentDiscreteValues(
+                                    DiscreteValueCondition { values: vec![2.5] },
+                                ),
+                            ),
+                        },
+                        ConditionalParameterSpec {
+                            parameter_spec: Some(parameter_spec(
+                                "warmup",
+                                ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+                                    min_value: 0,
+                                    max_value: 10,
+                                    default_value: None,
+                                }),
+                            )),
+                            parent_value_condition: Some(ParentValueCondition::ParentIntValues(
+                                IntValueCondition { values: vec![3] },
+                            )),
+                        },
+                    ],
+                    ..parameter_spec(
+                        "optimizer",
+                        ParameterValueSpec::CategoricalValueSpec(CategoricalValueSpec {
+                            values: vec!["sgd".to_string(), "adam".to_string()],
+                            default_value: Some("adam".to_string()),
+                        }),
+                    )
+                },
+            ],
+            algorithm: Algorithm::GridSearch as i32,
+            observation_noise: ObservationNoise::High as i32,
+            measurement_selection_type: MeasurementSelectionType::BestMeasurement as i32,
+            automated_stopping_spec: Some(AutomatedStoppingSpec::ConvexAutomatedStoppingSpec(
+                ConvexAutomatedStoppingSpec {
+                    max_step_count: 100,
+                    min_step_count: 10,
+                    min_measurement_count: 5,
+                    learning_rate_parameter_name: "lr".to_string(),
+                    use_elapsed_duration: true,
+                },
+            )),
+        };
+
+        let round_trip = |study_spec: &StudySpec| {
+            let json: Value = serde_json::from_str(&to_proto_json(study_spec)).unwrap();
+            decode_study_spec(&json)
+        };
+
+        assert_eq!(round_trip(&study_spec), study_spec);
+
+        // The other stopping specs.
+        for stopping_spec in [
+            AutomatedStoppingSpec::DecayCurveStoppingSpec(DecayCurveAutomatedStoppingSpec {
+                use_elapsed_duration: true,
+            }),
+            AutomatedStoppingSpec::MedianAutomatedStoppingSpec(MedianAutomatedStoppingSpec {
+                use_elapsed_duration: true,
+            }),
+        ] {
+            study_spec.automated_stopping_spec = Some(stopping_spec);
+
+            assert_eq!(round_trip(&study_spec), study_spec);
+        }
+    }
+}
@@ -14,5 +14,12 @@
 
 //! Model for Vizier API.
 
+#[cfg(feature = "json")]
+pub mod json;
+pub mod name;
+pub mod optimize;
 pub mod study;
+pub mod time;
 pub mod trial;
+
+pub use time::{StudyTimes, TrialTimes};
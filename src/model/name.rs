@@ -0,0 +1,94 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of resource names.
+
+/// Error returned when parsing a resource name such as a
+/// [StudyName](crate::StudyName) or a [TrialName](crate::TrialName).
+///
+/// The positions are the 0-based indices of the `/`-separated segments of the name.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum NameParseError {
+    /// The name does not have the expected number of segments.
+    #[error("expected {expected} segments, found {found}")]
+    WrongSegmentCount {
+        /// The expected number of segments.
+        expected: usize,
+        /// The number of segments found.
+        found: usize,
+    },
+    /// A component of the name is empty.
+    #[error("missing {component} at segment {position}")]
+    MissingComponent {
+        /// The position of the segment.
+        position: usize,
+        /// The name of the missing component (e.g. `project`).
+        component: &'static str,
+    },
+    /// A literal segment of the name is not the expected one.
+    #[error("expected '{expected}' at segment {position}, found '{found}'")]
+    UnexpectedLiteral {
+        /// The position of the segment.
+        position: usize,
+        /// The expected literal (e.g. `studies`).
+        expected: &'static str,
+        /// The segment found.
+        found: String,
+    },
+}
+
+/// A segment of a resource name pattern.
+pub(crate) enum Segment {
+    /// A fixed segment, such as `projects`.
+    Literal(&'static str),
+    /// A variable segment, such as the project id.
+    Component(&'static str),
+}
+
+/// Parses `name` according to `pattern` and returns its components, in order.
+pub(crate) fn parse<'a>(
+    name: &'a str,
+    pattern: &[Segment],
+) -> Result<Vec<&'a str>, NameParseError> {
+    let segments: Vec<&str> = name.split('/').collect();
+    if segments.len() != pattern.len() {
+        return Err(NameParseError::WrongSegmentCount {
+            expected: pattern.len(),
+            found: segments.len(),
+        });
+    }
+
+    let mut components = vec![];
+    for (position, (segment, expected)) in segments.into_iter().zip(pattern).enumerate() {
+        match expected {
+            Segment::Literal(literal) if segment != *literal => {
+                return Err(NameParseError::UnexpectedLiteral {
+                    position,
+                    expected: literal,
+                    found: segment.to_string(),
+                })
+            }
+            Segment::Literal(_) => {}
+            Segment::Component(component) if segment.is_empty() => {
+                return Err(NameParseError::MissingComponent {
+                    position,
+                    component,
+                })
+            }
+            Segment::Component(_) => components.push(segment),
+        }
+    }
+
+    Ok(components)
+}
@@ -0,0 +1,596 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Suggest-evaluate-complete optimization loop.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{fmt, panic};
+
+use tokio::time::Instant;
+
+use crate::google::cloud::aiplatform::v1::Trial;
+use crate::metadata::routed;
+use crate::study::StudyName;
+use crate::trial::complete::FinalMeasurementOrReason;
+use crate::trial::ToTrialName;
+use crate::{timeout, Error, VizierClient};
+
+/// A phase of a round of an [Optimizer].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// The trials of the round are suggested.
+    Suggest,
+    /// A trial is evaluated.
+    Evaluate,
+    /// A trial is completed.
+    Complete,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Suggest => write!(f, "suggest"),
+            Phase::Evaluate => write!(f, "evaluate"),
+            Phase::Complete => write!(f, "complete"),
+        }
+    }
+}
+
+/// Drives the optimization of a study: trials are suggested by batches, evaluated with a
+/// closure and completed with its result.
+pub struct Optimizer<F> {
+    client: VizierClient,
+    rounds: Rounds<F>,
+}
+
+impl<F> Optimizer<F>
+where
+    F: FnMut(&Trial) -> FinalMeasurementOrReason + Send + 'static,
+{
+    /// Creates a new [Optimizer].
+    /// # Arguments
+    /// * `client` - The client used to talk to the service.
+    /// * `study_name` - The study to optimize.
+    /// * `client_id` - The client id used to suggest trials - see
+    ///   [VizierClient::default_client_id].
+    /// * `batch_size` - The number of trials suggested at each iteration.
+    /// * `evaluate` - Evaluates a trial and returns its final measurement, or the reason
+    ///   for it to be infeasible. It runs on the blocking thread pool of the runtime -
+    ///   see [tokio::task::spawn_blocking].
+    pub fn new(
+        client: VizierClient,
+        study_name: StudyName,
+        client_id: impl Into<String>,
+        batch_size: i32,
+        evaluate: F,
+    ) -> Self {
+        Optimizer {
+            client,
+            rounds: Rounds {
+                study_name,
+                client_id: client_id.into(),
+                batch_size,
+                evaluate: Arc::new(Mutex::new(evaluate)),
+                round_timeout: None,
+                budget: None,
+            },
+        }
+    }
+
+    /// Sets the maximum number of trials suggested over a [Optimizer::run] - `None` (the
+    /// default) for no limit.
+    ///
+    /// The v1 [StudySpec](crate::google::cloud::aiplatform::v1::StudySpec) has no trial
+    /// budget: it is enforced here, by asking for fewer trials in the last round and
+    /// stopping once `budget` trials have been suggested.
+    pub fn with_budget(mut self, budget: Option<usize>) -> Self {
+        self.rounds.budget = budget;
+        self
+    }
+
+    /// Sets the wall-clock timeout of each round - suggesting the trials of a batch,
+    /// evaluating and completing them. `None` (the default) to wait indefinitely.
+    ///
+    /// When a round is not done in time, the trials of the batch that were not completed
+    /// yet are stopped and [Optimizer::run] fails with [Error::RoundTimeout], naming the
+    /// [Phase] that was running. An evaluation still running at the deadline is not
+    /// waited for: it keeps running in the background and its result is discarded.
+    pub fn with_round_timeout(mut self, round_timeout: Option<Duration>) -> Self {
+        self.rounds.round_timeout = round_timeout;
+        self
+    }
+
+    /// Runs `iterations` iterations of suggesting `batch_size` trials, evaluating them
+    /// and completing them, then returns the optimal trials of the study.
+    ///
+    /// Stops early if no trial is suggested - e.g. when the search space is exhausted -
+    /// or once the budget is reached - see [Optimizer::with_budget].
+    /// The first error aborts the loop; the trials of the current batch that were not
+    /// completed yet are left active - unless the round timed out, see
+    /// [Optimizer::with_round_timeout].
+    pub async fn run(&mut self, iterations: usize) -> Result<Vec<Trial>, Error> {
+        self.rounds.run(&mut self.client, iterations).await
+    }
+}
+
+/// The calls of an [Optimizer] to the Vizier service.
+trait Service {
+    /// Suggests `suggestion_count` trials.
+    async fn suggest_trials(
+        &mut self,
+        study_name: &StudyName,
+        suggestion_count: i32,
+        client_id: &str,
+    ) -> Result<Vec<Trial>, Error>;
+
+    /// Completes `trial`.
+    async fn complete_trial(
+        &mut self,
+        trial: &Trial,
+        final_measurement: FinalMeasurementOrReason,
+    ) -> Result<(), Error>;
+
+    /// Stops `trial`.
+    async fn stop_trial(&mut self, trial: &Trial) -> Result<(), Error>;
+
+    /// Lists the optimal trials of the study.
+    async fn optimal_trials(&mut self, study_name: &StudyName) -> Result<Vec<Trial>, Error>;
+}
+
+impl Service for VizierClient {
+    async fn suggest_trials(
+        &mut self,
+        study_name: &StudyName,
+        suggestion_count: i32,
+        client_id: &str,
+    ) -> Result<Vec<Trial>, Error> {
+        let request =
+            self.mk_suggest_trials_request(study_name.clone(), suggestion_count, client_id);
+
+        Ok(VizierClient::suggest_trials(self, request).await?.trials)
+    }
+
+    async fn complete_trial(
+        &mut self,
+        trial: &Trial,
+        final_measurement: FinalMeasurementOrReason,
+    ) -> Result<(), Error> {
+        VizierClient::complete_trial(self, trial.to_trial_name(), final_measurement).await?;
+
+        Ok(())
+    }
+
+    async fn stop_trial(&mut self, trial: &Trial) -> Result<(), Error> {
+        let request = self.mk_stop_trial_request(trial.to_trial_name());
+        timeout::call(
+            self.request_timeout,
+            self.service.stop_trial(routed(request)),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn optimal_trials(&mut self, study_name: &StudyName) -> Result<Vec<Trial>, Error> {
+        let request = self.mk_list_optimal_trials_request(study_name.clone());
+        let resp = self
+            .call_idempotent(request, |mut service, request| async move {
+                service.list_optimal_trials(routed(request)).await
+            })
+            .await?;
+
+        Ok(resp.into_inner().optimal_trials)
+    }
+}
+
+/// The rounds of an [Optimizer], run against a [Service].
+struct Rounds<F> {
+    study_name: StudyName,
+    client_id: String,
+    batch_size: i32,
+    evaluate: Arc<Mutex<F>>,
+    round_timeout: Option<Duration>,
+    budget: Option<usize>,
+}
+
+impl<F> Rounds<F>
+where
+    F: FnMut(&Trial) -> FinalMeasurementOrReason + Send + 'static,
+{
+    /// See [Optimizer::run].
+    async fn run(
+        &mut self,
+        service: &mut impl Service,
+        iterations: usize,
+    ) -> Result<Vec<Trial>, Error> {
+        let mut suggested = 0;
+        for _ in 0..iterations {
+            let suggestion_count = match self.budget {
+                Some(budget) => {
+                    let remaining = budget.saturating_sub(suggested);
+                    i32::try_from(remaining).map_or(self.batch_size, |r| r.min(self.batch_size))
+                }
+                None => self.batch_size,
+            };
+            if suggestion_count <= 0 {
+                break;
+            }
+
+            let deadline = self.round_timeout.map(Deadline::from_now);
+
+            let trials = within(
+                deadline,
+                Phase::Suggest,
+                service.suggest_trials(&self.study_name, suggestion_count, &self.client_id),
+            )
+            .await?;
+            if trials.is_empty() {
+                break;
+            }
+            suggested += trials.len();
+
+            for (i, trial) in trials.iter().enumerate() {
+                let completion =
+                    match within(deadline, Phase::Evaluate, self.evaluate(trial.clone())).await {
+                        Ok(final_measurement) => {
+                            within(
+                                deadline,
+                                Phase::Complete,
+                                service.complete_trial(trial, final_measurement),
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+
+                if let Err(e) = completion {
+                    if let Error::RoundTimeout { .. } = e {
+                        stop_trials(service, &trials[i..]).await;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        service.optimal_trials(&self.study_name).await
+    }
+
+    /// Evaluates `trial` on the blocking thread pool - a panic of the evaluation is
+    /// resumed.
+    async fn evaluate(&self, trial: Trial) -> Result<FinalMeasurementOrReason, Error> {
+        let evaluate = Arc::clone(&self.evaluate);
+        let evaluation = tokio::task::spawn_blocking(move || {
+            let mut evaluate = evaluate.lock().unwrap_or_else(|e| e.into_inner());
+            (evaluate)(&trial)
+        });
+
+        match evaluation.await {
+            Ok(final_measurement) => Ok(final_measurement),
+            Err(e) => panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+/// Stops the `trials` abandoned at the end of a round - the failures are logged.
+async fn stop_trials(service: &mut impl Service, trials: &[Trial]) {
+    for trial in trials {
+        if let Err(e) = service.stop_trial(trial).await {
+            log::warn!("failed to stop trial {}: {}", trial.name, e);
+        }
+    }
+}
+
+/// The deadline of a round.
+#[derive(Clone, Copy, Debug)]
+struct Deadline {
+    timeout: Duration,
+    at: Instant,
+}
+
+impl Deadline {
+    fn from_now(timeout: Duration) -> Self {
+        Deadline {
+            timeout,
+            at: Instant::now() + timeout,
+        }
+    }
+
+    fn expired(&self, phase: Phase) -> Error {
+        Error::RoundTimeout {
+            phase,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Awaits `request` until `deadline` - indefinitely if `None` - and fails with
+/// [Error::RoundTimeout] for `phase` afterwards.
+async fn within<T>(
+    deadline: Option<Deadline>,
+    phase: Phase,
+    request: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline.at, request).await {
+            Ok(result) => result,
+            Err(_) => Err(deadline.expired(phase)),
+        },
+        None => request.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::mpsc;
+
+    use tokio::time::sleep;
+
+    use super::*;
+    use crate::trial::measurement::MeasurementBuilder;
+
+    fn trial(id: &str) -> Trial {
+        Trial {
+            name: format!("projects/p/locations/l/studies/s/trials/{id}"),
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// A [Service] suggesting predefined batches of trials.
+    #[derive(Default)]
+    struct FakeService {
+        /// The batches suggested, in order - then no trial.
+        batches: VecDeque<Vec<Trial>>,
+        /// The id of the trial whose completion fails.
+        failing_completion: Option<String>,
+        /// How long a suggestion takes.
+        suggestion_delay: Duration,
+        /// How long a completion takes.
+        completion_delay: Duration,
+        suggestion_counts: Vec<i32>,
+        completed: Vec<String>,
+        stopped: Vec<String>,
+    }
+
+    impl FakeService {
+        fn new(batches: impl IntoIterator<Item = Vec<Trial>>) -> Self {
+            FakeService {
+                batches: batches.into_iter().collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Service for FakeService {
+        async fn suggest_trials(
+            &mut self,
+            _study_name: &StudyName,
+            suggestion_count: i32,
+            _client_id: &str,
+        ) -> Result<Vec<Trial>, Error> {
+            self.suggestion_counts.push(suggestion_count);
+            sleep(self.suggestion_delay).await;
+            Ok(self.batches.pop_front().unwrap_or_default())
+        }
+
+        async fn complete_trial(
+            &mut self,
+            trial: &Trial,
+            _final_measurement: FinalMeasurementOrReason,
+        ) -> Result<(), Error> {
+            sleep(self.completion_delay).await;
+            if self.failing_completion.as_ref() == Some(&trial.id) {
+                return Err(tonic::Status::failed_precondition("already stopped").into());
+            }
+            self.completed.push(trial.id.clone());
+            Ok(())
+        }
+
+        async fn stop_trial(&mut self, trial: &Trial) -> Result<(), Error> {
+            self.stopped.push(trial.id.clone());
+            Ok(())
+        }
+
+        async fn optimal_trials(&mut self, _study_name: &StudyName) -> Result<Vec<Trial>, Error> {
+            Ok(self
+                .completed
+                .first()
+                .map(|id| trial(id))
+                .into_iter()
+                .collect())
+        }
+    }
+
+    fn rounds<F>(evaluate: F) -> Rounds<F> {
+        Rounds {
+            study_name: StudyName::new("p".to_string(), "l".to_string(), "s".to_string()),
+            client_id: "client".to_string(),
+            batch_size: 2,
+            evaluate: Arc::new(Mutex::new(evaluate)),
+            round_timeout: None,
+            budget: None,
+        }
+    }
+
+    fn measure(_: &Trial) -> FinalMeasurementOrReason {
+        FinalMeasurementOrReason::FinalMeasurement(
+            MeasurementBuilder::new().add_metric("m", 1.0).build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_completes_the_suggested_trials_and_returns_the_optimal_ones() {
+        let mut service = FakeService::new([vec![trial("1"), trial("2")], vec![trial("3")]]);
+
+        let optimal = rounds(measure).run(&mut service, 2).await.unwrap();
+
+        assert_eq!(optimal, vec![trial("1")]);
+        assert_eq!(service.suggestion_counts, [2, 2]);
+        assert_eq!(service.completed, ["1", "2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn it_stops_early_when_no_trial_is_suggested() {
+        let mut service = FakeService::new([vec![trial("1")]]);
+
+        let optimal = rounds(measure).run(&mut service, 5).await.unwrap();
+
+        assert_eq!(optimal, vec![trial("1")]);
+        // The second suggestion is empty, no third one is asked for.
+        assert_eq!(service.suggestion_counts, [2, 2]);
+        assert_eq!(service.completed, ["1"]);
+    }
+
+    #[tokio::test]
+    async fn it_stops_suggesting_once_the_budget_is_reached() {
+        let mut service = FakeService::new([
+            vec![trial("1"), trial("2")],
+            vec![trial("3"), trial("4")],
+            vec![trial("5")],
+        ]);
+        let mut rounds = rounds(measure);
+        rounds.budget = Some(5);
+
+        rounds.run(&mut service, 10).await.unwrap();
+
+        // The last round only asks for the remaining trial.
+        assert_eq!(service.suggestion_counts, [2, 2, 1]);
+        assert_eq!(service.completed, ["1", "2", "3", "4", "5"]);
+    }
+
+    #[tokio::test]
+    async fn it_propagates_completion_errors() {
+        let mut service = FakeService::new([vec![trial("1"), trial("2"), trial("3")]]);
+        service.failing_completion = Some("2".to_string());
+
+        let err = rounds(measure).run(&mut service, 3).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Status(s) if s.code() == tonic::Code::FailedPrecondition
+        ));
+        // The loop stops at the error, the remaining trial is left active.
+        assert_eq!(service.completed, ["1"]);
+        assert_eq!(service.suggestion_counts, [2]);
+        assert!(service.stopped.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_interrupts_an_evaluation_running_at_the_deadline() {
+        let mut service = FakeService::new([vec![trial("1"), trial("2"), trial("3")]]);
+
+        // The evaluation of the second trial hangs until the end of the test.
+        let (release, hung) = mpsc::channel::<()>();
+        let hung = Mutex::new(hung);
+        let evaluate = move |trial: &Trial| {
+            if trial.id == "2" {
+                let _ = hung.lock().unwrap().recv();
+            }
+            measure(trial)
+        };
+        let mut rounds = rounds(evaluate);
+        rounds.batch_size = 3;
+        rounds.round_timeout = Some(Duration::from_millis(50));
+
+        let err = rounds.run(&mut service, 1).await.unwrap_err();
+        drop(release);
+
+        assert!(matches!(
+            err,
+            Error::RoundTimeout {
+                phase: Phase::Evaluate,
+                ..
+            }
+        ));
+        assert_eq!(service.completed, ["1"]);
+        assert_eq!(service.stopped, ["2", "3"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_stops_the_trials_of_a_round_timing_out_in_completion() {
+        let mut service = FakeService::new([vec![trial("1"), trial("2"), trial("3")]]);
+        service.completion_delay = Duration::from_secs(4);
+        let mut rounds = rounds(measure);
+        rounds.batch_size = 3;
+        rounds.round_timeout = Some(Duration::from_secs(10));
+
+        let err = rounds.run(&mut service, 1).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::RoundTimeout {
+                phase: Phase::Complete,
+                ..
+            }
+        ));
+        assert_eq!(service.completed, ["1", "2"]);
+        assert_eq!(service.stopped, ["3"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_times_out_a_round_in_suggestion() {
+        let mut service = FakeService::new([vec![trial("1")]]);
+        service.suggestion_delay = Duration::from_secs(20);
+        let mut rounds = rounds(measure);
+        rounds.round_timeout = Some(Duration::from_secs(10));
+
+        let err = rounds.run(&mut service, 1).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::RoundTimeout {
+                phase: Phase::Suggest,
+                timeout
+            } if timeout == Duration::from_secs(10)
+        ));
+        assert!(service.completed.is_empty());
+        assert!(service.stopped.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_fails_the_phase_running_at_the_deadline() {
+        let round = Deadline::from_now(Duration::from_secs(10));
+        let deadline = Some(round);
+
+        let fast = async {
+            sleep(Duration::from_secs(4)).await;
+            Ok(1)
+        };
+        assert_eq!(within(deadline, Phase::Suggest, fast).await.unwrap(), 1);
+
+        let slow = async {
+            sleep(Duration::from_secs(7)).await;
+            Ok(2)
+        };
+        let err = within(deadline, Phase::Complete, slow).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RoundTimeout { phase: Phase::Complete, timeout } if timeout == Duration::from_secs(10)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "round timed out after 10s in the complete phase"
+        );
+        assert!(Instant::now() >= round.at);
+
+        let unbounded = async {
+            sleep(Duration::from_secs(3600)).await;
+            Ok(3)
+        };
+        assert_eq!(within(None, Phase::Suggest, unbounded).await.unwrap(), 3);
+    }
+}
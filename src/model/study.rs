@@ -14,19 +14,54 @@
 
 //! Study model.
 
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
 use crate::google::cloud::aiplatform::v1::Study;
+use crate::model::name::{self, NameParseError, Segment};
+use crate::model::StudyTimes;
 
 pub mod create;
 pub mod delete;
 pub mod get;
 pub mod list;
 pub mod lookup;
+pub mod results;
 pub mod spec;
+pub mod validate;
+pub mod view;
+
+impl Study {
+    /// Returns the wall-clock time elapsed since the creation of the study.
+    ///
+    /// Returns `None` if `create_time` is unset, invalid or in the future. Note that the
+    /// v1 [Study] has no `update_time`: the last activity of a study can only be inferred
+    /// from the `start_time`/`end_time` of its trials.
+    pub fn age(&self) -> Option<Duration> {
+        self.age_at(SystemTime::now())
+    }
+
+    /// Returns the wall-clock time elapsed between the creation of the study and `now` -
+    /// see [Study::age].
+    pub fn age_at(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.created_at()?).ok()
+    }
+
+    /// Returns a typed [view::StudyView] of the study.
+    pub fn view(&self) -> view::StudyView<'_> {
+        view::StudyView::new(self)
+    }
+}
 
 /// The name of a study.
-#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+///
+/// Names are ordered lexicographically on their path.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, PartialOrd, Ord)]
 pub struct StudyName(String);
 
+/// Error returned when parsing a [StudyName].
+pub type ParseStudyNameError = NameParseError;
+
 impl StudyName {
     /// Creates a new StudyName from its parts.
     pub fn new(project: String, location: String, study: String) -> Self {
@@ -35,6 +70,84 @@ impl StudyName {
             project, location, study
         ))
     }
+
+    /// Returns the segment at `position` of the name.
+    fn segment(&self, position: usize) -> &str {
+        self.0.split('/').nth(position).unwrap_or_default()
+    }
+
+    /// Returns the project - {project} in
+    /// "projects/{project}/locations/{location}/studies/{study}".
+    pub fn project(&self) -> &str {
+        self.segment(1)
+    }
+
+    /// Returns the location - {location} in
+    /// "projects/{project}/locations/{location}/studies/{study}".
+    pub fn location(&self) -> &str {
+        self.segment(3)
+    }
+
+    /// Returns the study id - {study} in
+    /// "projects/{project}/locations/{location}/studies/{study}".
+    pub fn study_id(&self) -> &str {
+        self.segment(5)
+    }
+
+    /// Parses a name of the form
+    /// "projects/{project}/locations/{location}/studies/{study}".
+    ///
+    /// Same as [FromStr] but returns [crate::Error::InvalidResourceName] on failure.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(s: &str) -> Result<Self, crate::Error> {
+        s.parse()
+            .map_err(|e| crate::Error::InvalidResourceName(format!("'{s}' - {e}")))
+    }
+}
+
+impl FromStr for StudyName {
+    type Err = NameParseError;
+
+    /// Parses a name of the form
+    /// "projects/{project}/locations/{location}/studies/{study}".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        name::parse(
+            s,
+            &[
+                Segment::Literal("projects"),
+                Segment::Component("project"),
+                Segment::Literal("locations"),
+                Segment::Component("location"),
+                Segment::Literal("studies"),
+                Segment::Component("study"),
+            ],
+        )?;
+
+        Ok(StudyName(s.to_string()))
+    }
+}
+
+impl TryFrom<&str> for StudyName {
+    type Error = ParseStudyNameError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for StudyName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StudyName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 /// Can be converted to a [StudyName].
@@ -43,9 +156,15 @@ pub trait ToStudyName {
     fn to_study_name(&self) -> StudyName;
 }
 
+impl From<&Study> for StudyName {
+    fn from(study: &Study) -> Self {
+        StudyName(study.name.clone())
+    }
+}
+
 impl ToStudyName for Study {
     fn to_study_name(&self) -> StudyName {
-        StudyName(self.name.clone())
+        self.into()
     }
 }
 
@@ -60,3 +179,138 @@ impl From<&StudyName> for String {
         study_name.0.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn it_parses_study_names() {
+        let name = "projects/p/locations/us-central1/studies/42";
+        assert_eq!(
+            name.parse::<StudyName>().map(String::from),
+            Ok(name.to_string())
+        );
+
+        assert_eq!(
+            "projects/p/locations/us-central1".parse::<StudyName>(),
+            Err(NameParseError::WrongSegmentCount {
+                expected: 6,
+                found: 4
+            })
+        );
+        assert_eq!(
+            "projects//locations/us-central1/studies/42".parse::<StudyName>(),
+            Err(NameParseError::MissingComponent {
+                position: 1,
+                component: "project"
+            })
+        );
+        assert_eq!(
+            "projects/p/locations/us-central1/trials/42".parse::<StudyName>(),
+            Err(NameParseError::UnexpectedLiteral {
+                position: 4,
+                expected: "studies",
+                found: "trials".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_study_names_into_the_crate_error() {
+        let name = "projects/p/locations/us-central1/studies/42";
+        assert_eq!(StudyName::parse(name).unwrap(), name.parse().unwrap());
+
+        let err = StudyName::parse("projects/p/locations/us-central1").unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                crate::Error::InvalidResourceName(msg)
+                    if msg == "'projects/p/locations/us-central1' - expected 6 segments, found 4"
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn it_hashes_and_orders_study_names() {
+        let name = |s: &str| StudyName::new("p".to_string(), "us-central1".to_string(), s.into());
+
+        let mut cache = HashMap::new();
+        cache.insert(name("42"), "first");
+        cache.insert(name("42"), "second");
+        cache.insert(name("7"), "other");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache[&name("42")], "second");
+
+        let mut names = vec![name("7"), name("42"), name("100")];
+        names.sort();
+        assert_eq!(names, [name("100"), name("42"), name("7")]);
+    }
+
+    #[test]
+    fn it_exposes_the_components_of_study_names() {
+        let study_name: StudyName = "projects/foo/locations/us-central1/studies/42"
+            .parse()
+            .unwrap();
+
+        assert_eq!(study_name.project(), "foo");
+        assert_eq!(study_name.location(), "us-central1");
+        assert_eq!(study_name.study_id(), "42");
+        assert_eq!(
+            StudyName::try_from("projects/foo/locations/us-central1/studies/42"),
+            Ok(study_name)
+        );
+
+        assert!(
+            StudyName::try_from("projects/foo/locations/us-central1/studies/42/trials/1").is_err()
+        );
+    }
+
+    #[test]
+    fn it_converts_studies_to_names() {
+        let study = Study {
+            name: "projects/p/locations/us-central1/studies/42".to_string(),
+            ..Default::default()
+        };
+
+        let name: StudyName = (&study).into();
+        assert_eq!(name, study.to_study_name());
+        assert_eq!(StudyName::from(&study), study.name.parse().unwrap());
+    }
+
+    #[test]
+    fn it_computes_the_age_of_a_study() {
+        let create_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let study = Study {
+            create_time: Some(create_time.into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            study.age_at(create_time + Duration::from_secs(90)),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(study.age_at(SystemTime::UNIX_EPOCH), None);
+        assert_eq!(Study::default().age(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_study_names() {
+        let name: StudyName = "projects/p/locations/us-central1/studies/42"
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(json, r#""projects/p/locations/us-central1/studies/42""#);
+        assert_eq!(serde_json::from_str::<StudyName>(&json).unwrap(), name);
+
+        let err =
+            serde_json::from_str::<StudyName>(r#""projects/p/locations/us-central1/trials/42""#)
+                .unwrap_err();
+        assert!(err.to_string().contains("expected"), "{err}");
+    }
+}
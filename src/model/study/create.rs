@@ -17,6 +17,7 @@
 use regex::Regex;
 
 use crate::google::cloud::aiplatform::v1::{CreateStudyRequest, Study, StudySpec};
+use crate::study::validate::Violation;
 
 /// Error returned by [RequestBuilder].
 #[derive(thiserror::Error, Debug)]
@@ -33,9 +34,16 @@ pub enum Error {
     /// Study_spec is missing or Display_name is missing
     #[error("study_spec and display_name is required")]
     StudySpecAndDisplayNameRequired,
+    /// Study_spec is invalid - see [StudySpec::validate].
+    #[error("invalid study_spec - {0:?}")]
+    InvalidStudySpec(Vec<Violation>),
 }
 
 /// [CreateStudyRequest] builder.
+///
+/// The display name and the spec are the only fields of the v1 [Study] that can be set
+/// at creation: the others (name, state, create time and inactive reason) are output
+/// only, and the v1 API has no labels or metadata to tag studies with.
 pub struct RequestBuilder {
     project: String,
     location: String,
@@ -45,18 +53,18 @@ pub struct RequestBuilder {
 
 impl RequestBuilder {
     /// Create a new [RequestBuilder] for the given project and location.
-    pub fn new(project: String, location: String) -> Self {
+    pub fn new(project: impl Into<String>, location: impl Into<String>) -> Self {
         Self {
-            project,
-            location,
+            project: project.into(),
+            location: location.into(),
             display_name: None,
             study_spec: None,
         }
     }
 
     /// Set the display name - required.
-    pub fn with_display_name(mut self, display_name: String) -> Self {
-        self.display_name = Some(display_name);
+    pub fn with_display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
         self
     }
 
@@ -67,6 +75,9 @@ impl RequestBuilder {
     }
 
     /// Builds the [CreateStudyRequest].
+    ///
+    /// The study spec is validated locally, so an invalid spec fails here rather than
+    /// with an error from the service.
     pub fn build(self) -> Result<CreateStudyRequest, Error> {
         match (self.display_name, self.study_spec) {
             (Some(display_name), Some(study_spec)) => {
@@ -75,6 +86,8 @@ impl RequestBuilder {
                     return Err(Error::InvalidDisplayName);
                 }
 
+                study_spec.validate().map_err(Error::InvalidStudySpec)?;
+
                 Ok(CreateStudyRequest {
                     parent: format!(
                         "projects/{project}/locations/{location}",
@@ -95,3 +108,90 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+    use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::ScaleType;
+    use crate::google::cloud::aiplatform::v1::study_spec::MetricSpec;
+    use crate::study::spec::integer_param;
+
+    fn study_spec() -> StudySpec {
+        StudySpec {
+            metrics: vec![MetricSpec {
+                metric_id: "m1".to_string(),
+                goal: GoalType::Maximize as i32,
+                ..Default::default()
+            }],
+            parameters: vec![integer_param("a", 0, 3, None, ScaleType::Unspecified)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_accepts_str_and_string() {
+        assert_eq!(
+            RequestBuilder::new("p", "l")
+                .with_display_name("study")
+                .with_study_spec(study_spec())
+                .build()
+                .unwrap(),
+            RequestBuilder::new("p".to_string(), "l".to_string())
+                .with_display_name("study".to_string())
+                .with_study_spec(study_spec())
+                .build()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn it_sets_the_study_of_the_request() {
+        let request = RequestBuilder::new("p", "l")
+            .with_display_name("study")
+            .with_study_spec(study_spec())
+            .build()
+            .unwrap();
+
+        assert_eq!(request.parent, "projects/p/locations/l");
+        assert_eq!(
+            request.study,
+            Some(Study {
+                display_name: "study".to_string(),
+                study_spec: Some(study_spec()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn it_rejects_an_invalid_study_spec() {
+        let mut study_spec = study_spec();
+        study_spec.metrics.push(study_spec.metrics[0].clone());
+        study_spec.parameters[0].parameter_id = "a b".to_string();
+
+        let err = RequestBuilder::new("p", "l")
+            .with_display_name("study")
+            .with_study_spec(study_spec)
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::InvalidStudySpec(violations) if violations == vec![
+                Violation::DuplicateMetricId("m1".to_string()),
+                Violation::InvalidParameterId("a b".to_string()),
+            ]
+        ));
+
+        let err = RequestBuilder::new("p", "l")
+            .with_display_name("study")
+            .with_study_spec(StudySpec::default())
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidStudySpec(violations) if violations.contains(&Violation::NoMetric)
+        ));
+    }
+}
@@ -14,7 +14,52 @@
 
 //! Study list request builder.
 
-use crate::google::cloud::aiplatform::v1::ListStudiesRequest;
+use std::cmp::Ordering;
+
+use futures::{Stream, TryStreamExt};
+
+use crate::google::cloud::aiplatform::v1::{ListStudiesRequest, Study};
+
+/// Order of listed studies.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StudyOrder {
+    /// By display name.
+    DisplayName,
+    /// By creation time - the studies without one first.
+    CreateTime,
+}
+
+impl StudyOrder {
+    /// Compares two studies according to this order.
+    pub fn compare(self, a: &Study, b: &Study) -> Ordering {
+        match self {
+            StudyOrder::DisplayName => a.display_name.cmp(&b.display_name),
+            StudyOrder::CreateTime => {
+                let key = |s: &Study| s.create_time.as_ref().map(|t| (t.seconds, t.nanos));
+                key(a).cmp(&key(b))
+            }
+        }
+    }
+}
+
+/// Keeps the studies of `studies` for which `predicate` returns true.
+pub(crate) fn filter_studies<E>(
+    studies: impl Stream<Item = Result<Study, E>>,
+    predicate: impl Fn(&Study) -> bool,
+) -> impl Stream<Item = Result<Study, E>> {
+    studies.try_filter(move |study| futures::future::ready(predicate(study)))
+}
+
+/// Maximum number of studies in a page.
+pub const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Error returned by [RequestBuilder::try_build].
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The page size is negative or above [MAX_PAGE_SIZE].
+    #[error("invalid page_size {0} - must be between 0 and {MAX_PAGE_SIZE}")]
+    InvalidPageSize(i32),
+}
 
 /// [ListStudiesRequest] builder.
 pub struct RequestBuilder {
@@ -26,16 +71,16 @@ pub struct RequestBuilder {
 
 impl RequestBuilder {
     /// Creates a new instance of [ListStudiesRequest] builder.
-    pub fn new(project: String, location: String) -> Self {
+    pub fn new(project: impl Into<String>, location: impl Into<String>) -> Self {
         RequestBuilder {
-            project,
-            location,
+            project: project.into(),
+            location: location.into(),
             page_size: None,
             page_token: None,
         }
     }
 
-    /// Sets the page size.
+    /// Sets the page size - 0 for the default of the service, at most [MAX_PAGE_SIZE].
     pub fn with_page_size(mut self, page_size: i32) -> Self {
         self.page_size = Some(page_size);
         self
@@ -43,12 +88,26 @@ impl RequestBuilder {
 
     /// Sets the page token to get a following page - See
     /// [`next_page_token`](ListStudiesResponse.next_page_token).
-    pub fn with_page_token(mut self, page_token: String) -> Self {
-        self.page_token = Some(page_token);
+    pub fn with_page_token(mut self, page_token: impl Into<String>) -> Self {
+        self.page_token = Some(page_token.into());
         self
     }
 
-    /// Builds the [ListStudiesRequest].
+    /// Builds the [ListStudiesRequest], checking the page size.
+    ///
+    /// Fails with [Error::InvalidPageSize] if the page size is negative or above
+    /// [MAX_PAGE_SIZE].
+    pub fn try_build(self) -> Result<ListStudiesRequest, Error> {
+        match self.page_size {
+            Some(page_size) if !(0..=MAX_PAGE_SIZE).contains(&page_size) => {
+                Err(Error::InvalidPageSize(page_size))
+            }
+            _ => Ok(self.build()),
+        }
+    }
+
+    /// Builds the [ListStudiesRequest] - the page size is not checked, see
+    /// [RequestBuilder::try_build].
     pub fn build(self) -> ListStudiesRequest {
         ListStudiesRequest {
             parent: format!(
@@ -61,3 +120,95 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Timestamp;
+
+    use super::*;
+    use crate::paging::paginate;
+
+    fn study(display_name: &str, create_time: Option<i64>) -> Study {
+        Study {
+            display_name: display_name.to_string(),
+            create_time: create_time.map(|seconds| Timestamp { seconds, nanos: 0 }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_accepts_str_and_string() {
+        assert_eq!(
+            RequestBuilder::new("p", "l").with_page_token("t").build(),
+            RequestBuilder::new("p".to_string(), "l".to_string())
+                .with_page_token("t".to_string())
+                .build()
+        );
+    }
+
+    #[test]
+    fn it_checks_the_page_size() {
+        let builder = || RequestBuilder::new("p", "l");
+
+        assert!(matches!(
+            builder().with_page_size(-1).try_build(),
+            Err(Error::InvalidPageSize(-1))
+        ));
+        assert_eq!(
+            builder().with_page_size(0).try_build().unwrap().page_size,
+            0
+        );
+        assert_eq!(builder().try_build().unwrap().page_size, 0);
+        assert_eq!(
+            builder().with_page_size(50).try_build().unwrap().page_size,
+            50
+        );
+        assert_eq!(
+            builder()
+                .with_page_size(MAX_PAGE_SIZE)
+                .try_build()
+                .unwrap()
+                .page_size,
+            MAX_PAGE_SIZE
+        );
+        assert!(matches!(
+            builder().with_page_size(1001).try_build(),
+            Err(Error::InvalidPageSize(1001))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_filters_all_the_pages() {
+        let pages = paginate(|page_token| async move {
+            Ok::<_, crate::Error>(match page_token.as_str() {
+                "" => (
+                    vec![study("keep_a", None), study("drop_b", None)],
+                    "1".to_string(),
+                ),
+                "1" => (vec![study("drop_c", None)], "2".to_string()),
+                _ => (vec![study("keep_d", None)], String::new()),
+            })
+        });
+
+        let studies: Vec<Study> = filter_studies(pages, |s| s.display_name.starts_with("keep"))
+            .try_collect()
+            .await
+            .unwrap();
+
+        let names: Vec<&str> = studies.iter().map(|s| s.display_name.as_str()).collect();
+        assert_eq!(names, vec!["keep_a", "keep_d"]);
+    }
+
+    #[test]
+    fn it_orders_studies() {
+        let mut studies = [study("b", Some(1)), study("c", None), study("a", Some(2))];
+
+        studies.sort_by(|a, b| StudyOrder::DisplayName.compare(a, b));
+        let names: Vec<&str> = studies.iter().map(|s| s.display_name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        studies.sort_by(|a, b| StudyOrder::CreateTime.compare(a, b));
+        let names: Vec<&str> = studies.iter().map(|s| s.display_name.as_str()).collect();
+        assert_eq!(names, vec!["c", "b", "a"]);
+    }
+}
@@ -14,7 +14,10 @@
 
 //! Study lookup request builder.
 
-use crate::google::cloud::aiplatform::v1::LookupStudyRequest;
+use std::future::Future;
+
+use crate::google::cloud::aiplatform::v1::{LookupStudyRequest, Study};
+use crate::Error;
 
 /// [LookupStudyRequest] builder.
 pub struct RequestBuilder {
@@ -25,11 +28,15 @@ pub struct RequestBuilder {
 
 impl RequestBuilder {
     /// Creates a new instance of [LookupStudyRequest] builder.
-    pub fn new(project: String, location: String, display_name: String) -> Self {
+    pub fn new(
+        project: impl Into<String>,
+        location: impl Into<String>,
+        display_name: impl Into<String>,
+    ) -> Self {
         RequestBuilder {
-            project,
-            location,
-            display_name,
+            project: project.into(),
+            location: location.into(),
+            display_name: display_name.into(),
         }
     }
 
@@ -45,3 +52,107 @@ impl RequestBuilder {
         }
     }
 }
+
+/// Looks a study up with `lookup`, creating it with `create` if it is not found.
+///
+/// If the creation fails with [tonic::Code::AlreadyExists] - i.e. the study has been
+/// created concurrently by someone else - the study is looked up again.
+pub(crate) async fn get_or_create<L, LFut, C, CFut>(
+    mut lookup: L,
+    create: C,
+) -> Result<Study, Error>
+where
+    L: FnMut() -> LFut,
+    LFut: Future<Output = Result<Study, Error>>,
+    C: FnOnce() -> CFut,
+    CFut: Future<Output = Result<Study, Error>>,
+{
+    match lookup().await {
+        Err(e) if e.is_not_found() => match create().await {
+            Err(Error::Status(status)) if status.code() == tonic::Code::AlreadyExists => {
+                lookup().await
+            }
+            result => result,
+        },
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tonic::Status;
+
+    use super::*;
+
+    fn study(name: &str) -> Study {
+        Study {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_gets_an_existing_study() {
+        let study = get_or_create(
+            || async { Ok(study("found")) },
+            || async { panic!("should not create") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(study.name, "found");
+    }
+
+    #[tokio::test]
+    async fn it_creates_a_missing_study() {
+        let study = get_or_create(
+            || async { Err(Status::not_found("no such study").into()) },
+            || async { Ok(study("created")) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(study.name, "created");
+
+        let err = get_or_create(
+            || async { Err(Status::permission_denied("denied").into()) },
+            || async { panic!("should not create") },
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::Status(s) if s.code() == tonic::Code::PermissionDenied));
+    }
+
+    #[tokio::test]
+    async fn it_looks_up_again_a_study_created_concurrently() {
+        let lookups = AtomicUsize::new(0);
+
+        let study = get_or_create(
+            || {
+                let attempt = lookups.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match attempt {
+                        0 => Err(Status::not_found("no such study").into()),
+                        _ => Ok(study("created concurrently")),
+                    }
+                }
+            },
+            || async { Err(Status::already_exists("study exists").into()) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(study.name, "created concurrently");
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn it_accepts_str_and_string() {
+        assert_eq!(
+            RequestBuilder::new("p", "l", "study").build(),
+            RequestBuilder::new("p".to_string(), "l".to_string(), "study".to_string()).build()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human-readable table of the results of a study.
+
+use prost_types::value::Kind;
+
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+use crate::google::cloud::aiplatform::v1::trial::State;
+use crate::google::cloud::aiplatform::v1::StudySpec;
+use crate::trial::analysis::final_metric_value;
+use crate::Trial;
+
+fn parameter_cell(trial: &Trial, parameter_id: &str) -> String {
+    let kind = trial
+        .parameters
+        .iter()
+        .find(|p| p.parameter_id == parameter_id)
+        .and_then(|p| p.value.as_ref())
+        .and_then(|v| v.kind.as_ref());
+
+    match kind {
+        Some(Kind::NumberValue(v)) => v.to_string(),
+        Some(Kind::StringValue(v)) => v.clone(),
+        Some(Kind::BoolValue(v)) => v.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Formats the trials of a study as an aligned table.
+///
+/// There is one row per trial with its id, its state, its parameters and the metrics of
+/// its final measurement, in the order of the [StudySpec]. The rows are sorted by the
+/// first metric of the spec (the primary objective), best first; the trials without this
+/// metric come last.
+pub fn format_results_table(study_spec: &StudySpec, trials: &[Trial]) -> String {
+    let mut header = vec!["trial".to_string(), "state".to_string()];
+    header.extend(study_spec.parameters.iter().map(|p| p.parameter_id.clone()));
+    header.extend(study_spec.metrics.iter().map(|m| m.metric_id.clone()));
+
+    let mut trials: Vec<&Trial> = trials.iter().collect();
+    if let Some(objective) = study_spec.metrics.first() {
+        let minimize = objective.goal == GoalType::Minimize as i32;
+        trials.sort_by(|a, b| {
+            let a = final_metric_value(a, &objective.metric_id);
+            let b = final_metric_value(b, &objective.metric_id);
+            match (a, b) {
+                (Some(a), Some(b)) if minimize => a.total_cmp(&b),
+                (Some(a), Some(b)) => b.total_cmp(&a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            }
+        });
+    }
+
+    let rows: Vec<Vec<String>> = trials
+        .iter()
+        .map(|t| {
+            let state = State::from_i32(t.state).unwrap_or(State::Unspecified);
+            let mut row = vec![t.id.clone(), state.as_str_name().to_string()];
+            row.extend(
+                study_spec
+                    .parameters
+                    .iter()
+                    .map(|p| parameter_cell(t, &p.parameter_id)),
+            );
+            row.extend(study_spec.metrics.iter().map(|m| {
+                final_metric_value(t, &m.metric_id)
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+            }));
+            row
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain(std::iter::once(header[i].chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        cells.join("  ").trim_end().to_string()
+    };
+
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+
+    let mut lines = vec![format_row(&header), format_row(&separator)];
+    lines.extend(rows.iter().map(|r| format_row(r)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Value;
+
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::study_spec::{MetricSpec, ParameterSpec};
+    use crate::google::cloud::aiplatform::v1::{measurement, trial, Measurement};
+
+    fn trial(id: &str, x: f64, m: Option<f64>) -> Trial {
+        Trial {
+            id: id.to_string(),
+            state: State::Succeeded as i32,
+            parameters: vec![trial::Parameter {
+                parameter_id: "x".to_string(),
+                value: Some(Value {
+                    kind: Some(Kind::NumberValue(x)),
+                }),
+            }],
+            final_measurement: m.map(|value| Measurement {
+                metrics: vec![measurement::Metric {
+                    metric_id: "m".to_string(),
+                    value,
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_aligns_the_columns_on_the_characters() {
+        let study_spec = StudySpec {
+            metrics: vec![MetricSpec {
+                metric_id: "m".to_string(),
+                ..Default::default()
+            }],
+            parameters: vec![ParameterSpec {
+                parameter_id: "ε".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut trial = trial("1", 0.5, Some(2.0));
+        trial.parameters[0].parameter_id = "ε".to_string();
+        trial.parameters[0].value = Some(Value {
+            kind: Some(Kind::StringValue("über".to_string())),
+        });
+
+        let table = format_results_table(&study_spec, &[trial]);
+
+        assert_eq!(
+            table,
+            "trial  state      ε     m\n\
+             -----  ---------  ----  -\n\
+             1      SUCCEEDED  über  2"
+        );
+    }
+
+    #[test]
+    fn it_formats_a_sorted_table() {
+        let study_spec = StudySpec {
+            metrics: vec![MetricSpec {
+                metric_id: "m".to_string(),
+                goal: GoalType::Minimize as i32,
+                ..Default::default()
+            }],
+            parameters: vec![ParameterSpec {
+                parameter_id: "x".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let trials = vec![
+            trial("1", 0.5, Some(3.0)),
+            trial("2", 1.5, None),
+            trial("3", 10.0, Some(1.25)),
+        ];
+
+        let table = format_results_table(&study_spec, &trials);
+
+        assert_eq!(
+            table,
+            "trial  state      x    m\n\
+             -----  ---------  ---  ----\n\
+             3      SUCCEEDED  10   1.25\n\
+             1      SUCCEEDED  0.5  3\n\
+             2      SUCCEEDED  1.5"
+        );
+    }
+}
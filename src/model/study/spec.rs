@@ -14,13 +14,380 @@
 
 //! StudySpec builder.
 
+use std::ops::RangeInclusive;
+
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::conditional_parameter_spec::{
+    CategoricalValueCondition, DiscreteValueCondition, IntValueCondition, ParentValueCondition,
+};
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
+    CategoricalValueSpec, ConditionalParameterSpec, DiscreteValueSpec, DoubleValueSpec,
+    IntegerValueSpec, ParameterValueSpec, ScaleType,
+};
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::{
+    GoalType, SafetyMetricConfig,
+};
 use crate::google::cloud::aiplatform::v1::study_spec::{
-    Algorithm, AutomatedStoppingSpec, MeasurementSelectionType, MetricSpec, ObservationNoise,
-    ParameterSpec,
+    Algorithm, AutomatedStoppingSpec, DecayCurveAutomatedStoppingSpec, MeasurementSelectionType,
+    MedianAutomatedStoppingSpec, MetricSpec, ObservationNoise, ParameterSpec,
 };
 use crate::google::cloud::aiplatform::v1::StudySpec;
 
+/// Error returned by [StudySpecBuilder].
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Grid search only supports parameters with a finite set of values.
+    #[error("grid search does not support continuous parameter '{0}'")]
+    ContinuousParameterWithGridSearch(String),
+    /// The range of a parameter is empty.
+    #[error("empty range for parameter '{0}'")]
+    EmptyRange(String),
+}
+
+/// Automated stopping strategy configured for a study.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StoppingStrategy {
+    /// Decay curve automated stopping.
+    DecayCurve,
+    /// Median automated stopping.
+    Median,
+    /// Convex automated stopping.
+    Convex,
+}
+
+impl StudySpec {
+    /// Returns the automated stopping strategy configured for the study, if any.
+    pub fn stopping_strategy(&self) -> Option<StoppingStrategy> {
+        self.automated_stopping_spec.as_ref().map(|s| match s {
+            AutomatedStoppingSpec::DecayCurveStoppingSpec(_) => StoppingStrategy::DecayCurve,
+            AutomatedStoppingSpec::MedianAutomatedStoppingSpec(_) => StoppingStrategy::Median,
+            AutomatedStoppingSpec::ConvexAutomatedStoppingSpec(_) => StoppingStrategy::Convex,
+        })
+    }
+}
+
+/// Creates a [ParameterSpec] for a double parameter taking values in `[min, max]`,
+/// explored with `scale_type` - see also [ParameterSpec::log] and co.
+pub fn double_param(
+    parameter_id: impl Into<String>,
+    min_value: f64,
+    max_value: f64,
+    default_value: Option<f64>,
+    scale_type: ScaleType,
+) -> ParameterSpec {
+    ParameterSpec {
+        parameter_id: parameter_id.into(),
+        scale_type: scale_type as i32,
+        parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+            min_value,
+            max_value,
+            default_value,
+        })),
+        ..Default::default()
+    }
+}
+
+/// Creates a [ParameterSpec] for an integer parameter taking values in `[min, max]`,
+/// explored with `scale_type` - see also [ParameterSpec::log] and co.
+pub fn integer_param(
+    parameter_id: impl Into<String>,
+    min_value: i64,
+    max_value: i64,
+    default_value: Option<i64>,
+    scale_type: ScaleType,
+) -> ParameterSpec {
+    ParameterSpec {
+        parameter_id: parameter_id.into(),
+        scale_type: scale_type as i32,
+        parameter_value_spec: Some(ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+            min_value,
+            max_value,
+            default_value,
+        })),
+        ..Default::default()
+    }
+}
+
+/// Creates a [ParameterSpec] for a categorical parameter taking one of `values`.
+pub fn categorical_param(
+    parameter_id: impl Into<String>,
+    values: impl IntoIterator<Item = impl Into<String>>,
+) -> ParameterSpec {
+    ParameterSpec {
+        parameter_id: parameter_id.into(),
+        parameter_value_spec: Some(ParameterValueSpec::CategoricalValueSpec(
+            CategoricalValueSpec {
+                values: values.into_iter().map(Into::into).collect(),
+                default_value: None,
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// Creates a [ParameterSpec] for a discrete parameter taking one of `values`.
+///
+/// The values are sorted in increasing order, as expected by the service.
+pub fn discrete_param(
+    parameter_id: impl Into<String>,
+    values: impl IntoIterator<Item = f64>,
+) -> ParameterSpec {
+    let mut values: Vec<f64> = values.into_iter().collect();
+    values.sort_by(f64::total_cmp);
+
+    ParameterSpec {
+        parameter_id: parameter_id.into(),
+        parameter_value_spec: Some(ParameterValueSpec::DiscreteValueSpec(DiscreteValueSpec {
+            values,
+            default_value: None,
+        })),
+        ..Default::default()
+    }
+}
+
+impl ParameterSpec {
+    /// Sets the [ScaleType] used to explore this (double or integer) parameter.
+    pub fn with_scale_type(mut self, scale_type: ScaleType) -> Self {
+        self.scale_type = scale_type as i32;
+        self
+    }
+
+    /// Explores the parameter linearly - [ScaleType::UnitLinearScale].
+    pub fn linear(self) -> Self {
+        self.with_scale_type(ScaleType::UnitLinearScale)
+    }
+
+    /// Explores the parameter logarithmically - [ScaleType::UnitLogScale]. The range of
+    /// the parameter must be strictly positive.
+    pub fn log(self) -> Self {
+        self.with_scale_type(ScaleType::UnitLogScale)
+    }
+
+    /// Explores the parameter "reverse" logarithmically, i.e. more finely close to its
+    /// maximum - [ScaleType::UnitReverseLogScale]. The range of the parameter must be
+    /// strictly positive.
+    pub fn reverse_log(self) -> Self {
+        self.with_scale_type(ScaleType::UnitReverseLogScale)
+    }
+
+    /// Creates a [ParameterSpec] for a double parameter taking values in `range`.
+    ///
+    /// No default value is set. Fails with [Error::EmptyRange] if `range` is empty (or
+    /// bounded by a NaN).
+    pub fn double_range(
+        parameter_id: impl Into<String>,
+        range: RangeInclusive<f64>,
+    ) -> Result<ParameterSpec, Error> {
+        let parameter_id = parameter_id.into();
+        if range.is_empty() {
+            return Err(Error::EmptyRange(parameter_id));
+        }
+
+        Ok(ParameterSpec {
+            parameter_id,
+            parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+                min_value: *range.start(),
+                max_value: *range.end(),
+                default_value: None,
+            })),
+            ..Default::default()
+        })
+    }
+
+    /// Creates a [ParameterSpec] for an integer parameter taking values in `range`.
+    ///
+    /// No default value is set. Fails with [Error::EmptyRange] if `range` is empty.
+    pub fn int_range(
+        parameter_id: impl Into<String>,
+        range: RangeInclusive<i64>,
+    ) -> Result<ParameterSpec, Error> {
+        let parameter_id = parameter_id.into();
+        if range.is_empty() {
+            return Err(Error::EmptyRange(parameter_id));
+        }
+
+        Ok(ParameterSpec {
+            parameter_id,
+            parameter_value_spec: Some(ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+                min_value: *range.start(),
+                max_value: *range.end(),
+                default_value: None,
+            })),
+            ..Default::default()
+        })
+    }
+
+    /// Adds a conditional parameter, only active when this (parent) parameter takes one
+    /// of the values of `parent_value_condition`.
+    ///
+    /// The condition has to match the type of the parent parameter: categorical, discrete
+    /// or integer. See also [ParameterSpec::with_categorical_child],
+    /// [ParameterSpec::with_discrete_child] and [ParameterSpec::with_integer_child].
+    pub fn with_conditional_parameter_spec(
+        mut self,
+        parent_value_condition: ParentValueCondition,
+        parameter_spec: ParameterSpec,
+    ) -> Self {
+        self.conditional_parameter_specs
+            .push(ConditionalParameterSpec {
+                parameter_spec: Some(parameter_spec),
+                parent_value_condition: Some(parent_value_condition),
+            });
+        self
+    }
+
+    /// Adds a conditional parameter, only active when this categorical parameter takes
+    /// one of `parent_values`.
+    pub fn with_categorical_child(
+        self,
+        parent_values: impl IntoIterator<Item = impl Into<String>>,
+        parameter_spec: ParameterSpec,
+    ) -> Self {
+        self.with_conditional_parameter_spec(
+            ParentValueCondition::ParentCategoricalValues(CategoricalValueCondition {
+                values: parent_values.into_iter().map(Into::into).collect(),
+            }),
+            parameter_spec,
+        )
+    }
+
+    /// Adds a conditional parameter, only active when this discrete parameter takes one
+    /// of `parent_values`.
+    pub fn with_discrete_child(
+        self,
+        parent_values: impl IntoIterator<Item = f64>,
+        parameter_spec: ParameterSpec,
+    ) -> Self {
+        self.with_conditional_parameter_spec(
+            ParentValueCondition::ParentDiscreteValues(DiscreteValueCondition {
+                values: parent_values.into_iter().collect(),
+            }),
+            parameter_spec,
+        )
+    }
+
+    /// Adds a conditional parameter, only active when this integer parameter takes one of
+    /// `parent_values`.
+    pub fn with_integer_child(
+        self,
+        parent_values: impl IntoIterator<Item = i64>,
+        parameter_spec: ParameterSpec,
+    ) -> Self {
+        self.with_conditional_parameter_spec(
+            ParentValueCondition::ParentIntValues(IntValueCondition {
+                values: parent_values.into_iter().collect(),
+            }),
+            parameter_spec,
+        )
+    }
+}
+
+enum BuilderStrategy {
+    DecayCurve,
+    Median,
+}
+
+/// [AutomatedStoppingSpec] builder.
+pub struct AutomatedStoppingSpecBuilder {
+    strategy: BuilderStrategy,
+    use_elapsed_duration: bool,
+}
+
+impl AutomatedStoppingSpecBuilder {
+    /// Creates a builder for a decay curve automated stopping spec.
+    pub fn decay_curve() -> Self {
+        Self {
+            strategy: BuilderStrategy::DecayCurve,
+            use_elapsed_duration: false,
+        }
+    }
+
+    /// Creates a builder for a median automated stopping spec.
+    pub fn median_automated() -> Self {
+        Self {
+            strategy: BuilderStrategy::Median,
+            use_elapsed_duration: false,
+        }
+    }
+
+    /// Uses the elapsed duration of the measurements, rather than their step count, to
+    /// compare the trials.
+    pub fn with_use_elapsed_duration(mut self, use_elapsed_duration: bool) -> Self {
+        self.use_elapsed_duration = use_elapsed_duration;
+        self
+    }
+
+    /// Builds the [AutomatedStoppingSpec].
+    pub fn build(self) -> AutomatedStoppingSpec {
+        let use_elapsed_duration = self.use_elapsed_duration;
+        match self.strategy {
+            BuilderStrategy::DecayCurve => {
+                AutomatedStoppingSpec::DecayCurveStoppingSpec(DecayCurveAutomatedStoppingSpec {
+                    use_elapsed_duration,
+                })
+            }
+            BuilderStrategy::Median => {
+                AutomatedStoppingSpec::MedianAutomatedStoppingSpec(MedianAutomatedStoppingSpec {
+                    use_elapsed_duration,
+                })
+            }
+        }
+    }
+}
+
+/// Creates a [MetricSpecBuilder] for the metric `metric_id` optimized towards `goal`.
+pub fn metric_spec(metric_id: impl Into<String>, goal: GoalType) -> MetricSpecBuilder {
+    MetricSpecBuilder {
+        metric_id: metric_id.into(),
+        goal,
+        safety_config: None,
+    }
+}
+
+/// [MetricSpec] builder - see [metric_spec].
+pub struct MetricSpecBuilder {
+    metric_id: String,
+    goal: GoalType,
+    safety_config: Option<SafetyMetricConfig>,
+}
+
+impl MetricSpecBuilder {
+    /// Makes the metric a safety metric: trials on the wrong side of `safety_threshold`
+    /// (w.r.t. the goal of the metric) are unsafe.
+    ///
+    /// A safety metric is not optimized: the [StudySpec] needs a separate objective
+    /// metric.
+    pub fn with_safety_threshold(mut self, safety_threshold: f64) -> Self {
+        self.safety_config
+            .get_or_insert_with(Default::default)
+            .safety_threshold = safety_threshold;
+        self
+    }
+
+    /// Sets the fraction of safe trials - in `[0, 1]` - the service should target (best
+    /// effort) - makes the metric a safety metric with a threshold of 0 unless
+    /// [MetricSpecBuilder::with_safety_threshold] is used.
+    pub fn with_desired_min_safe_trials_fraction(mut self, fraction: f64) -> Self {
+        self.safety_config
+            .get_or_insert_with(Default::default)
+            .desired_min_safe_trials_fraction = Some(fraction);
+        self
+    }
+
+    /// Builds the [MetricSpec].
+    pub fn build(self) -> MetricSpec {
+        MetricSpec {
+            metric_id: self.metric_id,
+            goal: self.goal as i32,
+            safety_config: self.safety_config,
+        }
+    }
+}
+
 /// [StudySpec] builder.
+///
+/// Note that the v1 [StudySpec] carries no trial budget (such as a maximum trial count):
+/// the number of trials to run is enforced client-side - see
+/// [Optimizer::with_budget](crate::model::optimize::Optimizer::with_budget).
 pub struct StudySpecBuilder {
     metrics: Vec<MetricSpec>,
     parameters: Vec<ParameterSpec>,
@@ -47,19 +414,31 @@ impl StudySpecBuilder {
         }
     }
 
-    /// Sets the [MetricSpec]s to the [StudySpec].
+    /// Uses [Algorithm::GridSearch] to explore the search space.
+    ///
+    /// Grid search requires every parameter (including conditional ones) to have a finite
+    /// set of values: integer, categorical or discrete. [StudySpecBuilder::build] rejects
+    /// parameters using a `DoubleValueSpec`.
+    pub fn with_grid_search(mut self) -> Self {
+        self.algorithm = Algorithm::GridSearch;
+        self
+    }
+
+    /// Sets the [MetricSpec]s to the [StudySpec] - see [metric_spec].
     pub fn with_metric_specs(mut self, metrics: Vec<MetricSpec>) -> Self {
         self.metrics = metrics;
         self
     }
 
-    /// Sets the [ParameterSpec]s to the [StudySpec].
-    pub fn with_parameters(mut self, parameters: Vec<ParameterSpec>) -> Self {
-        self.parameters = parameters;
+    /// Sets the [ParameterSpec]s to the [StudySpec] - see [double_param],
+    /// [integer_param], [categorical_param] and [discrete_param].
+    pub fn with_parameters(mut self, parameters: impl IntoIterator<Item = ParameterSpec>) -> Self {
+        self.parameters = parameters.into_iter().collect();
         self
     }
 
-    /// Sets the [AutomatedStoppingSpec] to the [StudySpec].
+    /// Sets the [AutomatedStoppingSpec] to the [StudySpec] - see
+    /// [AutomatedStoppingSpecBuilder].
     pub fn with_automated_stopping_spec(
         mut self,
         automated_stopping_spec: AutomatedStoppingSpec,
@@ -69,14 +448,321 @@ impl StudySpecBuilder {
     }
 
     /// Builds the [StudySpec].
-    pub fn build(self) -> StudySpec {
-        StudySpec {
+    ///
+    /// Fails if [Algorithm::GridSearch] is used with a continuous parameter.
+    pub fn build(self) -> Result<StudySpec, Error> {
+        if self.algorithm == Algorithm::GridSearch {
+            check_grid_search_parameters(&self.parameters)?;
+        }
+
+        Ok(StudySpec {
             metrics: self.metrics,
             parameters: self.parameters,
             algorithm: self.algorithm as i32,
             observation_noise: self.observation_noise as i32,
             measurement_selection_type: self.measurement_selection_type as i32,
             automated_stopping_spec: self.automated_stopping_spec,
+        })
+    }
+}
+
+fn check_grid_search_parameters(parameters: &[ParameterSpec]) -> Result<(), Error> {
+    parameters.iter().try_for_each(check_grid_search_parameter)
+}
+
+fn check_grid_search_parameter(parameter: &ParameterSpec) -> Result<(), Error> {
+    if let Some(ParameterValueSpec::DoubleValueSpec(_)) = parameter.parameter_value_spec {
+        return Err(Error::ContinuousParameterWithGridSearch(
+            parameter.parameter_id.clone(),
+        ));
+    }
+
+    parameter
+        .conditional_parameter_specs
+        .iter()
+        .filter_map(|c| c.parameter_spec.as_ref())
+        .try_for_each(check_grid_search_parameter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> StudySpecBuilder {
+        StudySpecBuilder::new(
+            Algorithm::Unspecified,
+            ObservationNoise::Low,
+            MeasurementSelectionType::LastMeasurement,
+        )
+        .with_grid_search()
+    }
+
+    fn integer_parameter(parameter_id: &str) -> ParameterSpec {
+        ParameterSpec {
+            parameter_id: parameter_id.to_string(),
+            parameter_value_spec: Some(ParameterValueSpec::IntegerValueSpec(IntegerValueSpec {
+                min_value: 0,
+                max_value: 3,
+                default_value: None,
+            })),
+            ..Default::default()
         }
     }
+
+    fn double_parameter(parameter_id: &str) -> ParameterSpec {
+        ParameterSpec {
+            parameter_id: parameter_id.to_string(),
+            parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(DoubleValueSpec {
+                min_value: 0.0,
+                max_value: 1.0,
+                default_value: None,
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_reads_the_stopping_strategy() {
+        use crate::google::cloud::aiplatform::v1::study_spec::MedianAutomatedStoppingSpec;
+
+        let spec = builder().build().unwrap();
+        assert_eq!(spec.stopping_strategy(), None);
+
+        let spec = builder()
+            .with_automated_stopping_spec(AutomatedStoppingSpec::MedianAutomatedStoppingSpec(
+                MedianAutomatedStoppingSpec {
+                    use_elapsed_duration: true,
+                },
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(spec.stopping_strategy(), Some(StoppingStrategy::Median));
+    }
+
+    #[test]
+    fn it_builds_grid_searches_over_integer_parameters() {
+        let spec = builder()
+            .with_parameters(vec![integer_parameter("a")])
+            .build()
+            .unwrap();
+
+        assert_eq!(spec.algorithm, Algorithm::GridSearch as i32);
+    }
+
+    #[test]
+    fn it_rejects_grid_searches_over_continuous_parameters() {
+        let mut parent = integer_parameter("a");
+        parent.conditional_parameter_specs = vec![ConditionalParameterSpec {
+            parameter_spec: Some(double_parameter("b")),
+            ..Default::default()
+        }];
+
+        let err = builder().with_parameters(vec![parent]).build().unwrap_err();
+
+        assert!(matches!(err, Error::ContinuousParameterWithGridSearch(id) if id == "b"));
+    }
+
+    #[test]
+    fn it_creates_parameters_from_ranges() {
+        assert_eq!(
+            ParameterSpec::double_range("d", 0.0..=1.0).unwrap(),
+            double_parameter("d")
+        );
+        assert_eq!(
+            ParameterSpec::int_range("i", 0..=3).unwrap(),
+            integer_parameter("i")
+        );
+
+        assert!(matches!(
+            ParameterSpec::int_range("i", RangeInclusive::new(4, 3)),
+            Err(Error::EmptyRange(id)) if id == "i"
+        ));
+        assert!(ParameterSpec::double_range("d", 0.0..=f64::NAN).is_err());
+    }
+
+    #[test]
+    fn it_creates_parameters() {
+        assert_eq!(
+            double_param("d", 0.0, 1.0, None, ScaleType::Unspecified),
+            double_parameter("d")
+        );
+        assert_eq!(
+            integer_param("i", 0, 3, None, ScaleType::Unspecified),
+            integer_parameter("i")
+        );
+
+        assert_eq!(
+            categorical_param("c", ["sgd", "adam"]).parameter_value_spec,
+            Some(ParameterValueSpec::CategoricalValueSpec(
+                CategoricalValueSpec {
+                    values: vec!["sgd".to_string(), "adam".to_string()],
+                    default_value: None,
+                }
+            ))
+        );
+        assert_eq!(
+            discrete_param("b", [64.0, 16.0, 32.0]).parameter_value_spec,
+            Some(ParameterValueSpec::DiscreteValueSpec(DiscreteValueSpec {
+                values: vec![16.0, 32.0, 64.0],
+                default_value: None,
+            }))
+        );
+
+        let spec = builder()
+            .with_parameters([
+                integer_param("i", 0, 3, None, ScaleType::Unspecified),
+                discrete_param("b", [1.0]),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(spec.parameters.len(), 2);
+    }
+
+    #[test]
+    fn it_sets_the_scale_type() {
+        let lr = double_param("lr", 1e-4, 1e-1, None, ScaleType::Unspecified).log();
+        assert_eq!(lr.scale_type, ScaleType::UnitLogScale as i32);
+        assert_eq!(
+            lr,
+            double_param("lr", 1e-4, 1e-1, None, ScaleType::UnitLogScale)
+        );
+
+        assert_eq!(
+            integer_param("units", 1, 512, None, ScaleType::UnitLogScale)
+                .linear()
+                .scale_type,
+            ScaleType::UnitLinearScale as i32
+        );
+        assert_eq!(
+            ParameterSpec::double_range("momentum", 0.5..=0.99)
+                .unwrap()
+                .reverse_log()
+                .scale_type,
+            ScaleType::UnitReverseLogScale as i32
+        );
+    }
+
+    #[test]
+    fn it_adds_conditional_parameters() {
+        let optimizer = categorical_param("optimizer", ["sgd", "adam"])
+            .with_categorical_child(
+                ["sgd"],
+                double_param("momentum", 0.0, 1.0, None, ScaleType::Unspecified),
+            )
+            .with_categorical_child(
+                ["adam"],
+                double_param("beta1", 0.8, 1.0, None, ScaleType::Unspecified),
+            );
+
+        assert_eq!(
+            optimizer.conditional_parameter_specs,
+            vec![
+                ConditionalParameterSpec {
+                    parameter_spec: Some(double_param(
+                        "momentum",
+                        0.0,
+                        1.0,
+                        None,
+                        ScaleType::Unspecified
+                    )),
+                    parent_value_condition: Some(ParentValueCondition::ParentCategoricalValues(
+                        CategoricalValueCondition {
+                            values: vec!["sgd".to_string()],
+                        }
+                    )),
+                },
+                ConditionalParameterSpec {
+                    parameter_spec: Some(double_param(
+                        "beta1",
+                        0.8,
+                        1.0,
+                        None,
+                        ScaleType::Unspecified
+                    )),
+                    parent_value_condition: Some(ParentValueCondition::ParentCategoricalValues(
+                        CategoricalValueCondition {
+                            values: vec!["adam".to_string()],
+                        }
+                    )),
+                },
+            ]
+        );
+
+        let layers = integer_param("layers", 1, 3, None, ScaleType::Unspecified)
+            .with_integer_child(
+                [2, 3],
+                integer_param("width", 8, 64, None, ScaleType::Unspecified),
+            );
+        assert_eq!(
+            layers.conditional_parameter_specs[0].parent_value_condition,
+            Some(ParentValueCondition::ParentIntValues(IntValueCondition {
+                values: vec![2, 3]
+            }))
+        );
+    }
+
+    #[test]
+    fn it_builds_automated_stopping_specs() {
+        assert_eq!(
+            AutomatedStoppingSpecBuilder::decay_curve().build(),
+            AutomatedStoppingSpec::DecayCurveStoppingSpec(DecayCurveAutomatedStoppingSpec {
+                use_elapsed_duration: false
+            })
+        );
+        assert_eq!(
+            AutomatedStoppingSpecBuilder::median_automated()
+                .with_use_elapsed_duration(true)
+                .build(),
+            AutomatedStoppingSpec::MedianAutomatedStoppingSpec(MedianAutomatedStoppingSpec {
+                use_elapsed_duration: true
+            })
+        );
+
+        let spec = builder()
+            .with_automated_stopping_spec(AutomatedStoppingSpecBuilder::median_automated().build())
+            .build()
+            .unwrap();
+        assert_eq!(spec.stopping_strategy(), Some(StoppingStrategy::Median));
+    }
+
+    #[test]
+    fn it_builds_safety_metric_specs() {
+        assert_eq!(
+            metric_spec("accuracy", GoalType::Maximize).build(),
+            MetricSpec {
+                metric_id: "accuracy".to_string(),
+                goal: GoalType::Maximize as i32,
+                safety_config: None,
+            }
+        );
+
+        let spec = builder()
+            .with_metric_specs(vec![
+                metric_spec("accuracy", GoalType::Maximize).build(),
+                metric_spec("latency", GoalType::Minimize)
+                    .with_safety_threshold(0.2)
+                    .with_desired_min_safe_trials_fraction(0.9)
+                    .build(),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            spec.metrics[1].safety_config,
+            Some(SafetyMetricConfig {
+                safety_threshold: 0.2,
+                desired_min_safe_trials_fraction: Some(0.9),
+            })
+        );
+
+        let fraction_only = metric_spec("latency", GoalType::Minimize)
+            .with_desired_min_safe_trials_fraction(0.5)
+            .build();
+        assert_eq!(
+            fraction_only.safety_config,
+            Some(SafetyMetricConfig {
+                safety_threshold: 0.0,
+                desired_min_safe_trials_fraction: Some(0.5),
+            })
+        );
+    }
 }
@@ -0,0 +1,416 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pre-flight validation of an experiment: a [StudySpec] and its seed [Trial]s.
+
+use std::collections::HashSet;
+
+use prost_types::value::Kind;
+
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::ParameterValueSpec;
+use crate::google::cloud::aiplatform::v1::study_spec::{Algorithm, ParameterSpec};
+use crate::google::cloud::aiplatform::v1::StudySpec;
+use crate::{Measurement, Trial};
+
+/// A problem found in an experiment definition.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// The spec has no metric.
+    #[error("at least one metric is required")]
+    NoMetric,
+    /// A metric id is empty or contains whitespaces.
+    #[error("invalid metric id '{0}' - must be non-empty and without whitespaces")]
+    InvalidMetricId(String),
+    /// A metric id is used more than once.
+    #[error("duplicate metric id '{0}'")]
+    DuplicateMetricId(String),
+    /// The spec has no parameter.
+    #[error("at least one parameter is required")]
+    NoParameter,
+    /// A parameter id is empty or contains whitespaces.
+    #[error("invalid parameter id '{0}' - must be non-empty and without whitespaces")]
+    InvalidParameterId(String),
+    /// A parameter id is used more than once.
+    #[error("duplicate parameter id '{0}'")]
+    DuplicateParameterId(String),
+    /// A parameter has no value spec.
+    #[error("parameter '{0}' has no value spec")]
+    MissingValueSpec(String),
+    /// A parameter has empty bounds or a default value out of them.
+    #[error("parameter '{0}' has invalid bounds or default value")]
+    InvalidBounds(String),
+    /// Grid search only supports parameters with a finite set of values.
+    #[error("grid search does not support continuous parameter '{0}'")]
+    ContinuousParameterWithGridSearch(String),
+    /// A measurement reports a metric that is not in the spec.
+    #[error("unknown metric '{0}'")]
+    UnknownMetric(String),
+    /// A seed trial sets a parameter that is not in the spec.
+    #[error("seed trial #{trial} sets unknown parameter '{parameter_id}'")]
+    UnknownParameter {
+        /// Index of the seed trial.
+        trial: usize,
+        /// The parameter id.
+        parameter_id: String,
+    },
+    /// A seed trial does not set a (non-conditional) parameter of the spec.
+    #[error("seed trial #{trial} does not set parameter '{parameter_id}'")]
+    MissingParameter {
+        /// Index of the seed trial.
+        trial: usize,
+        /// The parameter id.
+        parameter_id: String,
+    },
+    /// A seed trial sets a parameter to a value not allowed by the spec.
+    #[error("seed trial #{trial} sets parameter '{parameter_id}' to an invalid value")]
+    InvalidParameterValue {
+        /// Index of the seed trial.
+        trial: usize,
+        /// The parameter id.
+        parameter_id: String,
+    },
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && !id.chars().any(char::is_whitespace)
+}
+
+/// Returns the parameters of the spec, including the conditional ones.
+fn all_parameters(parameters: &[ParameterSpec]) -> Vec<&ParameterSpec> {
+    let mut all = vec![];
+    for p in parameters {
+        all.push(p);
+        for c in &p.conditional_parameter_specs {
+            if let Some(child) = &c.parameter_spec {
+                all.extend(all_parameters(std::slice::from_ref(child)));
+            }
+        }
+    }
+    all
+}
+
+fn has_valid_bounds(spec: &ParameterValueSpec) -> bool {
+    match spec {
+        ParameterValueSpec::DoubleValueSpec(s) => {
+            s.min_value <= s.max_value
+                && s.default_value
+                    .is_none_or(|d| s.min_value <= d && d <= s.max_value)
+        }
+        ParameterValueSpec::IntegerValueSpec(s) => {
+            s.min_value <= s.max_value
+                && s.default_value
+                    .is_none_or(|d| s.min_value <= d && d <= s.max_value)
+        }
+        ParameterValueSpec::CategoricalValueSpec(s) => {
+            !s.values.is_empty()
+                && s.default_value
+                    .as_ref()
+                    .is_none_or(|d| s.values.contains(d))
+        }
+        ParameterValueSpec::DiscreteValueSpec(s) => {
+            !s.values.is_empty() && s.default_value.is_none_or(|d| s.values.contains(&d))
+        }
+    }
+}
+
+fn is_valid_value(spec: &ParameterValueSpec, kind: Option<&Kind>) -> bool {
+    match (spec, kind) {
+        (ParameterValueSpec::DoubleValueSpec(s), Some(Kind::NumberValue(v))) => {
+            s.min_value <= *v && *v <= s.max_value
+        }
+        (ParameterValueSpec::IntegerValueSpec(s), Some(Kind::NumberValue(v))) => {
+            v.fract() == 0.0 && s.min_value as f64 <= *v && *v <= s.max_value as f64
+        }
+        (ParameterValueSpec::CategoricalValueSpec(s), Some(Kind::StringValue(v))) => {
+            s.values.contains(v)
+        }
+        (ParameterValueSpec::DiscreteValueSpec(s), Some(Kind::NumberValue(v))) => {
+            s.values.contains(v)
+        }
+        _ => false,
+    }
+}
+
+/// Validates an experiment definition before launching it.
+///
+/// Checks that:
+/// - the spec has at least one metric and one parameter,
+/// - metric and parameter ids are non-empty, without whitespaces and unique,
+/// - each parameter has a value spec with valid bounds and default value,
+/// - grid search is only used with parameters having a finite set of values,
+/// - each seed trial sets every non-conditional parameter, only sets parameters of the
+///   spec, and with values allowed by the spec.
+///
+/// All the problems found are returned at once.
+pub fn validate_experiment(spec: &StudySpec, seed_trials: &[Trial]) -> Result<(), Vec<Violation>> {
+    let mut violations = vec![];
+
+    if spec.metrics.is_empty() {
+        violations.push(Violation::NoMetric);
+    }
+    let mut metric_ids = HashSet::new();
+    for m in &spec.metrics {
+        if !is_valid_id(&m.metric_id) {
+            violations.push(Violation::InvalidMetricId(m.metric_id.clone()));
+        }
+        if !metric_ids.insert(m.metric_id.as_str()) {
+            violations.push(Violation::DuplicateMetricId(m.metric_id.clone()));
+        }
+    }
+
+    if spec.parameters.is_empty() {
+        violations.push(Violation::NoParameter);
+    }
+    let parameters = all_parameters(&spec.parameters);
+    let mut parameter_ids = HashSet::new();
+    for p in &parameters {
+        let id = &p.parameter_id;
+        if !is_valid_id(id) {
+            violations.push(Violation::InvalidParameterId(id.clone()));
+        }
+        if !parameter_ids.insert(id.as_str()) {
+            violations.push(Violation::DuplicateParameterId(id.clone()));
+        }
+        match &p.parameter_value_spec {
+            None => violations.push(Violation::MissingValueSpec(id.clone())),
+            Some(s) => {
+                if !has_valid_bounds(s) {
+                    violations.push(Violation::InvalidBounds(id.clone()));
+                }
+                if spec.algorithm == Algorithm::GridSearch as i32
+                    && matches!(s, ParameterValueSpec::DoubleValueSpec(_))
+                {
+                    violations.push(Violation::ContinuousParameterWithGridSearch(id.clone()));
+                }
+            }
+        }
+    }
+
+    for (trial, t) in seed_trials.iter().enumerate() {
+        for required in &spec.parameters {
+            if !t
+                .parameters
+                .iter()
+                .any(|p| p.parameter_id == required.parameter_id)
+            {
+                violations.push(Violation::MissingParameter {
+                    trial,
+                    parameter_id: required.parameter_id.clone(),
+                });
+            }
+        }
+
+        for p in &t.parameters {
+            let parameter_id = p.parameter_id.clone();
+            match parameters.iter().find(|s| s.parameter_id == p.parameter_id) {
+                None => violations.push(Violation::UnknownParameter {
+                    trial,
+                    parameter_id,
+                }),
+                Some(s) => {
+                    let kind = p.value.as_ref().and_then(|v| v.kind.as_ref());
+                    let valid = s
+                        .parameter_value_spec
+                        .as_ref()
+                        .is_none_or(|s| is_valid_value(s, kind));
+                    if !valid {
+                        violations.push(Violation::InvalidParameterValue {
+                            trial,
+                            parameter_id,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+impl StudySpec {
+    /// Validates the spec on its own - see [validate_experiment].
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        validate_experiment(self, &[])
+    }
+}
+
+/// Validates a measurement against a study spec.
+///
+/// Checks that the measurement only reports metrics of the spec, at most once each. A
+/// measurement does not have to report all the metrics of the spec.
+pub fn validate_measurement(
+    spec: &StudySpec,
+    measurement: &Measurement,
+) -> Result<(), Vec<Violation>> {
+    let mut violations = vec![];
+
+    let mut metric_ids = HashSet::new();
+    for m in &measurement.metrics {
+        if !spec.metrics.iter().any(|s| s.metric_id == m.metric_id) {
+            violations.push(Violation::UnknownMetric(m.metric_id.clone()));
+        }
+        if !metric_ids.insert(m.metric_id.as_str()) {
+            violations.push(Violation::DuplicateMetricId(m.metric_id.clone()));
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+    use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
+        CategoricalValueSpec, DoubleValueSpec,
+    };
+    use crate::google::cloud::aiplatform::v1::study_spec::MetricSpec;
+    use crate::google::cloud::aiplatform::v1::trial::Parameter;
+
+    fn metric(metric_id: &str) -> MetricSpec {
+        MetricSpec {
+            metric_id: metric_id.to_string(),
+            goal: GoalType::Maximize as i32,
+            ..Default::default()
+        }
+    }
+
+    fn spec() -> StudySpec {
+        StudySpec {
+            metrics: vec![metric("m1")],
+            parameters: vec![
+                ParameterSpec {
+                    parameter_id: "x".to_string(),
+                    parameter_value_spec: Some(ParameterValueSpec::DoubleValueSpec(
+                        DoubleValueSpec {
+                            min_value: 0.0,
+                            max_value: 1.0,
+                            default_value: None,
+                        },
+                    )),
+                    ..Default::default()
+                },
+                ParameterSpec {
+                    parameter_id: "c".to_string(),
+                    parameter_value_spec: Some(ParameterValueSpec::CategoricalValueSpec(
+                        CategoricalValueSpec {
+                            values: vec!["a".to_string(), "b".to_string()],
+                            default_value: None,
+                        },
+                    )),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn trial(parameters: Vec<(&str, Kind)>) -> Trial {
+        Trial {
+            parameters: parameters
+                .into_iter()
+                .map(|(id, kind)| Parameter {
+                    parameter_id: id.to_string(),
+                    value: Some(prost_types::Value { kind: Some(kind) }),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_accepts_a_valid_experiment() {
+        let seed = trial(vec![
+            ("x", Kind::NumberValue(0.5)),
+            ("c", Kind::StringValue("a".to_string())),
+        ]);
+
+        assert_eq!(validate_experiment(&spec(), &[seed]), Ok(()));
+    }
+
+    #[test]
+    fn it_reports_all_violations() {
+        let mut spec = spec();
+        spec.metrics.push(metric("m1"));
+        spec.metrics.push(metric("bad id"));
+        spec.algorithm = Algorithm::GridSearch as i32;
+        if let Some(ParameterValueSpec::DoubleValueSpec(s)) =
+            &mut spec.parameters[0].parameter_value_spec
+        {
+            s.min_value = 2.0;
+        }
+
+        let seed = trial(vec![
+            ("c", Kind::StringValue("z".to_string())),
+            ("y", Kind::NumberValue(1.0)),
+        ]);
+
+        let violations = validate_experiment(&spec, &[seed]).unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                Violation::DuplicateMetricId("m1".to_string()),
+                Violation::InvalidMetricId("bad id".to_string()),
+                Violation::InvalidBounds("x".to_string()),
+                Violation::ContinuousParameterWithGridSearch("x".to_string()),
+                Violation::MissingParameter {
+                    trial: 0,
+                    parameter_id: "x".to_string()
+                },
+                Violation::InvalidParameterValue {
+                    trial: 0,
+                    parameter_id: "c".to_string()
+                },
+                Violation::UnknownParameter {
+                    trial: 0,
+                    parameter_id: "y".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_validates_measurements() {
+        let measurement = |ids: &[&str]| Measurement {
+            metrics: ids
+                .iter()
+                .map(
+                    |id| crate::google::cloud::aiplatform::v1::measurement::Metric {
+                        metric_id: id.to_string(),
+                        value: 1.0,
+                    },
+                )
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(validate_measurement(&spec(), &measurement(&["m1"])), Ok(()));
+        assert_eq!(validate_measurement(&spec(), &measurement(&[])), Ok(()));
+        assert_eq!(
+            validate_measurement(&spec(), &measurement(&["m1", "m2", "m1"])),
+            Err(vec![
+                Violation::UnknownMetric("m2".to_string()),
+                Violation::DuplicateMetricId("m1".to_string()),
+            ])
+        );
+    }
+}
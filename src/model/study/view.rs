@@ -0,0 +1,159 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed view of a [Study].
+
+use crate::google::cloud::aiplatform::v1::study::State;
+use crate::google::cloud::aiplatform::v1::study_spec::{Algorithm, MetricSpec};
+use crate::google::cloud::aiplatform::v1::Study;
+use crate::study::{StudyName, ToStudyName};
+
+/// Error returned when decoding a [Study].
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    /// The state of the study is not a known [State].
+    #[error("unknown study state {0}")]
+    UnknownState(i32),
+    /// The algorithm of the study is not a known [Algorithm].
+    #[error("unknown study algorithm {0}")]
+    UnknownAlgorithm(i32),
+}
+
+/// Typed view of a [Study] - such as the one returned by `GetStudy` - decoding its enums
+/// and defaulting its optional fields.
+#[derive(Clone, Copy, Debug)]
+pub struct StudyView<'a> {
+    study: &'a Study,
+}
+
+impl<'a> StudyView<'a> {
+    /// Creates a view of `study`.
+    pub fn new(study: &'a Study) -> Self {
+        StudyView { study }
+    }
+
+    /// Returns the underlying [Study].
+    pub fn study(&self) -> &'a Study {
+        self.study
+    }
+
+    /// Returns the [StudyName] of the study.
+    pub fn name(&self) -> StudyName {
+        self.study.to_study_name()
+    }
+
+    /// Returns the search [Algorithm] of the study - [Algorithm::Unspecified] if the
+    /// study has no spec.
+    ///
+    /// Fails with [Error::UnknownAlgorithm] if the algorithm is not known to this
+    /// version of the crate.
+    pub fn algorithm(&self) -> Result<Algorithm, Error> {
+        let algorithm = self
+            .study
+            .study_spec
+            .as_ref()
+            .map(|spec| spec.algorithm)
+            .unwrap_or_default();
+
+        Algorithm::from_i32(algorithm).ok_or(Error::UnknownAlgorithm(algorithm))
+    }
+
+    /// Returns the metrics of the study - none if the study has no spec.
+    pub fn metrics(&self) -> &'a [MetricSpec] {
+        self.study
+            .study_spec
+            .as_ref()
+            .map(|spec| spec.metrics.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the [State] of the study.
+    ///
+    /// Fails with [Error::UnknownState] if the state is not known to this version of the
+    /// crate.
+    pub fn state(&self) -> Result<State, Error> {
+        State::from_i32(self.study.state).ok_or(Error::UnknownState(self.study.state))
+    }
+}
+
+impl<'a> From<&'a Study> for StudyView<'a> {
+    fn from(study: &'a Study) -> Self {
+        StudyView::new(study)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::StudySpec;
+
+    fn study() -> Study {
+        Study {
+            name: "projects/p/locations/us-central1/studies/42".to_string(),
+            state: State::Completed as i32,
+            study_spec: Some(StudySpec {
+                algorithm: Algorithm::RandomSearch as i32,
+                metrics: vec![MetricSpec {
+                    metric_id: "m1".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_decodes_the_enums() {
+        let study = study();
+        let view = StudyView::new(&study);
+
+        assert_eq!(view.algorithm(), Ok(Algorithm::RandomSearch));
+        assert_eq!(view.state(), Ok(State::Completed));
+        assert_eq!(view.metrics().len(), 1);
+        assert_eq!(view.metrics()[0].metric_id, "m1");
+
+        let unknown = Study {
+            state: 42,
+            study_spec: Some(StudySpec {
+                algorithm: 42,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let view = StudyView::from(&unknown);
+        assert_eq!(view.algorithm(), Err(Error::UnknownAlgorithm(42)));
+        assert_eq!(view.state(), Err(Error::UnknownState(42)));
+    }
+
+    #[test]
+    fn it_defaults_a_missing_spec() {
+        let study = Study::default();
+        let view = StudyView::new(&study);
+
+        assert_eq!(view.algorithm(), Ok(Algorithm::Unspecified));
+        assert!(view.metrics().is_empty());
+        assert_eq!(view.state(), Ok(State::Unspecified));
+    }
+
+    #[test]
+    fn it_extracts_the_study_name() {
+        let study = study();
+        let name = StudyView::new(&study).name();
+
+        assert_eq!(name.project(), "p");
+        assert_eq!(name.location(), "us-central1");
+        assert_eq!(name.study_id(), "42");
+    }
+}
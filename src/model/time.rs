@@ -0,0 +1,116 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Times of studies and trials.
+
+use std::time::SystemTime;
+
+use prost_types::Timestamp;
+
+use crate::{Study, Trial};
+
+/// Converts a [Timestamp] - possibly before the epoch - to a [SystemTime].
+///
+/// Returns `None` if the timestamp is unset or cannot be represented.
+fn to_system_time(timestamp: Option<&Timestamp>) -> Option<SystemTime> {
+    SystemTime::try_from(timestamp?.clone()).ok()
+}
+
+/// Times of a [Study].
+pub trait StudyTimes {
+    /// Returns the creation time of the study, if set.
+    fn created_at(&self) -> Option<SystemTime>;
+}
+
+impl StudyTimes for Study {
+    fn created_at(&self) -> Option<SystemTime> {
+        to_system_time(self.create_time.as_ref())
+    }
+}
+
+/// Times of a [Trial].
+pub trait TrialTimes {
+    /// Returns the start time of the trial, if set.
+    fn started_at(&self) -> Option<SystemTime>;
+
+    /// Returns the end time of the trial - set once the trial is in a final state.
+    fn ended_at(&self) -> Option<SystemTime>;
+}
+
+impl TrialTimes for Trial {
+    fn started_at(&self) -> Option<SystemTime> {
+        to_system_time(self.start_time.as_ref())
+    }
+
+    fn ended_at(&self) -> Option<SystemTime> {
+        to_system_time(self.end_time.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn timestamp(seconds: i64, nanos: i32) -> Option<Timestamp> {
+        Some(Timestamp { seconds, nanos })
+    }
+
+    #[test]
+    fn it_converts_timestamps() {
+        let trial = Trial {
+            start_time: timestamp(0, 0),
+            end_time: timestamp(1, 500_000_000),
+            ..Default::default()
+        };
+        assert_eq!(trial.started_at(), Some(UNIX_EPOCH));
+        assert_eq!(
+            trial.ended_at(),
+            Some(UNIX_EPOCH + Duration::from_millis(1500))
+        );
+
+        let study = Study {
+            create_time: timestamp(-1, 500_000_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            study.created_at(),
+            Some(UNIX_EPOCH - Duration::from_millis(500))
+        );
+
+        assert_eq!(Trial::default().ended_at(), None);
+        assert_eq!(Study::default().created_at(), None);
+    }
+
+    #[test]
+    fn it_sorts_trials_by_start_time() {
+        let mut trials = [
+            Trial {
+                id: "2".to_string(),
+                start_time: timestamp(20, 0),
+                ..Default::default()
+            },
+            Trial {
+                id: "1".to_string(),
+                start_time: timestamp(10, 0),
+                ..Default::default()
+            },
+        ];
+
+        trials.sort_by_key(|t| t.started_at());
+
+        assert_eq!(trials[0].id, "1");
+    }
+}
@@ -14,19 +14,40 @@
 
 //! Trial model.
 
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::model::name::{self, NameParseError, Segment};
 use crate::{StudyName, Trial};
 
 pub mod add_measurement;
+pub mod analysis;
 pub mod complete;
 pub mod create;
 pub mod delete;
 pub mod early_stopping;
 pub mod get;
 pub mod list;
+pub mod measurement;
 pub mod optimal;
+pub mod params;
+pub mod pareto;
 pub mod stop;
 pub mod suggest;
 
+/// Returns the values of the metrics of the final measurement of `trial` by metric id -
+/// empty if the trial has no final measurement (yet).
+///
+/// Together with [params::parameters], gives the complete result of a trial.
+pub fn final_metrics(trial: &Trial) -> HashMap<String, f64> {
+    trial
+        .final_measurement
+        .iter()
+        .flat_map(|m| &m.metrics)
+        .map(|m| (m.metric_id.clone(), m.value))
+        .collect()
+}
+
 /// The name of a trial.
 #[derive(Clone, PartialEq, Debug, Eq, Hash)]
 pub struct TrialName(String);
@@ -45,6 +66,55 @@ impl TrialName {
         let study: String = study_name.into();
         TrialName(format!("{}/trials/{}", study, trial))
     }
+
+    /// Parses a name of the form
+    /// "projects/{project}/locations/{location}/studies/{study}/trials/{trial}".
+    ///
+    /// Same as [FromStr] but returns [crate::Error::InvalidResourceName] on failure.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(s: &str) -> Result<Self, crate::Error> {
+        s.parse()
+            .map_err(|e| crate::Error::InvalidResourceName(format!("'{s}' - {e}")))
+    }
+}
+
+impl FromStr for TrialName {
+    type Err = NameParseError;
+
+    /// Parses a name of the form
+    /// "projects/{project}/locations/{location}/studies/{study}/trials/{trial}".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        name::parse(
+            s,
+            &[
+                Segment::Literal("projects"),
+                Segment::Component("project"),
+                Segment::Literal("locations"),
+                Segment::Component("location"),
+                Segment::Literal("studies"),
+                Segment::Component("study"),
+                Segment::Literal("trials"),
+                Segment::Component("trial"),
+            ],
+        )?;
+
+        Ok(TrialName(s.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for TrialName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TrialName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 /// Can be converted to a [TrialName].
@@ -53,9 +123,15 @@ pub trait ToTrialName {
     fn to_trial_name(&self) -> TrialName;
 }
 
+impl From<&Trial> for TrialName {
+    fn from(trial: &Trial) -> Self {
+        TrialName(trial.name.clone())
+    }
+}
+
 impl ToTrialName for Trial {
     fn to_trial_name(&self) -> TrialName {
-        TrialName(self.name.clone())
+        self.into()
     }
 }
 
@@ -70,3 +146,113 @@ impl From<&TrialName> for String {
         trial_name.0.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_trial_names() {
+        let name = "projects/p/locations/us-central1/studies/42/trials/1";
+        assert_eq!(
+            name.parse::<TrialName>().map(String::from),
+            Ok(name.to_string())
+        );
+
+        assert_eq!(
+            "projects/p/locations/us-central1/studies/42/trials/".parse::<TrialName>(),
+            Err(NameParseError::MissingComponent {
+                position: 7,
+                component: "trial"
+            })
+        );
+        assert_eq!(
+            "projects/p/locations/us-central1/studies/42".parse::<TrialName>(),
+            Err(NameParseError::WrongSegmentCount {
+                expected: 8,
+                found: 6
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_trial_names_into_the_crate_error() {
+        let name = "projects/p/locations/us-central1/studies/42/trials/1";
+        assert_eq!(TrialName::parse(name).unwrap(), name.parse().unwrap());
+
+        let err =
+            TrialName::parse("projects/p/locations/us-central1/studies/42/trials/").unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                crate::Error::InvalidResourceName(msg) if msg.contains("missing trial")
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn it_converts_trials_to_names() {
+        let trial = Trial {
+            name: "projects/p/locations/us-central1/studies/42/trials/1".to_string(),
+            ..Default::default()
+        };
+
+        let name: TrialName = (&trial).into();
+        assert_eq!(name, trial.to_trial_name());
+        assert_eq!(TrialName::from(&trial), trial.name.parse().unwrap());
+    }
+
+    #[test]
+    fn it_returns_the_final_metrics() {
+        use crate::google::cloud::aiplatform::v1::measurement::Metric;
+        use crate::google::cloud::aiplatform::v1::Measurement;
+
+        let metric = |metric_id: &str, value| Metric {
+            metric_id: metric_id.to_string(),
+            value,
+        };
+        let completed = Trial {
+            final_measurement: Some(Measurement {
+                metrics: vec![metric("loss", 0.25), metric("accuracy", 0.9)],
+                ..Default::default()
+            }),
+            measurements: vec![Measurement {
+                metrics: vec![metric("loss", 0.5)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            final_metrics(&completed),
+            HashMap::from([("loss".to_string(), 0.25), ("accuracy".to_string(), 0.9)])
+        );
+
+        let incomplete = Trial {
+            measurements: completed.measurements.clone(),
+            ..Default::default()
+        };
+        assert!(final_metrics(&incomplete).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_trial_names() {
+        let name: TrialName = "projects/p/locations/us-central1/studies/42/trials/1"
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(
+            json,
+            r#""projects/p/locations/us-central1/studies/42/trials/1""#
+        );
+        assert_eq!(serde_json::from_str::<TrialName>(&json).unwrap(), name);
+
+        let err =
+            serde_json::from_str::<TrialName>(r#""projects/p/locations/us-central1/studies/42""#)
+                .unwrap_err();
+        assert!(err.to_string().contains("expected"), "{err}");
+    }
+}
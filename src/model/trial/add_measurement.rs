@@ -14,8 +14,44 @@
 
 //! Trial add_measurement request builder.
 
+use crate::google::cloud::aiplatform::v1::trial::State;
 use crate::google::cloud::aiplatform::v1::AddTrialMeasurementRequest;
-use crate::{Measurement, TrialName};
+use crate::{Measurement, Trial, TrialName};
+
+/// Error returned when decoding the trial returned by the service.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The state of the trial is not a known [State].
+    #[error("unknown trial state {0}")]
+    UnknownState(i32),
+}
+
+/// Outcome of [crate::VizierClient::add_trial_measurement].
+#[derive(Clone, Debug)]
+pub struct AddedMeasurement {
+    /// The trial returned by the service.
+    pub trial: Trial,
+    /// Whether the measurement is the last one of the trial returned by the service.
+    pub appended: bool,
+}
+
+/// Returns true if `measurement` is the last measurement of `trial`.
+///
+/// Only the `step_count` and the metrics are compared as the service might normalize the
+/// `elapsed_duration`.
+pub fn is_last_measurement(trial: &Trial, measurement: &Measurement) -> bool {
+    trial.measurements.last().is_some_and(|last| {
+        last.step_count == measurement.step_count && last.metrics == measurement.metrics
+    })
+}
+
+/// Decodes the state of `trial`.
+///
+/// Fails with [Error::UnknownState] if the state is not known to this version of the
+/// crate.
+pub fn trial_state(trial: &Trial) -> Result<State, Error> {
+    State::from_i32(trial.state).ok_or(Error::UnknownState(trial.state))
+}
 
 /// [AddTrialMeasurementRequest] builder.
 pub struct RequestBuilder {
@@ -40,3 +76,47 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::measurement;
+
+    fn measurement(step_count: i64, value: f64) -> Measurement {
+        Measurement {
+            step_count,
+            metrics: vec![measurement::Metric {
+                metric_id: "m".to_string(),
+                value,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_detects_a_lost_measurement() {
+        let trial = Trial {
+            measurements: vec![measurement(1, 1.0), measurement(2, 2.0)],
+            ..Default::default()
+        };
+
+        assert!(is_last_measurement(&trial, &measurement(2, 2.0)));
+        assert!(!is_last_measurement(&trial, &measurement(2, 3.0)));
+        assert!(!is_last_measurement(
+            &Trial::default(),
+            &measurement(1, 1.0)
+        ));
+    }
+
+    #[test]
+    fn it_decodes_the_trial_state() {
+        let mut trial = Trial {
+            state: State::Active as i32,
+            ..Default::default()
+        };
+        assert_eq!(trial_state(&trial).unwrap(), State::Active);
+
+        trial.state = 42;
+        assert!(matches!(trial_state(&trial), Err(Error::UnknownState(42))));
+    }
+}
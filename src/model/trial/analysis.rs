@@ -0,0 +1,603 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Analysis of the results of a study.
+
+use std::collections::{HashMap, VecDeque};
+
+use prost_types::value::Kind;
+
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+use crate::google::cloud::aiplatform::v1::trial;
+use crate::{Measurement, Trial};
+
+/// Returns the value of the metric `metric_id` in the final measurement of `trial`.
+pub(crate) fn final_metric_value(trial: &Trial, metric_id: &str) -> Option<f64> {
+    trial
+        .final_measurement
+        .as_ref()?
+        .metrics
+        .iter()
+        .find(|m| m.metric_id == metric_id)
+        .map(|m| m.value)
+}
+
+/// Returns true if `candidate` is better than `current` for the given `goal`.
+///
+/// [GoalType::Unspecified] is treated as [GoalType::Maximize].
+pub(crate) fn is_better(goal: GoalType, candidate: f64, current: f64) -> bool {
+    match goal {
+        GoalType::Minimize => candidate < current,
+        GoalType::Maximize | GoalType::Unspecified => candidate > current,
+    }
+}
+
+/// Computes the "best-so-far" curve of a study.
+///
+/// The trials are ordered by their numeric id (then start time) and, for each of them,
+/// the best value of the metric `metric_id` observed so far in a final measurement is
+/// reported as `(trial id, best value)`.
+///
+/// Trials without a numeric id or without the metric in their final measurement are
+/// skipped.
+pub fn best_so_far(trials: &[Trial], metric_id: &str, goal: GoalType) -> Vec<(i64, f64)> {
+    let mut points: Vec<(&Trial, i64, f64)> = trials
+        .iter()
+        .filter_map(|t| {
+            Some((
+                t,
+                t.id.parse::<i64>().ok()?,
+                final_metric_value(t, metric_id)?,
+            ))
+        })
+        .collect();
+
+    points.sort_by_key(|(t, id, _)| (*id, t.start_time.as_ref().map(|ts| (ts.seconds, ts.nanos))));
+
+    let mut best: Option<f64> = None;
+    points
+        .into_iter()
+        .map(|(_, id, value)| {
+            let current = match best {
+                Some(b) if !is_better(goal, value, b) => b,
+                _ => value,
+            };
+            best = Some(current);
+            (id, current)
+        })
+        .collect()
+}
+
+/// Returns the learning curve of the metric `metric_id` of a trial as `(step_count,
+/// value)` points sorted by step - e.g. to plot it.
+///
+/// The measurements without the metric are skipped. The step is a `f64` to be used as
+/// is on a plot axis.
+pub fn measurement_series(trial: &Trial, metric_id: &str) -> Vec<(f64, f64)> {
+    let mut series: Vec<(i64, f64)> = trial
+        .measurements
+        .iter()
+        .filter_map(|m| {
+            let metric = m
+                .metrics
+                .iter()
+                .find(|metric| metric.metric_id == metric_id)?;
+            Some((m.step_count, metric.value))
+        })
+        .collect();
+
+    series.sort_by_key(|(step, _)| *step);
+
+    series
+        .into_iter()
+        .map(|(step, value)| (step as f64, value))
+        .collect()
+}
+
+/// Counts the trials in each [trial::State].
+///
+/// Trials with an unknown state are counted as [trial::State::Unspecified].
+pub fn count_by_state(trials: &[Trial]) -> HashMap<trial::State, usize> {
+    let mut counts = HashMap::new();
+    for t in trials {
+        let state = trial::State::from_i32(t.state).unwrap_or(trial::State::Unspecified);
+        *counts.entry(state).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Returns the number of trials that are pending or running - that is in the
+/// [trial::State::Requested], [trial::State::Active] or [trial::State::Stopping] states.
+pub fn count_in_progress(counts: &HashMap<trial::State, usize>) -> usize {
+    [
+        trial::State::Requested,
+        trial::State::Active,
+        trial::State::Stopping,
+    ]
+    .iter()
+    .filter_map(|s| counts.get(s))
+    .sum()
+}
+
+/// How to choose between measurements with the same metric value in [best_measurement].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TieBreak {
+    /// Prefer the measurement with the smallest `step_count` - the default.
+    #[default]
+    EarlierStep,
+    /// Prefer the measurement with the largest `step_count`.
+    LaterStep,
+    /// Prefer the measurement with the smallest `elapsed_duration` - measurements without
+    /// it come last.
+    ShorterElapsed,
+}
+
+impl TieBreak {
+    /// Returns true if `candidate` should be preferred over `current`.
+    fn prefers(self, candidate: &Measurement, current: &Measurement) -> bool {
+        let elapsed = |m: &Measurement| {
+            m.elapsed_duration
+                .as_ref()
+                .map(|d| (d.seconds, d.nanos))
+                .unwrap_or((i64::MAX, i32::MAX))
+        };
+
+        match self {
+            TieBreak::EarlierStep => candidate.step_count < current.step_count,
+            TieBreak::LaterStep => candidate.step_count > current.step_count,
+            TieBreak::ShorterElapsed => elapsed(candidate) < elapsed(current),
+        }
+    }
+}
+
+/// Selects the measurement with the best value of the metric `metric_id` - as
+/// [MeasurementSelectionType::BestMeasurement] does on the server, but deterministically.
+///
+/// The measurements with the same best value are decided with `tie_break`
+/// ([TieBreak::EarlierStep] by default); if they are still tied, the first one in
+/// `measurements` is returned. [GoalType::Unspecified] is treated as
+/// [GoalType::Maximize]. Measurements without the metric are ignored.
+///
+/// [MeasurementSelectionType::BestMeasurement]: crate::google::cloud::aiplatform::v1::study_spec::MeasurementSelectionType::BestMeasurement
+pub fn best_measurement<'a>(
+    measurements: &'a [Measurement],
+    metric_id: &str,
+    goal: GoalType,
+    tie_break: TieBreak,
+) -> Option<&'a Measurement> {
+    let mut best: Option<(&Measurement, f64)> = None;
+    for m in measurements {
+        let Some(value) = m
+            .metrics
+            .iter()
+            .find(|metric| metric.metric_id == metric_id)
+            .map(|metric| metric.value)
+        else {
+            continue;
+        };
+
+        best = match best {
+            Some((current, b))
+                if is_better(goal, b, value) || (b == value && !tie_break.prefers(m, current)) =>
+            {
+                Some((current, b))
+            }
+            _ => Some((m, value)),
+        };
+    }
+    best.map(|(m, _)| m)
+}
+
+/// Returns the fraction of a trial `budget` used by the completed trials -
+/// [trial::State::Succeeded] or [trial::State::Infeasible] - clamped to 1.0.
+///
+/// A budget of 0 is reported as fully used.
+pub fn progress(counts: &HashMap<trial::State, usize>, budget: usize) -> f32 {
+    if budget == 0 {
+        return 1.0;
+    }
+
+    let completed: usize = [trial::State::Succeeded, trial::State::Infeasible]
+        .iter()
+        .filter_map(|s| counts.get(s))
+        .sum();
+
+    (completed as f32 / budget as f32).min(1.0)
+}
+
+/// Returns true if the study has trials and all of them are completed -
+/// [trial::State::Succeeded] or [trial::State::Infeasible].
+pub fn is_complete(counts: &HashMap<trial::State, usize>) -> bool {
+    let completed = [trial::State::Succeeded, trial::State::Infeasible];
+
+    counts.values().any(|&count| count > 0)
+        && counts
+            .iter()
+            .all(|(state, &count)| count == 0 || completed.contains(state))
+}
+
+/// Returns the trial with the best value of the metric `metric_id` in its final
+/// measurement, if any.
+///
+/// [GoalType::Unspecified] is treated as [GoalType::Maximize].
+pub fn best_trial<'a>(trials: &'a [Trial], metric_id: &str, goal: GoalType) -> Option<&'a Trial> {
+    let mut best: Option<(&Trial, f64)> = None;
+    for t in trials {
+        if let Some(value) = final_metric_value(t, metric_id) {
+            match best {
+                Some((_, b)) if !is_better(goal, value, b) => {}
+                _ => best = Some((t, value)),
+            }
+        }
+    }
+    best.map(|(t, _)| t)
+}
+
+/// Returns the Pearson correlation coefficient of `xs` and `ys`, or `None` if one of them
+/// is constant.
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// Computes a simple importance score for each numeric parameter of the trials.
+///
+/// The score is the Pearson correlation between the values of the parameter and the
+/// value of the metric `metric_id` in the final measurement of the trials, oriented
+/// by `goal`: its magnitude (between 0 and 1) ranks the parameters by importance, and
+/// it is positive when larger values of the parameter improve the objective.
+/// [GoalType::Unspecified] is treated as [GoalType::Maximize].
+///
+/// Only the trials reporting the metric are considered. Categorical parameters, and
+/// parameters with fewer than 2 distinct values, are not scored.
+///
+/// Limitations: the correlation only captures monotonic, roughly linear effects of
+/// each parameter taken alone - interactions between parameters and non-monotonic
+/// effects (e.g. an optimum in the middle of the range) are missed. With few trials,
+/// the scores are noisy, and since the suggestions of the service concentrate on
+/// promising regions, the trials are not a uniform sample of the search space.
+pub fn parameter_importance(
+    trials: &[Trial],
+    metric_id: &str,
+    goal: GoalType,
+) -> HashMap<String, f64> {
+    let mut samples: HashMap<&str, (Vec<f64>, Vec<f64>)> = HashMap::new();
+
+    for t in trials {
+        let Some(objective) = final_metric_value(t, metric_id) else {
+            continue;
+        };
+
+        for p in &t.parameters {
+            if let Some(Kind::NumberValue(v)) = p.value.as_ref().and_then(|v| v.kind.as_ref()) {
+                let (xs, ys) = samples.entry(p.parameter_id.as_str()).or_default();
+                xs.push(*v);
+                ys.push(objective);
+            }
+        }
+    }
+
+    let orientation = match goal {
+        GoalType::Minimize => -1.0,
+        GoalType::Maximize | GoalType::Unspecified => 1.0,
+    };
+
+    samples
+        .into_iter()
+        .filter_map(|(id, (xs, ys))| Some((id.to_string(), orientation * correlation(&xs, &ys)?)))
+        .collect()
+}
+
+/// Convergence detection based on the exponential moving average (EMA) of the best
+/// metric value.
+///
+/// Each observed value `v` updates the EMA as `ema = alpha * v + (1 - alpha) * ema`,
+/// with the smoothing factor `alpha = 2 / (window + 1)` - the usual choice for an EMA
+/// spanning `window` observations. The first observation initializes the EMA.
+///
+/// Convergence is declared when the EMA changed by less than `tolerance` (in absolute
+/// value) over the last `window` observations.
+#[derive(Clone, Debug)]
+pub struct EmaConvergence {
+    alpha: f64,
+    tolerance: f64,
+    window: usize,
+    ema: Option<f64>,
+    history: VecDeque<f64>,
+}
+
+impl EmaConvergence {
+    /// Creates a new [EmaConvergence].
+    /// # Arguments
+    /// * `tolerance` - The maximum change of the EMA over `window` observations.
+    /// * `window` - The number of observations - must be at least 1.
+    pub fn new(tolerance: f64, window: usize) -> Self {
+        let window = window.max(1);
+        EmaConvergence {
+            alpha: 2.0 / (window as f64 + 1.0),
+            tolerance,
+            window,
+            ema: None,
+            history: VecDeque::with_capacity(window + 1),
+        }
+    }
+
+    /// Returns the current EMA, if any value has been observed.
+    pub fn ema(&self) -> Option<f64> {
+        self.ema
+    }
+
+    /// Observes a new value and returns true if convergence is reached.
+    pub fn update(&mut self, value: f64) -> bool {
+        let ema = match self.ema {
+            Some(ema) => self.alpha * value + (1.0 - self.alpha) * ema,
+            None => value,
+        };
+        self.ema = Some(ema);
+
+        self.history.push_back(ema);
+        if self.history.len() > self.window + 1 {
+            self.history.pop_front();
+        }
+
+        match (self.history.front(), self.history.back()) {
+            (Some(first), Some(last)) if self.history.len() == self.window + 1 => {
+                (last - first).abs() < self.tolerance
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::{measurement, Measurement};
+
+    fn trial(id: &str, metric_id: &str, value: f64) -> Trial {
+        Trial {
+            id: id.to_string(),
+            final_measurement: Some(Measurement {
+                metrics: vec![measurement::Metric {
+                    metric_id: metric_id.to_string(),
+                    value,
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_extracts_the_measurement_series() {
+        let measurement = |step_count: i64, metrics: &[(&str, f64)]| Measurement {
+            step_count,
+            metrics: metrics
+                .iter()
+                .map(|(metric_id, value)| measurement::Metric {
+                    metric_id: metric_id.to_string(),
+                    value: *value,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let trial = Trial {
+            measurements: vec![
+                measurement(20, &[("loss", 0.5), ("accuracy", 0.8)]),
+                measurement(10, &[("loss", 0.9)]),
+                measurement(30, &[("accuracy", 0.85)]),
+                measurement(40, &[("loss", 0.3), ("accuracy", 0.9)]),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            measurement_series(&trial, "loss"),
+            [(10.0, 0.9), (20.0, 0.5), (40.0, 0.3)]
+        );
+        assert_eq!(
+            measurement_series(&trial, "accuracy"),
+            [(20.0, 0.8), (30.0, 0.85), (40.0, 0.9)]
+        );
+        assert!(measurement_series(&trial, "latency").is_empty());
+        assert!(measurement_series(&Trial::default(), "loss").is_empty());
+    }
+
+    #[test]
+    fn it_computes_the_best_so_far_curve() {
+        let trials = vec![
+            trial("3", "m", 2.0),
+            trial("1", "m", 5.0),
+            trial("2", "other", 0.0),
+            trial("4", "m", 1.0),
+            trial("5", "m", 3.0),
+        ];
+
+        assert_eq!(
+            best_so_far(&trials, "m", GoalType::Minimize),
+            vec![(1, 5.0), (3, 2.0), (4, 1.0), (5, 1.0)]
+        );
+        assert_eq!(
+            best_so_far(&trials, "m", GoalType::Maximize),
+            vec![(1, 5.0), (3, 5.0), (4, 5.0), (5, 5.0)]
+        );
+    }
+
+    #[test]
+    fn it_finds_the_best_trial() {
+        let trials = vec![
+            trial("1", "m", 5.0),
+            trial("2", "other", 9.0),
+            trial("3", "m", 2.0),
+        ];
+
+        assert_eq!(
+            best_trial(&trials, "m", GoalType::Minimize).unwrap().id,
+            "3"
+        );
+        assert_eq!(
+            best_trial(&trials, "m", GoalType::Maximize).unwrap().id,
+            "1"
+        );
+        assert!(best_trial(&trials, "none", GoalType::Maximize).is_none());
+    }
+
+    #[test]
+    fn it_detects_ema_convergence() {
+        let mut convergence = EmaConvergence::new(0.1, 2);
+
+        // alpha = 2/3
+        assert!(!convergence.update(0.0));
+        assert!(!convergence.update(3.0));
+        assert_eq!(convergence.ema(), Some(2.0));
+        assert!(!convergence.update(3.0));
+        assert!(!convergence.update(3.0));
+        assert!(!convergence.update(3.0));
+        assert!(convergence.update(3.0));
+    }
+
+    #[test]
+    fn it_computes_parameter_importance() {
+        use crate::google::cloud::aiplatform::v1::trial::Parameter;
+
+        let with_parameters = |id: &str, value: f64, parameters: &[(&str, Kind)]| Trial {
+            parameters: parameters
+                .iter()
+                .map(|(id, kind)| Parameter {
+                    parameter_id: id.to_string(),
+                    value: Some(prost_types::Value {
+                        kind: Some(kind.clone()),
+                    }),
+                })
+                .collect(),
+            ..trial(id, "m", value)
+        };
+
+        let trials: Vec<Trial> = (0..4)
+            .map(|i| {
+                let x = i as f64;
+                with_parameters(
+                    &i.to_string(),
+                    2.0 * x,
+                    &[
+                        ("x", Kind::NumberValue(x)),
+                        ("constant", Kind::NumberValue(1.0)),
+                        ("c", Kind::StringValue("a".to_string())),
+                    ],
+                )
+            })
+            .collect();
+
+        let importance = parameter_importance(&trials, "m", GoalType::Minimize);
+        assert_eq!(importance.len(), 1);
+        assert!((importance["x"] + 1.0).abs() < 1e-9);
+
+        let importance = parameter_importance(&trials, "m", GoalType::Maximize);
+        assert!((importance["x"] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn it_counts_trials_by_state() {
+        let with_state = |state: trial::State| Trial {
+            state: state as i32,
+            ..Default::default()
+        };
+        let trials = vec![
+            with_state(trial::State::Active),
+            with_state(trial::State::Active),
+            with_state(trial::State::Requested),
+            with_state(trial::State::Succeeded),
+            Trial {
+                state: 42,
+                ..Default::default()
+            },
+        ];
+
+        let counts = count_by_state(&trials);
+        assert_eq!(counts[&trial::State::Active], 2);
+        assert_eq!(counts[&trial::State::Requested], 1);
+        assert_eq!(counts[&trial::State::Succeeded], 1);
+        assert_eq!(counts[&trial::State::Unspecified], 1);
+        assert_eq!(count_in_progress(&counts), 3);
+
+        assert_eq!(progress(&counts, 4), 0.25);
+        assert_eq!(progress(&counts, 1), 1.0);
+        assert_eq!(progress(&counts, 0), 1.0);
+    }
+
+    #[test]
+    fn it_tells_whether_all_the_trials_are_complete() {
+        let counts = |states: &[(trial::State, usize)]| states.iter().copied().collect();
+
+        assert!(is_complete(&counts(&[
+            (trial::State::Succeeded, 3),
+            (trial::State::Infeasible, 1),
+        ])));
+        assert!(!is_complete(&counts(&[
+            (trial::State::Succeeded, 3),
+            (trial::State::Active, 1),
+        ])));
+        assert!(!is_complete(&counts(&[(trial::State::Unspecified, 1)])));
+        assert!(!is_complete(&HashMap::new()));
+    }
+
+    #[test]
+    fn it_selects_the_best_measurement_with_a_tie_break() {
+        let measurement = |step_count: i64, seconds: i64, value: f64| Measurement {
+            step_count,
+            elapsed_duration: Some(prost_types::Duration { seconds, nanos: 0 }),
+            metrics: vec![measurement::Metric {
+                metric_id: "m".to_string(),
+                value,
+            }],
+        };
+        let measurements = vec![
+            measurement(2, 30, 1.0),
+            measurement(3, 10, 1.0),
+            measurement(1, 20, 1.0),
+            measurement(4, 5, 0.5),
+        ];
+
+        let best = |goal, tie_break| {
+            best_measurement(&measurements, "m", goal, tie_break)
+                .unwrap()
+                .step_count
+        };
+
+        assert_eq!(best(GoalType::Maximize, TieBreak::default()), 1);
+        assert_eq!(best(GoalType::Maximize, TieBreak::LaterStep), 3);
+        assert_eq!(best(GoalType::Maximize, TieBreak::ShorterElapsed), 3);
+        assert_eq!(best(GoalType::Minimize, TieBreak::EarlierStep), 4);
+        assert!(best_measurement(
+            &measurements,
+            "none",
+            GoalType::Maximize,
+            TieBreak::default()
+        )
+        .is_none());
+    }
+}
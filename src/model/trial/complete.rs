@@ -14,8 +14,28 @@
 
 //! Trial complete request builder.
 
-use crate::google::cloud::aiplatform::v1::CompleteTrialRequest;
-use crate::{Measurement, TrialName};
+use crate::google::cloud::aiplatform::v1::{trial, CompleteTrialRequest};
+use crate::trial::measurement::IntoMetrics;
+use crate::{Measurement, Trial, TrialName};
+
+/// Outcome of [crate::VizierClient::complete_trial].
+#[derive(Clone, Debug)]
+pub struct CompletedTrial {
+    /// The completed trial.
+    pub trial: Trial,
+    /// Whether the trial had already been completed before the call - in which case
+    /// `trial` is its current state as fetched from the service.
+    pub already_completed: bool,
+}
+
+/// Returns true if `trial` is in a final state ([trial::State::Succeeded] or
+/// [trial::State::Infeasible]).
+pub fn is_completed(trial: &Trial) -> bool {
+    matches!(
+        trial::State::from_i32(trial.state),
+        Some(trial::State::Succeeded | trial::State::Infeasible)
+    )
+}
 
 /// Final measurement or reason for a trial to have ended.
 pub enum FinalMeasurementOrReason {
@@ -25,6 +45,14 @@ pub enum FinalMeasurementOrReason {
     Reason(String),
 }
 
+impl FinalMeasurementOrReason {
+    /// Creates a [FinalMeasurementOrReason::FinalMeasurement] from anything implementing
+    /// [IntoMetrics].
+    pub fn from_metrics(step_count: i64, metrics: impl IntoMetrics) -> Self {
+        FinalMeasurementOrReason::FinalMeasurement(Measurement::from_metrics(step_count, metrics))
+    }
+}
+
 /// [CompleteTrialRequest] builder.
 pub struct RequestBuilder {
     trial_name: TrialName,
@@ -57,3 +85,21 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_tells_completed_trials() {
+        let with_state = |state: trial::State| Trial {
+            state: state as i32,
+            ..Default::default()
+        };
+
+        assert!(is_completed(&with_state(trial::State::Succeeded)));
+        assert!(is_completed(&with_state(trial::State::Infeasible)));
+        assert!(!is_completed(&with_state(trial::State::Active)));
+        assert!(!is_completed(&with_state(trial::State::Stopping)));
+    }
+}
@@ -14,18 +14,87 @@
 
 //! Trial create request builder.
 
-use crate::google::cloud::aiplatform::v1::CreateTrialRequest;
+use prost_types::value::Kind;
+use prost_types::Value;
+
+use crate::google::cloud::aiplatform::v1::trial::{Parameter, State};
+use crate::google::cloud::aiplatform::v1::{CreateTrialRequest, Measurement, Trial};
 use crate::StudyName;
 
+/// [Trial] builder - to create a trial with [RequestBuilder].
+#[derive(Clone, Debug, Default)]
+pub struct TrialBuilder {
+    trial: Trial,
+}
+
+impl TrialBuilder {
+    /// Creates a new instance of [Trial] builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a parameter with a numeric value - for the `DOUBLE`, `INTEGER` and
+    /// `DISCRETE` parameters.
+    pub fn add_parameter_double(self, parameter_id: impl Into<String>, value: f64) -> Self {
+        self.add_parameter(parameter_id, Kind::NumberValue(value))
+    }
+
+    /// Adds a parameter with a string value - for the `CATEGORICAL` parameters.
+    pub fn add_parameter_categorical(
+        self,
+        parameter_id: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.add_parameter(parameter_id, Kind::StringValue(value.into()))
+    }
+
+    fn add_parameter(mut self, parameter_id: impl Into<String>, kind: Kind) -> Self {
+        self.trial.parameters.push(Parameter {
+            parameter_id: parameter_id.into(),
+            value: Some(Value { kind: Some(kind) }),
+        });
+        self
+    }
+
+    /// Sets the client id.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.trial.client_id = client_id.into();
+        self
+    }
+
+    /// Sets the [State].
+    pub fn with_state(mut self, state: State) -> Self {
+        self.trial.state = state as i32;
+        self
+    }
+
+    /// Adds a measurement.
+    pub fn add_measurement(mut self, measurement: Measurement) -> Self {
+        self.trial.measurements.push(measurement);
+        self
+    }
+
+    /// Sets the reason why the trial is infeasible.
+    pub fn with_infeasible_reason(mut self, infeasible_reason: impl Into<String>) -> Self {
+        self.trial.infeasible_reason = infeasible_reason.into();
+        self
+    }
+
+    /// Builds the [Trial].
+    pub fn build(self) -> Trial {
+        self.trial
+    }
+}
+
 /// [CreateTrialRequest] builder.
 pub struct RequestBuilder {
     study_name: StudyName,
-    trial: crate::google::cloud::aiplatform::v1::Trial,
+    trial: Trial,
 }
 
 impl RequestBuilder {
     /// Creates a new instance of [CreateTrialRequest] builder.
-    pub fn new(study_name: StudyName, trial: crate::google::cloud::aiplatform::v1::Trial) -> Self {
+    pub fn new(study_name: StudyName, trial: Trial) -> Self {
         RequestBuilder { study_name, trial }
     }
 
@@ -37,3 +106,53 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::measurement::Metric;
+
+    #[test]
+    fn it_builds_a_trial_with_mixed_parameters() {
+        let measurement = Measurement {
+            step_count: 1,
+            metrics: vec![Metric {
+                metric_id: "m1".to_string(),
+                value: 0.5,
+            }],
+            ..Default::default()
+        };
+
+        let trial = TrialBuilder::new()
+            .add_parameter_double("a", 2.0)
+            .add_parameter_categorical("b", "relu")
+            .with_client_id("client")
+            .with_state(State::Active)
+            .add_measurement(measurement.clone())
+            .build();
+
+        assert_eq!(
+            trial,
+            Trial {
+                parameters: vec![
+                    Parameter {
+                        parameter_id: "a".to_string(),
+                        value: Some(Value {
+                            kind: Some(Kind::NumberValue(2.0)),
+                        }),
+                    },
+                    Parameter {
+                        parameter_id: "b".to_string(),
+                        value: Some(Value {
+                            kind: Some(Kind::StringValue("relu".to_string())),
+                        }),
+                    },
+                ],
+                client_id: "client".to_string(),
+                state: State::Active as i32,
+                measurements: vec![measurement],
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -12,10 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Trial early stopping request builder.
+//! Trial early stopping request builder and decision.
 
-use crate::google::cloud::aiplatform::v1::CheckTrialEarlyStoppingStateRequest;
-use crate::TrialName;
+use std::future::Future;
+
+use crate::google::cloud::aiplatform::v1::{
+    CheckTrialEarlyStoppingStateRequest, CheckTrialEarlyStoppingStateResponse, Trial,
+};
+use crate::{Error, TrialName};
 
 /// [CheckTrialEarlyStoppingStateRequest] builder.
 pub struct RequestBuilder {
@@ -35,3 +39,65 @@ impl RequestBuilder {
         }
     }
 }
+
+/// Outcome of [crate::VizierClient::report_and_maybe_stop].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopDecision {
+    /// The service recommended to stop the trial, which has been stopped.
+    Stopped(Trial),
+    /// The service did not recommend to stop the trial.
+    Continue,
+}
+
+/// Stops the trial with `stop` if `response` recommends it.
+pub(crate) async fn stop_if_recommended<F, Fut>(
+    response: CheckTrialEarlyStoppingStateResponse,
+    stop: F,
+) -> Result<StopDecision, Error>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Trial, Error>>,
+{
+    if response.should_stop {
+        Ok(StopDecision::Stopped(stop().await?))
+    } else {
+        Ok(StopDecision::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trial() -> Trial {
+        Trial {
+            name: "projects/p/locations/l/studies/s/trials/1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_stops_the_trial_when_recommended() {
+        let response = CheckTrialEarlyStoppingStateResponse { should_stop: true };
+
+        let decision = stop_if_recommended(response, || async { Ok(trial()) })
+            .await
+            .unwrap();
+
+        assert_eq!(decision, StopDecision::Stopped(trial()));
+    }
+
+    #[tokio::test]
+    async fn it_continues_when_not_recommended() {
+        let response = CheckTrialEarlyStoppingStateResponse { should_stop: false };
+
+        let decision = stop_if_recommended(response, || async {
+            unreachable!("the trial must not be stopped")
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(decision, StopDecision::Continue);
+    }
+}
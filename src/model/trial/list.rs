@@ -14,9 +14,47 @@
 
 //! Trial list request builder.
 
-use crate::google::cloud::aiplatform::v1::ListTrialsRequest;
+use futures::{Stream, TryStreamExt};
+
+use crate::google::cloud::aiplatform::v1::trial::State;
+use crate::google::cloud::aiplatform::v1::{ListTrialsRequest, Trial};
 use crate::StudyName;
 
+/// Keeps the trials of `trials` in `state`.
+///
+/// The trials in a state unknown to this version of the crate are skipped with a
+/// warning.
+pub(crate) fn filter_by_state<E>(
+    trials: impl Stream<Item = Result<Trial, E>>,
+    state: State,
+) -> impl Stream<Item = Result<Trial, E>> {
+    trials.try_filter(move |trial| {
+        let keep = match State::from_i32(trial.state) {
+            Some(s) => s == state,
+            None => {
+                log::warn!(
+                    "skipping trial {} in unknown state {}",
+                    trial.name,
+                    trial.state
+                );
+                false
+            }
+        };
+        futures::future::ready(keep)
+    })
+}
+
+/// Maximum number of trials in a page.
+pub const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Error returned by [RequestBuilder::try_build].
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The page size is negative or above [MAX_PAGE_SIZE].
+    #[error("invalid page_size {0} - must be between 0 and {MAX_PAGE_SIZE}")]
+    InvalidPageSize(i32),
+}
+
 /// [ListTrialsRequest] builder.
 pub struct RequestBuilder {
     study_name: StudyName,
@@ -34,7 +72,7 @@ impl RequestBuilder {
         }
     }
 
-    /// Sets the page size.
+    /// Sets the page size - 0 for the default of the service, at most [MAX_PAGE_SIZE].
     pub fn with_page_size(mut self, page_size: i32) -> Self {
         self.page_size = Some(page_size);
         self
@@ -42,12 +80,26 @@ impl RequestBuilder {
 
     /// Sets the page token to get a following page - See
     /// [`next_page_token`](ListTrialsResponse.next_page_token).
-    pub fn with_page_token(mut self, page_token: String) -> Self {
-        self.page_token = Some(page_token);
+    pub fn with_page_token(mut self, page_token: impl Into<String>) -> Self {
+        self.page_token = Some(page_token.into());
         self
     }
 
-    /// Builds the [ListTrialsRequest].
+    /// Builds the [ListTrialsRequest], checking the page size.
+    ///
+    /// Fails with [Error::InvalidPageSize] if the page size is negative or above
+    /// [MAX_PAGE_SIZE].
+    pub fn try_build(self) -> Result<ListTrialsRequest, Error> {
+        match self.page_size {
+            Some(page_size) if !(0..=MAX_PAGE_SIZE).contains(&page_size) => {
+                Err(Error::InvalidPageSize(page_size))
+            }
+            _ => Ok(self.build()),
+        }
+    }
+
+    /// Builds the [ListTrialsRequest] - the page size is not checked, see
+    /// [RequestBuilder::try_build].
     pub fn build(self) -> ListTrialsRequest {
         ListTrialsRequest {
             parent: self.study_name.into(),
@@ -56,3 +108,86 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    fn trial(name: &str, state: i32) -> Trial {
+        Trial {
+            name: name.to_string(),
+            state,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_filters_trials_by_state() {
+        let trials = [
+            trial("1", State::Active as i32),
+            trial("2", State::Succeeded as i32),
+            trial("3", State::Infeasible as i32),
+            trial("4", 42),
+            trial("5", State::Active as i32),
+        ];
+
+        let active: Vec<Trial> =
+            filter_by_state(stream::iter(trials.clone().map(Ok::<_, ()>)), State::Active)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(
+            active,
+            [
+                trial("1", State::Active as i32),
+                trial("5", State::Active as i32)
+            ]
+        );
+
+        let infeasible: Vec<Trial> =
+            filter_by_state(stream::iter(trials.map(Ok::<_, ()>)), State::Infeasible)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(infeasible, [trial("3", State::Infeasible as i32)]);
+    }
+
+    #[test]
+    fn it_accepts_str_and_string() {
+        let study_name = StudyName::new("p".to_string(), "l".to_string(), "s".to_string());
+
+        assert_eq!(
+            RequestBuilder::new(study_name.clone())
+                .with_page_token("t")
+                .build(),
+            RequestBuilder::new(study_name)
+                .with_page_token("t".to_string())
+                .build()
+        );
+    }
+
+    #[test]
+    fn it_checks_the_page_size() {
+        let study_name = StudyName::new("p".to_string(), "l".to_string(), "s".to_string());
+        let builder = || RequestBuilder::new(study_name.clone());
+
+        assert!(matches!(
+            builder().with_page_size(-5).try_build(),
+            Err(Error::InvalidPageSize(-5))
+        ));
+        assert_eq!(
+            builder().with_page_size(0).try_build().unwrap().page_size,
+            0
+        );
+        assert_eq!(
+            builder().with_page_size(10).try_build().unwrap().page_size,
+            10
+        );
+        assert!(matches!(
+            builder().with_page_size(MAX_PAGE_SIZE + 1).try_build(),
+            Err(Error::InvalidPageSize(1001))
+        ));
+    }
+}
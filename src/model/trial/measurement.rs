@@ -0,0 +1,498 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measurement helpers.
+//!
+//! # Metadata
+//!
+//! The v1 [Measurement] has no field for arbitrary key/value metadata (such as a
+//! checkpoint path): it only carries a `step_count`, an `elapsed_duration` and metrics -
+//! which must be defined in the metrics of the study spec, so they cannot be used to
+//! smuggle annotations. The `infeasible_reason` of a trial is only set when the trial is
+//! completed as infeasible and is not suited either.
+//!
+//! The suggested convention is to use the `step_count` for the training progress (e.g.
+//! the epoch number) and to record any other metadata outside of Vizier, keyed by the
+//! trial name and the `step_count` of the measurement.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::google::cloud::aiplatform::v1::measurement;
+use crate::Measurement;
+
+/// Error returned when aggregating measurements.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// There is nothing to aggregate.
+    #[error("no measurement to aggregate")]
+    NoMeasurement,
+    /// The measurements do not report the same metrics.
+    #[error("the measurements do not report the same metrics")]
+    InconsistentMetrics,
+    /// Measurements of the same step report different values for the same metric.
+    #[error("conflicting values for metric '{metric_id}' at step {step_count}")]
+    ConflictingMetric {
+        /// The step of the measurements.
+        step_count: i64,
+        /// The metric with conflicting values.
+        metric_id: String,
+    },
+}
+
+/// How to handle a metric reported with different values at the same step in
+/// [merge_measurements].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MergeConflict {
+    /// Keep the value of the last measurement.
+    LastWins,
+    /// Fail with [Error::ConflictingMetric].
+    Error,
+}
+
+/// Merges the measurements with the same `step_count` by combining their metrics - for
+/// metrics of the same step reported by different sources.
+///
+/// The merged measurements are in the order of the first occurrence of their step, with
+/// their metrics in order of first occurrence, and the largest `elapsed_duration` of the
+/// step. A metric reported several times with the same value is not a conflict.
+pub fn merge_measurements(
+    measurements: &[Measurement],
+    on_conflict: MergeConflict,
+) -> Result<Vec<Measurement>, Error> {
+    let mut merged: Vec<Measurement> = vec![];
+
+    for m in measurements {
+        let Some(target) = merged.iter_mut().find(|t| t.step_count == m.step_count) else {
+            merged.push(m.clone());
+            continue;
+        };
+
+        if let Some(d) = &m.elapsed_duration {
+            let longer = target
+                .elapsed_duration
+                .as_ref()
+                .is_none_or(|t| (t.seconds, t.nanos) < (d.seconds, d.nanos));
+            if longer {
+                target.elapsed_duration = Some(d.clone());
+            }
+        }
+
+        for metric in &m.metrics {
+            match target
+                .metrics
+                .iter_mut()
+                .find(|t| t.metric_id == metric.metric_id)
+            {
+                None => target.metrics.push(metric.clone()),
+                Some(t) if t.value == metric.value => {}
+                Some(t) => match on_conflict {
+                    MergeConflict::LastWins => t.value = metric.value,
+                    MergeConflict::Error => {
+                        return Err(Error::ConflictingMetric {
+                            step_count: m.step_count,
+                            metric_id: metric.metric_id.clone(),
+                        })
+                    }
+                },
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+/// How to combine the values of a metric across several measurements.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AggStrategy {
+    /// Arithmetic mean.
+    Mean,
+    /// Median - the mean of the two middle values for an even count.
+    Median,
+    /// Smallest value.
+    Min,
+    /// Largest value.
+    Max,
+}
+
+impl AggStrategy {
+    fn apply(self, mut values: Vec<f64>) -> f64 {
+        match self {
+            AggStrategy::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            AggStrategy::Median => {
+                values.sort_by(f64::total_cmp);
+                let mid = values.len() / 2;
+                if values.len() % 2 == 1 {
+                    values[mid]
+                } else {
+                    (values[mid - 1] + values[mid]) / 2.0
+                }
+            }
+            AggStrategy::Min => values.into_iter().fold(f64::INFINITY, f64::min),
+            AggStrategy::Max => values.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// Conversion into the metrics of a [Measurement].
+///
+/// Implemented for `HashMap<String, f64>` (metrics sorted by id), `Vec<(String, f64)>`
+/// and `Vec<measurement::Metric>`. For a custom metrics struct, map each field to a
+/// metric id:
+///
+/// ```
+/// struct Metrics {
+///     accuracy: f64,
+///     loss: f64,
+/// }
+///
+/// impl IntoMetrics for Metrics {
+///     fn into_metrics(self) -> Vec<measurement::Metric> {
+///         vec![
+///             ("accuracy".to_string(), self.accuracy),
+///             ("loss".to_string(), self.loss),
+///         ]
+///         .into_metrics()
+///     }
+/// }
+///
+/// let m = Measurement::from_metrics(
+///     1,
+///     Metrics {
+///         accuracy: 0.9,
+///         loss: 0.1,
+///     },
+/// );
+/// ```
+pub trait IntoMetrics {
+    /// Converts `self` into a list of metrics.
+    fn into_metrics(self) -> Vec<measurement::Metric>;
+}
+
+impl IntoMetrics for Vec<measurement::Metric> {
+    fn into_metrics(self) -> Vec<measurement::Metric> {
+        self
+    }
+}
+
+impl IntoMetrics for Vec<(String, f64)> {
+    fn into_metrics(self) -> Vec<measurement::Metric> {
+        self.into_iter()
+            .map(|(metric_id, value)| measurement::Metric { metric_id, value })
+            .collect()
+    }
+}
+
+impl IntoMetrics for HashMap<String, f64> {
+    fn into_metrics(self) -> Vec<measurement::Metric> {
+        let mut metrics: Vec<(String, f64)> = self.into_iter().collect();
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+        metrics.into_metrics()
+    }
+}
+
+/// Converts an elapsed duration to a [prost_types::Duration].
+///
+/// Unlike `try_into()`, this does not fail: a duration too large to fit (over
+/// `i64::MAX` seconds) is saturated to the largest [prost_types::Duration].
+pub fn elapsed(d: Duration) -> prost_types::Duration {
+    d.try_into().unwrap_or(prost_types::Duration {
+        seconds: i64::MAX,
+        nanos: 999_999_999,
+    })
+}
+
+/// [Measurement] builder.
+#[derive(Clone, Debug, Default)]
+pub struct MeasurementBuilder {
+    step_count: i64,
+    elapsed_duration: Option<prost_types::Duration>,
+    metrics: Vec<measurement::Metric>,
+}
+
+impl MeasurementBuilder {
+    /// Creates a new instance of [Measurement] builder - at step 0, without elapsed
+    /// duration nor metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the step count.
+    pub fn with_step_count(mut self, step_count: i64) -> Self {
+        self.step_count = step_count;
+        self
+    }
+
+    /// Sets the elapsed duration - saturated if it does not fit in a
+    /// [prost_types::Duration], see [elapsed].
+    pub fn with_elapsed_duration(mut self, elapsed_duration: Duration) -> Self {
+        self.elapsed_duration = Some(elapsed(elapsed_duration));
+        self
+    }
+
+    /// Adds a metric.
+    pub fn add_metric(mut self, metric_id: impl Into<String>, value: f64) -> Self {
+        self.metrics.push(measurement::Metric {
+            metric_id: metric_id.into(),
+            value,
+        });
+        self
+    }
+
+    /// Sets the metrics, replacing the ones added so far.
+    pub fn with_metrics(mut self, metrics: impl IntoMetrics) -> Self {
+        self.metrics = metrics.into_metrics();
+        self
+    }
+
+    /// Builds the [Measurement].
+    pub fn build(self) -> Measurement {
+        Measurement {
+            elapsed_duration: self.elapsed_duration,
+            step_count: self.step_count,
+            metrics: self.metrics,
+        }
+    }
+}
+
+impl Measurement {
+    /// Creates a [Measurement] at `step_count` from anything implementing [IntoMetrics].
+    pub fn from_metrics(step_count: i64, metrics: impl IntoMetrics) -> Measurement {
+        Measurement {
+            step_count,
+            metrics: metrics.into_metrics(),
+            ..Default::default()
+        }
+    }
+
+    /// Aggregates repeated evaluations of the same parameters into a single measurement.
+    ///
+    /// Each metric is combined with `strategy`. All the measurements must report the same
+    /// set of metric ids. The resulting measurement has the largest `step_count` and
+    /// `elapsed_duration` of the inputs, and its metrics are in the order of the first
+    /// measurement.
+    pub fn aggregate(
+        measurements: &[Measurement],
+        strategy: AggStrategy,
+    ) -> Result<Measurement, Error> {
+        let first = measurements.first().ok_or(Error::NoMeasurement)?;
+
+        let mut metric_ids: Vec<&str> =
+            first.metrics.iter().map(|m| m.metric_id.as_str()).collect();
+        metric_ids.sort_unstable();
+        for m in measurements {
+            let mut ids: Vec<&str> = m.metrics.iter().map(|m| m.metric_id.as_str()).collect();
+            ids.sort_unstable();
+            if ids != metric_ids {
+                return Err(Error::InconsistentMetrics);
+            }
+        }
+
+        let metrics = first
+            .metrics
+            .iter()
+            .map(|metric| {
+                let values = measurements
+                    .iter()
+                    .flat_map(|m| &m.metrics)
+                    .filter(|m| m.metric_id == metric.metric_id)
+                    .map(|m| m.value)
+                    .collect();
+
+                measurement::Metric {
+                    metric_id: metric.metric_id.clone(),
+                    value: strategy.apply(values),
+                }
+            })
+            .collect();
+
+        let step_count = measurements.iter().map(|m| m.step_count).max().unwrap_or(0);
+        let elapsed_duration = measurements
+            .iter()
+            .filter_map(|m| m.elapsed_duration.clone())
+            .max_by_key(|d| (d.seconds, d.nanos));
+
+        Ok(Measurement {
+            elapsed_duration,
+            step_count,
+            metrics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(step_count: i64, metrics: &[(&str, f64)]) -> Measurement {
+        Measurement {
+            step_count,
+            metrics: metrics
+                .iter()
+                .map(|(id, value)| measurement::Metric {
+                    metric_id: id.to_string(),
+                    value: *value,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_aggregates_measurements() {
+        let measurements = vec![
+            measurement(1, &[("a", 1.0), ("b", 10.0)]),
+            measurement(3, &[("b", 40.0), ("a", 2.0)]),
+            measurement(2, &[("a", 6.0), ("b", 20.0)]),
+        ];
+
+        let values = |strategy| {
+            let m = Measurement::aggregate(&measurements, strategy).unwrap();
+            assert_eq!(m.step_count, 3);
+            m.metrics
+                .iter()
+                .map(|m| (m.metric_id.clone(), m.value))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            values(AggStrategy::Mean),
+            vec![("a".to_string(), 3.0), ("b".to_string(), 70.0 / 3.0)]
+        );
+        assert_eq!(
+            values(AggStrategy::Median),
+            vec![("a".to_string(), 2.0), ("b".to_string(), 20.0)]
+        );
+        assert_eq!(
+            values(AggStrategy::Min),
+            vec![("a".to_string(), 1.0), ("b".to_string(), 10.0)]
+        );
+        assert_eq!(
+            values(AggStrategy::Max),
+            vec![("a".to_string(), 6.0), ("b".to_string(), 40.0)]
+        );
+    }
+
+    #[test]
+    fn it_rejects_inconsistent_metrics() {
+        let measurements = vec![measurement(1, &[("a", 1.0)]), measurement(2, &[("b", 1.0)])];
+
+        assert_eq!(
+            Measurement::aggregate(&measurements, AggStrategy::Mean),
+            Err(Error::InconsistentMetrics)
+        );
+        assert_eq!(
+            Measurement::aggregate(&[], AggStrategy::Mean),
+            Err(Error::NoMeasurement)
+        );
+    }
+
+    #[test]
+    fn it_builds_measurements_from_metrics() {
+        let from_map = Measurement::from_metrics(
+            2,
+            HashMap::from([("b".to_string(), 2.0), ("a".to_string(), 1.0)]),
+        );
+        let from_vec =
+            Measurement::from_metrics(2, vec![("a".to_string(), 1.0), ("b".to_string(), 2.0)]);
+
+        assert_eq!(from_map, measurement(2, &[("a", 1.0), ("b", 2.0)]));
+        assert_eq!(from_vec, from_map);
+    }
+
+    #[test]
+    fn it_merges_measurements_of_the_same_step() {
+        let measurements = vec![
+            measurement(1, &[("a", 1.0)]),
+            measurement(2, &[("a", 2.0)]),
+            measurement(1, &[("b", 10.0), ("a", 1.0)]),
+            measurement(2, &[("a", 3.0)]),
+        ];
+
+        assert_eq!(
+            merge_measurements(&measurements, MergeConflict::LastWins),
+            Ok(vec![
+                measurement(1, &[("a", 1.0), ("b", 10.0)]),
+                measurement(2, &[("a", 3.0)]),
+            ])
+        );
+        assert_eq!(
+            merge_measurements(&measurements, MergeConflict::Error),
+            Err(Error::ConflictingMetric {
+                step_count: 2,
+                metric_id: "a".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn it_converts_elapsed_durations() {
+        assert_eq!(
+            elapsed(Duration::from_millis(10_250)),
+            prost_types::Duration {
+                seconds: 10,
+                nanos: 250_000_000,
+            }
+        );
+        assert_eq!(
+            elapsed(Duration::MAX),
+            prost_types::Duration {
+                seconds: i64::MAX,
+                nanos: 999_999_999,
+            }
+        );
+    }
+
+    #[test]
+    fn it_builds_measurements() {
+        let m = MeasurementBuilder::new()
+            .with_step_count(3)
+            .with_elapsed_duration(Duration::from_millis(1500))
+            .add_metric("m1", 0.5)
+            .add_metric("m2".to_string(), 1.5)
+            .build();
+
+        assert_eq!(
+            m,
+            Measurement {
+                elapsed_duration: Some(prost_types::Duration {
+                    seconds: 1,
+                    nanos: 500_000_000,
+                }),
+                step_count: 3,
+                metrics: vec![
+                    measurement::Metric {
+                        metric_id: "m1".to_string(),
+                        value: 0.5,
+                    },
+                    measurement::Metric {
+                        metric_id: "m2".to_string(),
+                        value: 1.5,
+                    },
+                ],
+            }
+        );
+
+        let m = MeasurementBuilder::new()
+            .add_metric("m0", 0.0)
+            .with_metrics(vec![("m1".to_string(), 2.0)])
+            .build();
+        assert_eq!(m.elapsed_duration, None);
+        assert_eq!(m.step_count, 0);
+        assert_eq!(
+            m,
+            Measurement::from_metrics(0, vec![("m1".to_string(), 2.0)])
+        );
+    }
+}
@@ -12,10 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Trial list optimal request builder.
+//! Trial list optimal request builder and results.
+
+use std::collections::HashMap;
 
 use crate::google::cloud::aiplatform::v1::ListOptimalTrialsRequest;
-use crate::StudyName;
+use crate::trial::params::{parameters, ParameterMap};
+use crate::trial::{final_metrics, ToTrialName, TrialName};
+use crate::{StudyName, Trial};
+
+/// An optimal trial - see [crate::VizierClient::optimal_trials].
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimalTrial {
+    /// The name of the trial.
+    pub name: TrialName,
+    /// The parameters of the trial.
+    pub parameters: ParameterMap,
+    /// The metrics of the final measurement of the trial by metric id - empty if the
+    /// trial has no final measurement.
+    pub metrics: HashMap<String, f64>,
+}
+
+impl From<&Trial> for OptimalTrial {
+    fn from(trial: &Trial) -> Self {
+        OptimalTrial {
+            name: trial.to_trial_name(),
+            parameters: parameters(trial),
+            metrics: final_metrics(trial),
+        }
+    }
+}
 
 /// [ListOptimalTrialsRequest] builder.
 pub struct RequestBuilder {
@@ -35,3 +61,48 @@ impl RequestBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::measurement::Metric;
+    use crate::google::cloud::aiplatform::v1::Measurement;
+    use crate::trial::create::TrialBuilder;
+
+    #[test]
+    fn it_decodes_optimal_trials() {
+        let trial = Trial {
+            name: "projects/p/locations/l/studies/s/trials/3".to_string(),
+            final_measurement: Some(Measurement {
+                metrics: vec![
+                    Metric {
+                        metric_id: "accuracy".to_string(),
+                        value: 0.9,
+                    },
+                    Metric {
+                        metric_id: "latency".to_string(),
+                        value: 12.0,
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..TrialBuilder::new()
+                .add_parameter_double("lr", 0.01)
+                .add_parameter_categorical("activation", "relu")
+                .build()
+        };
+
+        let optimal = OptimalTrial::from(&trial);
+
+        assert_eq!(String::from(&optimal.name), trial.name);
+        assert_eq!(optimal.parameters.get_f64("lr"), Some(0.01));
+        assert_eq!(optimal.parameters.get_str("activation"), Some("relu"));
+        assert_eq!(
+            optimal.metrics,
+            HashMap::from([("accuracy".to_string(), 0.9), ("latency".to_string(), 12.0)])
+        );
+
+        let pending = OptimalTrial::from(&Trial::default());
+        assert!(pending.metrics.is_empty());
+    }
+}
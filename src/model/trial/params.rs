@@ -0,0 +1,229 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parameters of a trial.
+
+use std::collections::HashMap;
+
+use prost_types::value::Kind;
+use prost_types::Value;
+
+use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::ParameterValueSpec;
+use crate::google::cloud::aiplatform::v1::StudySpec;
+use crate::Trial;
+
+/// Parameters of a trial with typed accessors - see [parameters].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterMap(HashMap<String, Value>);
+
+impl ParameterMap {
+    /// Returns the raw value of the parameter `parameter_id`.
+    pub fn get(&self, parameter_id: &str) -> Option<&Value> {
+        self.0.get(parameter_id)
+    }
+
+    /// Returns the value of a double (or discrete) parameter.
+    pub fn get_f64(&self, parameter_id: &str) -> Option<f64> {
+        match self.get(parameter_id)?.kind.as_ref()? {
+            Kind::NumberValue(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an integer parameter - `None` if the value is not integral.
+    pub fn get_i64(&self, parameter_id: &str) -> Option<i64> {
+        let v = self.get_f64(parameter_id)?;
+        (v.fract() == 0.0).then_some(v as i64)
+    }
+
+    /// Returns the value of a categorical parameter.
+    pub fn get_str(&self, parameter_id: &str) -> Option<&str> {
+        match self.get(parameter_id)?.kind.as_ref()? {
+            Kind::StringValue(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the parameters of a trial as a [ParameterMap].
+pub fn parameters(trial: &Trial) -> ParameterMap {
+    ParameterMap(extract_parameters(trial))
+}
+
+/// Parameters of a trial completed with the defaults of the study spec - see
+/// [with_defaults].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletedParameters {
+    /// The parameter values by parameter id.
+    pub values: HashMap<String, Value>,
+    /// The ids of the parameters whose value is the default declared in the spec, in the
+    /// order of the spec.
+    pub defaulted: Vec<String>,
+}
+
+/// Extracts the parameters of a trial as a map from parameter id to value.
+///
+/// Parameters without a value are skipped.
+pub fn extract_parameters(trial: &Trial) -> HashMap<String, Value> {
+    trial
+        .parameters
+        .iter()
+        .filter_map(|p| Some((p.parameter_id.clone(), p.value.clone()?)))
+        .collect()
+}
+
+/// Returns the default value declared in a [ParameterValueSpec], if any.
+fn default_value(spec: &ParameterValueSpec) -> Option<Value> {
+    let kind = match spec {
+        ParameterValueSpec::DoubleValueSpec(s) => Kind::NumberValue(s.default_value?),
+        ParameterValueSpec::IntegerValueSpec(s) => Kind::NumberValue(s.default_value? as f64),
+        ParameterValueSpec::CategoricalValueSpec(s) => Kind::StringValue(s.default_value.clone()?),
+        ParameterValueSpec::DiscreteValueSpec(s) => Kind::NumberValue(s.default_value?),
+    };
+
+    Some(Value { kind: Some(kind) })
+}
+
+/// Extracts the parameters of a trial and fills the omitted ones with the defaults
+/// declared in the study spec.
+///
+/// Only the top-level (non-conditional) parameters of the spec are filled. A conditional
+/// parameter is omitted by the service when its parent value does not activate it: it is
+/// left absent as it has no meaning for the trial. Parameters without a declared default
+/// are left absent too.
+pub fn with_defaults(study_spec: &StudySpec, trial: &Trial) -> CompletedParameters {
+    let mut values = extract_parameters(trial);
+    let mut defaulted = vec![];
+
+    for p in &study_spec.parameters {
+        if values.contains_key(&p.parameter_id) {
+            continue;
+        }
+
+        if let Some(value) = p.parameter_value_spec.as_ref().and_then(default_value) {
+            values.insert(p.parameter_id.clone(), value);
+            defaulted.push(p.parameter_id.clone());
+        }
+    }
+
+    CompletedParameters { values, defaulted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::cloud::aiplatform::v1::study_spec::parameter_spec::{
+        CategoricalValueSpec, IntegerValueSpec,
+    };
+    use crate::google::cloud::aiplatform::v1::study_spec::ParameterSpec;
+    use crate::google::cloud::aiplatform::v1::trial::Parameter;
+
+    fn number(v: f64) -> Value {
+        Value {
+            kind: Some(Kind::NumberValue(v)),
+        }
+    }
+
+    #[test]
+    fn it_fills_omitted_parameters_with_defaults() {
+        let study_spec = StudySpec {
+            parameters: vec![
+                ParameterSpec {
+                    parameter_id: "a".to_string(),
+                    parameter_value_spec: Some(ParameterValueSpec::IntegerValueSpec(
+                        IntegerValueSpec {
+                            min_value: 0,
+                            max_value: 10,
+                            default_value: Some(3),
+                        },
+                    )),
+                    ..Default::default()
+                },
+                ParameterSpec {
+                    parameter_id: "b".to_string(),
+                    parameter_value_spec: Some(ParameterValueSpec::IntegerValueSpec(
+                        IntegerValueSpec {
+                            min_value: 0,
+                            max_value: 10,
+                            default_value: Some(5),
+                        },
+                    )),
+                    ..Default::default()
+                },
+                ParameterSpec {
+                    parameter_id: "c".to_string(),
+                    parameter_value_spec: Some(ParameterValueSpec::CategoricalValueSpec(
+                        CategoricalValueSpec {
+                            values: vec!["x".to_string()],
+                            default_value: None,
+                        },
+                    )),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let trial = Trial {
+            parameters: vec![Parameter {
+                parameter_id: "b".to_string(),
+                value: Some(number(7.0)),
+            }],
+            ..Default::default()
+        };
+
+        let params = with_defaults(&study_spec, &trial);
+
+        assert_eq!(params.defaulted, vec!["a".to_string()]);
+        assert_eq!(
+            params.values,
+            HashMap::from([
+                ("a".to_string(), number(3.0)),
+                ("b".to_string(), number(7.0))
+            ])
+        );
+    }
+
+    #[test]
+    fn it_reads_typed_parameters() {
+        let trial = Trial {
+            parameters: vec![
+                Parameter {
+                    parameter_id: "n".to_string(),
+                    value: Some(number(4.0)),
+                },
+                Parameter {
+                    parameter_id: "x".to_string(),
+                    value: Some(number(0.5)),
+                },
+                Parameter {
+                    parameter_id: "c".to_string(),
+                    value: Some(Value {
+                        kind: Some(Kind::StringValue("adam".to_string())),
+                    }),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let params = parameters(&trial);
+
+        assert_eq!(params.get_f64("x"), Some(0.5));
+        assert_eq!(params.get_i64("n"), Some(4));
+        assert_eq!(params.get_i64("x"), None);
+        assert_eq!(params.get_str("c"), Some("adam"));
+        assert_eq!(params.get_f64("c"), None);
+        assert_eq!(params.get_f64("missing"), None);
+        assert_eq!(params.get_str("missing"), None);
+    }
+}
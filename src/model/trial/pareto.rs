@@ -0,0 +1,118 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Client-side Pareto front of multi-objective studies.
+
+use crate::google::cloud::aiplatform::v1::study_spec::metric_spec::GoalType;
+use crate::trial::analysis::{final_metric_value, is_better};
+use crate::Trial;
+
+/// Returns the trials that are not dominated by any other trial on `metrics` - the
+/// Pareto front - in the order of `trials`.
+///
+/// A trial dominates another if it is at least as good on every metric and strictly
+/// better on one, each metric being optimized towards its [GoalType]
+/// ([GoalType::Unspecified] is treated as [GoalType::Maximize]). Only the final
+/// measurements are considered: trials missing one of the metrics (or with a NaN value)
+/// are skipped. Trials with identical values are all kept.
+///
+/// Unlike [crate::VizierClient::optimal_trials], computed by the service, this works
+/// offline on any set of trials.
+pub fn pareto_front<'a>(trials: &'a [Trial], metrics: &[(String, GoalType)]) -> Vec<&'a Trial> {
+    let candidates: Vec<(&Trial, Vec<f64>)> = trials
+        .iter()
+        .filter_map(|t| {
+            let values = metrics
+                .iter()
+                .map(|(metric_id, _)| final_metric_value(t, metric_id).filter(|v| !v.is_nan()))
+                .collect::<Option<Vec<f64>>>()?;
+            Some((t, values))
+        })
+        .collect();
+
+    let dominates = |a: &[f64], b: &[f64]| {
+        let mut strictly_better = false;
+        for ((&a, &b), (_, goal)) in a.iter().zip(b).zip(metrics) {
+            if is_better(*goal, b, a) {
+                return false;
+            }
+            strictly_better |= is_better(*goal, a, b);
+        }
+        strictly_better
+    };
+
+    candidates
+        .iter()
+        .filter(|(_, values)| !candidates.iter().any(|(_, other)| dominates(other, values)))
+        .map(|(t, _)| *t)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::measurement::MeasurementBuilder;
+
+    fn trial(id: &str, accuracy: f64, latency: Option<f64>) -> Trial {
+        let mut measurement = MeasurementBuilder::new().add_metric("accuracy", accuracy);
+        if let Some(latency) = latency {
+            measurement = measurement.add_metric("latency", latency);
+        }
+
+        Trial {
+            id: id.to_string(),
+            final_measurement: Some(measurement.build()),
+            ..Default::default()
+        }
+    }
+
+    fn ids(trials: Vec<&Trial>) -> Vec<&str> {
+        trials.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn it_computes_the_pareto_front() {
+        let metrics = [
+            ("accuracy".to_string(), GoalType::Maximize),
+            ("latency".to_string(), GoalType::Minimize),
+        ];
+        let trials = vec![
+            trial("1", 0.9, Some(10.0)),
+            // dominated by 1: less accurate and slower
+            trial("2", 0.8, Some(12.0)),
+            trial("3", 0.7, Some(2.0)),
+            // dominated by 3: as accurate but slower
+            trial("4", 0.7, Some(3.0)),
+            trial("5", 0.95, Some(20.0)),
+            // same values as 1
+            trial("6", 0.9, Some(10.0)),
+            // no latency
+            trial("7", 0.99, None),
+            trial("8", 0.99, Some(f64::NAN)),
+            Trial::default(),
+        ];
+
+        assert_eq!(ids(pareto_front(&trials, &metrics)), ["1", "3", "5", "6"]);
+
+        // a single metric
+        assert_eq!(
+            ids(pareto_front(
+                &trials,
+                &[("accuracy".to_string(), GoalType::Unspecified)]
+            )),
+            ["7", "8"]
+        );
+        assert!(pareto_front(&[], &metrics).is_empty());
+    }
+}
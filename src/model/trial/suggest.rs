@@ -14,8 +14,23 @@
 
 //! Trial suggest request builder.
 
-use crate::google::cloud::aiplatform::v1::SuggestTrialsRequest;
-use crate::StudyName;
+use crate::google::cloud::aiplatform::v1::{SuggestTrialsRequest, SuggestTrialsResponse};
+use crate::trial::params::{parameters, ParameterMap};
+use crate::trial::{ToTrialName, TrialName};
+use crate::{StudyName, Trial};
+
+/// Returns a client id unique to the current process.
+///
+/// The id has the form `{hostname}-{pid}`, so workers on different hosts or in different
+/// processes do not share trials by accident, while a given process always reuses the
+/// same id.
+pub fn default_client_id() -> String {
+    format!(
+        "{hostname}-{pid}",
+        hostname = gethostname::gethostname().to_string_lossy(),
+        pid = std::process::id()
+    )
+}
 
 /// [SuggestTrialsRequest] builder.
 pub struct RequestBuilder {
@@ -26,11 +41,13 @@ pub struct RequestBuilder {
 
 impl RequestBuilder {
     /// Creates a new instance of [SuggestTrialsRequest] builder.
-    pub fn new(study_name: StudyName, suggestion_count: i32, client_id: String) -> Self {
+    ///
+    /// If `client_id` is empty, [default_client_id] is used.
+    pub fn new(study_name: StudyName, suggestion_count: i32, client_id: impl Into<String>) -> Self {
         RequestBuilder {
             study_name,
             suggestion_count,
-            client_id,
+            client_id: client_id.into(),
         }
     }
 
@@ -39,7 +56,108 @@ impl RequestBuilder {
         SuggestTrialsRequest {
             parent: self.study_name.into(),
             suggestion_count: self.suggestion_count,
-            client_id: self.client_id,
+            client_id: if self.client_id.is_empty() {
+                default_client_id()
+            } else {
+                self.client_id
+            },
         }
     }
 }
+
+/// Trials suggested by the service, with accessors to their names and parameters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Suggestions(SuggestTrialsResponse);
+
+impl Suggestions {
+    /// Returns the suggested trials.
+    pub fn trials(&self) -> &[Trial] {
+        &self.0.trials
+    }
+
+    /// Returns the suggested trials with their parameters - see [parameters].
+    pub fn iter_parameters(&self) -> impl Iterator<Item = (&Trial, ParameterMap)> {
+        self.0.trials.iter().map(|trial| (trial, parameters(trial)))
+    }
+
+    /// Returns the names of the suggested trials.
+    pub fn trial_names(&self) -> impl Iterator<Item = TrialName> + '_ {
+        self.0.trials.iter().map(ToTrialName::to_trial_name)
+    }
+
+    /// Returns the underlying [SuggestTrialsResponse].
+    pub fn into_inner(self) -> SuggestTrialsResponse {
+        self.0
+    }
+}
+
+impl From<SuggestTrialsResponse> for Suggestions {
+    fn from(response: SuggestTrialsResponse) -> Self {
+        Suggestions(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::create::TrialBuilder;
+
+    #[test]
+    fn it_uses_the_default_client_id_when_none_is_provided() {
+        let study_name = StudyName::new("p".to_string(), "l".to_string(), "s".to_string());
+
+        let request = RequestBuilder::new(study_name, 1, String::new()).build();
+
+        assert_eq!(request.client_id, default_client_id());
+        assert!(request
+            .client_id
+            .ends_with(&format!("-{}", std::process::id())));
+    }
+
+    #[test]
+    fn it_accepts_str_and_string() {
+        let study_name = StudyName::new("p".to_string(), "l".to_string(), "s".to_string());
+
+        assert_eq!(
+            RequestBuilder::new(study_name.clone(), 1, "client").build(),
+            RequestBuilder::new(study_name, 1, "client".to_string()).build()
+        );
+    }
+
+    #[test]
+    fn it_yields_the_parameters_of_the_suggestions() {
+        let trial = |id: &str| format!("projects/p/locations/l/studies/s/trials/{id}");
+        let response = SuggestTrialsResponse {
+            trials: vec![
+                Trial {
+                    name: trial("1"),
+                    ..TrialBuilder::new()
+                        .add_parameter_double("a", 1.0)
+                        .add_parameter_categorical("b", "x")
+                        .build()
+                },
+                Trial {
+                    name: trial("2"),
+                    ..TrialBuilder::new().add_parameter_double("a", 2.5).build()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let suggestions = Suggestions::from(response);
+
+        let mut parameters = suggestions.iter_parameters();
+        let (first, params) = parameters.next().unwrap();
+        assert_eq!(first.name, trial("1"));
+        assert_eq!(params.get_f64("a"), Some(1.0));
+        assert_eq!(params.get_str("b"), Some("x"));
+        let (second, params) = parameters.next().unwrap();
+        assert_eq!(second.name, trial("2"));
+        assert_eq!(params.get_f64("a"), Some(2.5));
+        assert_eq!(params.get_str("b"), None);
+        assert!(parameters.next().is_none());
+
+        let trial_names: Vec<String> = suggestions.trial_names().map(String::from).collect();
+        assert_eq!(trial_names, [trial("1"), trial("2")]);
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pagination of list calls.
+
+use std::future::Future;
+
+use futures::{Stream, TryStreamExt};
+
+use crate::Error;
+
+/// Turns a paginated list call into a [Stream] of items.
+///
+/// `fetch_page` is called with the page token (empty for the first page) and returns the
+/// items of the page and the next page token - empty after the last page. An error
+/// fetching a page is yielded by the stream, which then ends.
+pub(crate) fn paginate<T, F, Fut>(mut fetch_page: F) -> impl Stream<Item = Result<T, Error>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, String), Error>>,
+{
+    futures::stream::try_unfold(Some(String::new()), move |page_token| {
+        let page = page_token.map(&mut fetch_page);
+        async move {
+            let Some(page) = page else {
+                return Ok(None);
+            };
+
+            let (items, next_page_token) = page.await?;
+            let next_page_token = (!next_page_token.is_empty()).then_some(next_page_token);
+
+            Ok::<_, Error>(Some((
+                futures::stream::iter(items.into_iter().map(Ok)),
+                next_page_token,
+            )))
+        }
+    })
+    .try_flatten()
+}
+
+/// Counts the items of `items`, failing with the first error.
+pub(crate) async fn count<T>(items: impl Stream<Item = Result<T, Error>>) -> Result<usize, Error> {
+    items
+        .try_fold(0, |count, _| async move { Ok(count + 1) })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use tonic::Status;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_goes_through_all_the_pages() {
+        let items: Vec<i32> = paginate(|page_token| async move {
+            Ok(match page_token.as_str() {
+                "" => (vec![1, 2], "a".to_string()),
+                "a" => (vec![], "b".to_string()),
+                _ => (vec![3], String::new()),
+            })
+        })
+        .try_collect()
+        .await
+        .unwrap();
+
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn it_yields_page_errors() {
+        let items: Vec<Result<i32, Error>> = paginate(|page_token| async move {
+            match page_token.as_str() {
+                "" => Ok((vec![1], "a".to_string())),
+                _ => Err(Status::unavailable("down").into()),
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Ok(1)));
+        assert!(matches!(items[1], Err(Error::Status(_))));
+    }
+
+    #[tokio::test]
+    async fn it_counts_the_items_of_all_the_pages() {
+        let pages = paginate(|page_token| async move {
+            Ok(match page_token.as_str() {
+                "" => (vec![1, 2, 3], "a".to_string()),
+                _ => (vec![4, 5], String::new()),
+            })
+        });
+
+        assert_eq!(count(pages).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn it_stops_counting_at_the_first_error() {
+        let mut fetched = Vec::new();
+        let pages = paginate(|page_token| {
+            fetched.push(page_token.clone());
+            async move {
+                match page_token.as_str() {
+                    "" => Ok((vec![1], "a".to_string())),
+                    "a" => Err(Status::unavailable("down").into()),
+                    _ => Ok((vec![2], String::new())),
+                }
+            }
+        });
+
+        assert!(matches!(count(pages).await, Err(Error::Status(_))));
+        assert_eq!(fetched, ["", "a"]);
+    }
+}
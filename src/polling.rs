@@ -0,0 +1,776 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Polling loops.
+//!
+//! All the waits go through [tokio::time], so the loops can be tested deterministically
+//! with a paused clock (`#[tokio::test(start_paused = true)]`): the runtime then
+//! advances the time instantly whenever all the tasks are waiting on a timer.
+//!
+//! # Coalesced polling of long-running operations
+//!
+//! The operations API has no batch get, and `ListOperations` cannot select operations by
+//! name. When many operations are awaited at once, the polls are coalesced instead: the
+//! operation names are deduplicated and, every `poll_interval`, each pending operation is
+//! polled once - concurrently, with a bounded number of calls in flight. The number of
+//! `GetOperation` calls is then bounded by the number of distinct pending operations per
+//! interval, regardless of the number of waiters, and a round takes as long as its
+//! slowest poll rather than the sum of the polls.
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::time::{sleep, timeout_at, Instant};
+
+use crate::google::longrunning::{operation, Operation};
+use crate::util::{self, OperationResponse};
+use crate::{batch, Error};
+
+/// Default interval between two polls of an operation.
+pub(crate) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Polls with `poll` every `poll_interval` until `done` returns a value.
+///
+/// `done` is given the result of each poll and returns `None` to keep polling.
+pub(crate) async fn poll_until<X, T, F, Fut, D>(
+    poll_interval: Duration,
+    mut poll: F,
+    mut done: D,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<X, Error>>,
+    D: FnMut(X) -> Option<T>,
+{
+    loop {
+        if let Some(result) = done(poll().await?) {
+            return Ok(result);
+        }
+
+        sleep(poll_interval).await;
+    }
+}
+
+/// Polls with `poll` every `poll_interval` - the first time immediately - and yields the
+/// result of each poll, until `done` returns true for a result, which is yielded last.
+///
+/// An error is yielded and ends the stream.
+pub(crate) fn watch<T, F, Fut, D>(
+    poll_interval: Duration,
+    poll: F,
+    mut done: D,
+) -> impl Stream<Item = Result<T, Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+    D: FnMut(&T) -> bool,
+{
+    let polls = futures::stream::unfold((poll, true), move |(mut poll, first)| async move {
+        if !first {
+            sleep(poll_interval).await;
+        }
+        let result = poll().await;
+        Some((result, (poll, false)))
+    });
+
+    polls.scan(false, move |finished, result| {
+        if *finished {
+            return futures::future::ready(None);
+        }
+        *finished = result.as_ref().map_or(true, &mut done);
+        futures::future::ready(Some(result))
+    })
+}
+
+/// Logs the state of `operation`: its name and whether it is done at the debug level, the
+/// whole operation at the trace level.
+pub(crate) fn log_operation(operation: &Operation) {
+    log::debug!("operation {} done: {}", operation.name, operation.done);
+    log::trace!("operation: {:?}", operation);
+}
+
+/// Returns the result of `operation` - `None` while it is running - and logs its state -
+/// see [log_operation].
+///
+/// Fails with [Error::OperationWithoutResult] if the operation is done without result,
+/// which would otherwise be indistinguishable from a running operation.
+#[allow(clippy::result_large_err)]
+pub(crate) fn operation_result(operation: Operation) -> Result<Option<operation::Result>, Error> {
+    log_operation(&operation);
+    match (operation.done, operation.result) {
+        (false, _) => Ok(None),
+        (true, Some(result)) => Ok(Some(result)),
+        (true, None) => Err(Error::OperationWithoutResult {
+            operation_name: operation.name,
+        }),
+    }
+}
+
+/// Decodes the result of the done operation `operation_name` as the [OperationResponse]
+/// `X`.
+///
+/// Fails with [Error::OperationWithoutResult] if there is no result, and with
+/// [Error::DecodingError] if the operation failed or its response is not a `X`.
+#[allow(clippy::result_large_err)]
+pub(crate) fn decode_result<X: OperationResponse>(
+    operation_name: &str,
+    result: Option<operation::Result>,
+) -> Result<X, Error> {
+    let result = result.ok_or_else(|| Error::OperationWithoutResult {
+        operation_name: operation_name.to_string(),
+    })?;
+
+    Ok(util::decode_operation_result(result)?)
+}
+
+/// Polls `operation` with `get_operation` every `poll_interval` until it is done, and
+/// returns its result.
+///
+/// The result of an operation that is already done is returned without polling.
+/// `get_operation` returns `Ok(None)` while the operation is still running - see
+/// [operation_result].
+pub(crate) async fn poll_operation<F, Fut>(
+    operation: Operation,
+    poll_interval: Duration,
+    mut get_operation: F,
+) -> Result<operation::Result, Error>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Option<operation::Result>, Error>>,
+{
+    let name = operation.name.clone();
+    if let Some(result) = operation_result(operation)? {
+        return Ok(result);
+    }
+
+    poll_until(
+        poll_interval,
+        || get_operation(name.clone()),
+        |result| result,
+    )
+    .await
+}
+
+/// Awaits `wait` - a server-side wait for an operation, such as `WaitOperation` - and
+/// falls back to `poll` if the service does not implement it
+/// ([tonic::Code::Unimplemented]).
+pub(crate) async fn wait_or_poll<W, P, Fut>(
+    wait: W,
+    poll: P,
+) -> Result<Option<operation::Result>, Error>
+where
+    W: Future<Output = Result<Option<operation::Result>, Error>>,
+    P: FnOnce() -> Fut,
+    Fut: Future<Output = Result<operation::Result, Error>>,
+{
+    match wait.await {
+        Err(Error::Status(status)) if status.code() == tonic::Code::Unimplemented => {
+            log::debug!("operation wait not implemented, polling");
+            poll().await.map(Some)
+        }
+        result => result,
+    }
+}
+
+/// Polls the operations with `get_operation` until they are all done, and yields the
+/// name and the result of each operation once it is done.
+///
+/// Every `poll_interval` - the first time immediately - the pending operations are
+/// polled concurrently, at most [batch::MAX_CONCURRENT_REQUESTS] at once, and the
+/// operations found done during the round are yielded. `get_operation` returns `Ok(None)`
+/// while an operation is still running. An error is yielded for its operation only, which
+/// is not polled anymore.
+pub(crate) fn poll_operations<F, Fut>(
+    operation_names: impl IntoIterator<Item = String>,
+    poll_interval: Duration,
+    get_operation: F,
+) -> impl Stream<Item = (String, Result<operation::Result, Error>)>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Option<operation::Result>, Error>>,
+{
+    let pending: BTreeSet<String> = operation_names.into_iter().collect();
+
+    let rounds = futures::stream::unfold(
+        (pending, get_operation, true),
+        move |(mut pending, mut get_operation, first)| async move {
+            if pending.is_empty() {
+                return None;
+            }
+            if !first {
+                sleep(poll_interval).await;
+            }
+
+            let polls = pending.iter().map(|name| {
+                let poll = get_operation(name.clone());
+                let name = name.clone();
+                async move { (name, poll.await.transpose()) }
+            });
+            let done: Vec<_> = batch::in_order(polls)
+                .await
+                .into_iter()
+                .filter_map(|(name, result)| result.map(|result| (name, result)))
+                .collect();
+            for (name, _) in &done {
+                pending.remove(name);
+            }
+
+            Some((futures::stream::iter(done), (pending, get_operation, false)))
+        },
+    );
+
+    rounds.flatten()
+}
+
+/// Awaits `operations` - polled together with [poll_operations] - for at most `timeout`,
+/// and yields their results as they complete.
+///
+/// The operations already done are yielded first. Duplicate operations - by name - are
+/// awaited once and yield a single result, so fewer results than `operations` can be
+/// yielded. Each operation still pending at the deadline yields [Error::Timeout].
+pub(crate) fn await_operations<F, Fut>(
+    operations: Vec<Operation>,
+    timeout: Duration,
+    poll_interval: Duration,
+    get_operation: F,
+) -> impl Stream<Item = Result<operation::Result, Error>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Option<operation::Result>, Error>>,
+{
+    let deadline = Instant::now() + timeout;
+
+    let mut names = BTreeSet::new();
+    let mut done = vec![];
+    let mut pending = BTreeSet::new();
+    for operation in operations {
+        let name = operation.name.clone();
+        if !names.insert(name.clone()) {
+            continue;
+        }
+        match operation_result(operation) {
+            Ok(None) => {
+                pending.insert(name);
+            }
+            Ok(Some(result)) => done.push(Ok(result)),
+            Err(e) => done.push(Err(e)),
+        }
+    }
+
+    let polls = Box::pin(poll_operations(
+        pending.clone(),
+        poll_interval,
+        get_operation,
+    ));
+    let awaited = futures::stream::unfold(
+        (Some(polls), pending),
+        move |(polls, mut pending)| async move {
+            match polls {
+                Some(mut polls) => match timeout_at(deadline, polls.next()).await {
+                    Ok(Some((name, result))) => {
+                        pending.remove(&name);
+                        Some((result, (Some(polls), pending)))
+                    }
+                    Ok(None) => None,
+                    // The polling stops at the deadline.
+                    Err(_) => pending
+                        .pop_first()
+                        .map(|_| (Err(Error::Timeout(timeout)), (None, pending))),
+                },
+                None => pending
+                    .pop_first()
+                    .map(|_| (Err(Error::Timeout(timeout)), (None, pending))),
+            }
+        },
+    );
+
+    futures::stream::iter(done).chain(awaited)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::sync::Once;
+
+    use super::*;
+    use crate::google::rpc::Status;
+
+    #[tokio::test(start_paused = true)]
+    async fn it_polls_each_operation_once_per_round() {
+        let calls: RefCell<HashMap<String, usize>> = RefCell::default();
+
+        let names = ["op1", "op2", "op1", "op1"].map(String::from);
+        let results: HashMap<_, _> = poll_operations(names, Duration::from_secs(1), |name| {
+            let mut calls = calls.borrow_mut();
+            let count = calls.entry(name.clone()).or_default();
+            *count += 1;
+
+            // op1 is done at the first poll, op2 at the third one.
+            let done = name == "op1" || *count == 3;
+            async move {
+                Ok(done.then(|| {
+                    operation::Result::Error(Status {
+                        message: name,
+                        ..Default::default()
+                    })
+                }))
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results.values().all(Result::is_ok));
+        assert_eq!(calls.borrow()["op1"], 1);
+        assert_eq!(calls.borrow()["op2"], 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_polls_the_operations_of_a_round_concurrently() {
+        let start = tokio::time::Instant::now();
+
+        // Each poll takes 1s; op3 is done at the first poll, op1 at the second and op2
+        // at the third.
+        let rounds = HashMap::from([("op1", 2), ("op2", 3), ("op3", 1)]);
+        let calls: RefCell<HashMap<String, usize>> = RefCell::default();
+        let names = ["op1", "op2", "op3"].map(String::from);
+        let done: Vec<_> = poll_operations(names, Duration::from_secs(1), |name| {
+            let mut calls = calls.borrow_mut();
+            let count = calls.entry(name.clone()).or_default();
+            *count += 1;
+            let done = *count == rounds[name.as_str()];
+            async move {
+                sleep(Duration::from_secs(1)).await;
+                Ok(done.then(|| error_result(&name)))
+            }
+        })
+        .map(|(name, _)| (name, start.elapsed()))
+        .collect()
+        .await;
+
+        // A round takes 1s, not the sum of its polls, and is followed by a 1s wait.
+        assert_eq!(
+            done,
+            [("op3", 1), ("op1", 3), ("op2", 5)]
+                .map(|(name, secs)| (name.to_string(), Duration::from_secs(secs)))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_keeps_polling_the_other_operations_after_an_error() {
+        let mut calls = 0;
+
+        let names = ["failing", "slow"].map(String::from);
+        let results: Vec<_> = poll_operations(names, Duration::from_secs(1), |name| {
+            calls += 1;
+            let done = calls > 4;
+            async move {
+                match name.as_str() {
+                    "failing" => Err(tonic::Status::unavailable("down").into()),
+                    _ => Ok(done.then(|| error_result("slow"))),
+                }
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "failing");
+        assert!(matches!(results[0].1, Err(Error::Status(_))));
+        assert_eq!(results[1].0, "slow");
+        assert!(results[1].1.is_ok());
+        // failing is polled once, slow until the 4th round.
+        assert_eq!(calls, 5);
+    }
+
+    fn error_result(message: &str) -> operation::Result {
+        operation::Result::Error(Status {
+            message: message.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_yields_the_awaited_operations_as_they_complete() {
+        let start = tokio::time::Instant::now();
+
+        let operation = |name: &str, done: bool| Operation {
+            name: name.to_string(),
+            done,
+            result: done.then(|| error_result(name)),
+            ..Default::default()
+        };
+        let operations = vec![
+            operation("slow", false),
+            operation("fast", false),
+            operation("never", false),
+            operation("done", true),
+            operation("fast", false),
+        ];
+
+        // fast is done at the second poll, slow at the fourth; never is still running at
+        // the deadline.
+        let rounds = HashMap::from([("fast", 2), ("slow", 4), ("never", usize::MAX)]);
+        let calls: RefCell<HashMap<String, usize>> = RefCell::default();
+        let results: Vec<_> = await_operations(
+            operations,
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+            |name| {
+                let mut calls = calls.borrow_mut();
+                let count = calls.entry(name.clone()).or_default();
+                *count += 1;
+                let done = *count == rounds[name.as_str()];
+                async move { Ok(done.then(|| error_result(&name))) }
+            },
+        )
+        .map(|result| {
+            let name = match result {
+                Ok(operation::Result::Error(status)) => status.message,
+                Err(Error::Timeout(timeout)) => format!("timeout after {timeout:?}"),
+                result => panic!("unexpected result: {result:?}"),
+            };
+            (name, start.elapsed())
+        })
+        .collect()
+        .await;
+
+        // The duplicate fast operation is awaited once.
+        assert_eq!(
+            results,
+            [
+                ("done", 0),
+                ("fast", 2),
+                ("slow", 6),
+                ("timeout after 10s", 10)
+            ]
+            .map(|(name, secs)| (name.to_string(), Duration::from_secs(secs)))
+        );
+        // never is not polled after the deadline.
+        assert_eq!(calls.borrow()["never"], 6);
+    }
+
+    /// A logger capturing the records of the current thread.
+    struct CapturingLogger;
+
+    thread_local! {
+        static RECORDS: RefCell<Vec<(log::Level, String)>> = RefCell::default();
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            RECORDS.with(|records| {
+                records
+                    .borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn capture_logs() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&CapturingLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        RECORDS.with(|records| records.borrow_mut().clear());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_only_logs_the_operations_at_the_debug_and_trace_levels() {
+        capture_logs();
+
+        let operation = Operation {
+            name: "op".to_string(),
+            ..Default::default()
+        };
+        let mut polls = 0;
+        poll_operation(operation, Duration::from_secs(1), |name| {
+            polls += 1;
+            let operation = Operation {
+                name,
+                done: polls == 2,
+                result: Some(error_result("payload")),
+                ..Default::default()
+            };
+            async move { operation_result(operation) }
+        })
+        .await
+        .unwrap();
+
+        let records = RECORDS.with(|records| records.take());
+        // Nothing is logged at the default levels of the usual loggers (info and above).
+        assert!(records.iter().all(|(level, _)| *level >= log::Level::Debug));
+        let debug: Vec<_> = records
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Debug)
+            .map(|(_, message)| message.as_str())
+            .collect();
+        assert_eq!(
+            debug,
+            [
+                "operation op done: false",
+                "operation op done: false",
+                "operation op done: true"
+            ]
+        );
+        // The payload only goes to the trace level.
+        assert!(records
+            .iter()
+            .filter(|(_, message)| message.contains("payload"))
+            .all(|(level, _)| *level == log::Level::Trace));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_polls_a_pending_operation() {
+        let mut calls = 0;
+
+        let operation = Operation {
+            name: "op".to_string(),
+            ..Default::default()
+        };
+        let result = poll_operation(operation, Duration::from_millis(100), |name| {
+            calls += 1;
+            let done = calls == 3;
+            async move {
+                assert_eq!(name, "op");
+                Ok(done.then(|| error_result("late")))
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result, error_result("late"));
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_waits_the_poll_interval_between_polls() {
+        let start = tokio::time::Instant::now();
+        let mut polls = Vec::new();
+
+        let operation = Operation {
+            name: "op".to_string(),
+            ..Default::default()
+        };
+        poll_operation(operation, Duration::from_secs(3), |_| {
+            polls.push(start.elapsed());
+            let done = polls.len() == 3;
+            async move { Ok(done.then(|| error_result("late"))) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            polls,
+            [0, 3, 6].map(Duration::from_secs),
+            "polled at {polls:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_rejects_a_done_operation_without_result() {
+        let operation = Operation {
+            name: "op".to_string(),
+            done: true,
+            ..Default::default()
+        };
+        let result = poll_operation(operation, Duration::from_millis(100), |_| async {
+            unreachable!("the operation is done")
+        })
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::OperationWithoutResult { operation_name }) if operation_name == "op"
+        ));
+
+        let mut polls = 0;
+        let operation = Operation {
+            name: "op".to_string(),
+            ..Default::default()
+        };
+        let result = poll_operation(operation, Duration::from_millis(100), |name| {
+            polls += 1;
+            let operation = Operation {
+                name,
+                done: polls == 2,
+                ..Default::default()
+            };
+            async move { operation_result(operation) }
+        })
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::OperationWithoutResult { operation_name }) if operation_name == "op"
+        ));
+        assert_eq!(polls, 2);
+    }
+
+    #[tokio::test]
+    async fn it_polls_when_the_wait_is_not_implemented() {
+        let unimplemented = async { Err(tonic::Status::unimplemented("no wait").into()) };
+        let result = wait_or_poll(unimplemented, || async { Ok(error_result("polled")) }).await;
+        assert_eq!(result.unwrap(), Some(error_result("polled")));
+
+        let waited = async { Ok(Some(error_result("waited"))) };
+        let result = wait_or_poll(waited, || async { panic!("polled") }).await;
+        assert_eq!(result.unwrap(), Some(error_result("waited")));
+
+        let unavailable = async { Err(tonic::Status::unavailable("down").into()) };
+        let result = wait_or_poll(unavailable, || async { panic!("polled") }).await;
+        assert!(matches!(
+            result,
+            Err(Error::Status(s)) if s.code() == tonic::Code::Unavailable
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_watches_until_done() {
+        let start = tokio::time::Instant::now();
+        let mut states = vec!["active", "active", "succeeded", "unreachable"].into_iter();
+
+        let polls: Vec<_> = watch(
+            Duration::from_secs(1),
+            || {
+                let state = states.next().unwrap();
+                async move { Ok((state, start.elapsed())) }
+            },
+            |(state, _)| *state == "succeeded",
+        )
+        .collect()
+        .await;
+
+        let polls: Vec<_> = polls.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            polls,
+            [
+                ("active", Duration::from_secs(0)),
+                ("active", Duration::from_secs(1)),
+                ("succeeded", Duration::from_secs(2)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_stops_watching_on_errors() {
+        let mut polls = 0;
+
+        let results: Vec<Result<i32, _>> = watch(
+            Duration::from_secs(1),
+            || {
+                polls += 1;
+                let poll = polls;
+                async move {
+                    match poll {
+                        1 => Ok(1),
+                        _ => Err(tonic::Status::unavailable("down").into()),
+                    }
+                }
+            },
+            |_| false,
+        )
+        .collect()
+        .await;
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Ok(1)));
+        assert!(
+            matches!(&results[1], Err(Error::Status(s)) if s.code() == tonic::Code::Unavailable)
+        );
+    }
+
+    #[test]
+    fn it_decodes_the_result_of_done_operations() {
+        use prost::Message;
+        use prost_types::Any;
+
+        use crate::google::cloud::aiplatform::v1::SuggestTrialsResponse;
+
+        let expected = SuggestTrialsResponse {
+            trials: vec![crate::Trial {
+                name: "t".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let response = operation::Result::Response(Any {
+            type_url: SuggestTrialsResponse::TYPE_URL.to_string(),
+            value: expected.encode_to_vec(),
+        });
+        let decoded: SuggestTrialsResponse = decode_result("op", Some(response)).unwrap();
+        assert_eq!(decoded, expected);
+
+        let failed: Result<SuggestTrialsResponse, _> =
+            decode_result("op", Some(error_result("no feasible trial")));
+        assert!(matches!(
+            failed,
+            Err(Error::DecodingError(util::Error::RPCStatus(status)))
+                if status.message == "no feasible trial"
+        ));
+
+        let empty: Result<SuggestTrialsResponse, _> = decode_result("op", None);
+        assert!(matches!(
+            empty,
+            Err(Error::OperationWithoutResult { operation_name }) if operation_name == "op"
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_does_not_poll_a_done_operation() {
+        let operation = Operation {
+            name: "op".to_string(),
+            done: true,
+            result: Some(error_result("early")),
+            ..Default::default()
+        };
+        let result = poll_operation(operation, Duration::from_millis(100), |_| async {
+            unreachable!("the operation is done")
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result, error_result("early"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_polls_until_done() {
+        let start = tokio::time::Instant::now();
+        let mut polls = 0;
+
+        let result = poll_until(
+            Duration::from_secs(10),
+            || {
+                polls += 1;
+                let count = polls;
+                async move { Ok(count) }
+            },
+            |count| (count == 3).then_some("done"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, "done");
+        assert_eq!(polls, 3);
+        assert_eq!(start.elapsed(), Duration::from_secs(20));
+    }
+}
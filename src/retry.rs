@@ -0,0 +1,479 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retry policy for the RPCs issued by [crate::VizierClient].
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::time::{sleep, Instant};
+use tonic::{Code, Status};
+
+/// Error returned by [RetryConfig::with_multiplier].
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    /// The multiplier is not finite or is lower than 1.
+    #[error("multiplier must be finite and >= 1.0, got {0}")]
+    InvalidMultiplier(f64),
+}
+
+/// Configuration of the retries of failed RPCs.
+///
+/// Only the errors whose [Code] is in `retryable_codes` are retried, up to `max_retries`
+/// times. The first retry happens after `initial_backoff`, and the wait is multiplied by
+/// `multiplier` after each retry, without exceeding `max_backoff`. A [Jitter] can
+/// randomize the waits, to spread the retries of a fleet of clients failing at the same
+/// time.
+///
+/// Retrying is only safe for idempotent calls. Mutations such as `create_study`,
+/// `add_trial_measurement` or `complete_trial` may have been applied by the server even
+/// if the client saw an error (e.g. [Code::DeadlineExceeded]), and retrying them could
+/// apply them twice. The Vizier API does not offer idempotency keys - only
+/// `suggest_trials` is made safe by reusing the same `client_id` - so the crate only
+/// applies this policy to read-only calls and to the polling of long-running operations.
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    /// The codes of the errors that are retried.
+    retryable_codes: Vec<Code>,
+    /// The maximum number of retries.
+    max_retries: usize,
+    /// The wait before the first retry.
+    initial_backoff: Duration,
+    /// The factor applied to the wait after each retry.
+    multiplier: f64,
+    /// The maximum wait between two attempts.
+    max_backoff: Duration,
+    /// The randomization of the waits.
+    jitter: Jitter,
+    /// The seed of the random generator of the [Jitter] - drawn from the OS if `None`.
+    jitter_seed: Option<u64>,
+}
+
+/// Randomization of the waits between two attempts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// The waits are not randomized.
+    #[default]
+    None,
+    /// The wait is drawn uniformly between 0 and the backoff.
+    Full,
+    /// The wait is drawn uniformly between half the backoff and the backoff.
+    Equal,
+}
+
+impl Jitter {
+    /// Randomizes `backoff` with a factor drawn from `rng`.
+    fn apply(self, backoff: Duration, rng: &mut impl Rng) -> Duration {
+        self.scale(backoff, rng.gen())
+    }
+
+    /// Randomizes `backoff` with `factor` - taken as 1 (no randomization) if it is not
+    /// finite, and clamped to `[0, 1]` otherwise.
+    fn scale(self, backoff: Duration, factor: f64) -> Duration {
+        let factor = if factor.is_finite() {
+            factor.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        match self {
+            Jitter::None => backoff,
+            Jitter::Full => scale(backoff, factor, backoff),
+            Jitter::Equal => {
+                let half = backoff / 2;
+                half + scale(half, factor, half)
+            }
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    /// Retries [Code::Unavailable] and [Code::DeadlineExceeded] 3 times, waiting 500ms
+    /// before the first retry and doubling the wait after each, up to 30s - without
+    /// jitter.
+    fn default() -> Self {
+        Self {
+            retryable_codes: vec![Code::Unavailable, Code::DeadlineExceeded],
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(30),
+            jitter: Jitter::None,
+            jitter_seed: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Sets the codes of the errors that are retried.
+    pub fn with_retryable_codes(mut self, retryable_codes: impl IntoIterator<Item = Code>) -> Self {
+        self.retryable_codes = retryable_codes.into_iter().collect();
+        self
+    }
+
+    /// Sets the maximum number of retries.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the wait before the first retry.
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the factor applied to the wait after each retry.
+    ///
+    /// Fails if `multiplier` is not finite or is lower than 1.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Result<Self, Error> {
+        if !multiplier.is_finite() || multiplier < 1.0 {
+            return Err(Error::InvalidMultiplier(multiplier));
+        }
+        self.multiplier = multiplier;
+        Ok(self)
+    }
+
+    /// Sets the maximum wait between two attempts.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the randomization of the waits.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the seed of the random generator of the [Jitter] - to get reproducible
+    /// waits.
+    pub fn with_jitter_seed(mut self, seed: u64) -> Self {
+        self.jitter_seed = Some(seed);
+        self
+    }
+
+    /// Returns the waits before each of the retries.
+    ///
+    /// With a [Jitter], each call returns different waits unless a seed is set.
+    pub fn backoffs(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.unbounded_backoffs().take(self.max_retries)
+    }
+
+    /// Returns the waits before each of the retries, regardless of `max_retries`.
+    fn unbounded_backoffs(&self) -> impl Iterator<Item = Duration> + '_ {
+        let mut rng = (self.jitter != Jitter::None).then(|| match self.jitter_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        });
+        let mut backoff = self.initial_backoff.min(self.max_backoff);
+        std::iter::repeat_with(move || {
+            let current = backoff;
+            backoff = scale(backoff, self.multiplier, self.max_backoff);
+            match &mut rng {
+                Some(rng) => self.jitter.apply(current, rng),
+                None => current,
+            }
+        })
+    }
+
+    /// Returns true if an error with this code should be retried.
+    pub fn is_retryable(&self, code: Code) -> bool {
+        self.retryable_codes.contains(&code)
+    }
+}
+
+/// Returns `backoff` multiplied by `factor`, without exceeding `max` - which is also
+/// returned if the product is not a valid [Duration].
+fn scale(backoff: Duration, factor: f64, max: Duration) -> Duration {
+    Duration::try_from_secs_f64(backoff.as_secs_f64() * factor).map_or(max, |b| b.min(max))
+}
+
+/// Calls `f` until it succeeds, fails with a non-retryable error or runs out of attempts,
+/// waiting between the attempts as configured by `config`.
+pub(crate) async fn retry<T, F, Fut>(config: &RetryConfig, mut f: F) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let mut backoffs = config.backoffs();
+
+    loop {
+        match f().await {
+            Err(status) if config.is_retryable(status.code()) => match backoffs.next() {
+                Some(backoff) => sleep(backoff).await,
+                None => return Err(status),
+            },
+            res => return res,
+        }
+    }
+}
+
+/// Calls `f` while it fails with an error for which `should_retry` returns true, waiting
+/// between the attempts as configured by `config` - but regardless of `max_retries` and
+/// of the retryable codes - as long as the next attempt starts within `max_wait`.
+///
+/// The last error is returned once `max_wait` would be exceeded, and other errors are
+/// returned right away.
+pub(crate) async fn retry_while<T, E, F, Fut>(
+    config: &RetryConfig,
+    max_wait: Duration,
+    should_retry: impl Fn(&E) -> bool,
+    mut f: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let mut backoffs = config.unbounded_backoffs();
+
+    loop {
+        match f().await {
+            Err(e) if should_retry(&e) => {
+                let backoff = backoffs.next().unwrap_or(config.max_backoff);
+                if start.elapsed() + backoff > max_wait {
+                    return Err(e);
+                }
+                sleep(backoff).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    async fn failing(attempts: &AtomicUsize, code: Code) -> Result<(), Status> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(Status::new(code, "failure"))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_retries_the_retryable_codes() {
+        let attempts = AtomicUsize::new(0);
+
+        let res = retry(&RetryConfig::default(), || {
+            failing(&attempts, Code::Unavailable)
+        })
+        .await;
+
+        assert_eq!(res.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_succeeds_after_transient_failures() {
+        let attempts = AtomicUsize::new(0);
+
+        let res = retry(&RetryConfig::default(), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(Status::unavailable("connection reset")),
+                _ => Ok("study"),
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), "study");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_does_not_retry_other_codes() {
+        let attempts = AtomicUsize::new(0);
+        let config = RetryConfig::default().with_retryable_codes([Code::ResourceExhausted]);
+
+        let res = retry(&config, || failing(&attempts, Code::Unavailable)).await;
+
+        assert_eq!(res.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_follows_the_backoff_sequence() {
+        let config = RetryConfig::default()
+            .with_max_retries(5)
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_multiplier(3.0)
+            .unwrap()
+            .with_max_backoff(Duration::from_secs(10));
+
+        let start = tokio::time::Instant::now();
+        let attempts = std::sync::Mutex::new(vec![]);
+
+        let res = retry(&config, || {
+            attempts.lock().unwrap().push(start.elapsed());
+            async { Err::<(), _>(Status::unavailable("failure")) }
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(
+            attempts.into_inner().unwrap(),
+            [0, 1, 4, 13, 23, 33].map(Duration::from_secs)
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_multipliers() {
+        for multiplier in [f64::NAN, f64::INFINITY, -2.0, 0.5] {
+            assert!(matches!(
+                RetryConfig::default().with_multiplier(multiplier),
+                Err(Error::InvalidMultiplier(m)) if m.to_bits() == multiplier.to_bits()
+            ));
+        }
+        assert!(RetryConfig::default().with_multiplier(1.0).is_ok());
+    }
+
+    #[test]
+    fn it_saturates_the_backoffs() {
+        for multiplier in [f64::NAN, -2.0] {
+            let config = RetryConfig {
+                multiplier,
+                max_retries: 3,
+                ..Default::default()
+            };
+            let backoffs: Vec<Duration> = config.backoffs().collect();
+            assert_eq!(
+                backoffs,
+                [
+                    Duration::from_millis(500),
+                    config.max_backoff,
+                    config.max_backoff
+                ]
+            );
+        }
+
+        let config = RetryConfig::default()
+            .with_initial_backoff(Duration::MAX)
+            .with_max_backoff(Duration::MAX)
+            .with_multiplier(f64::MAX)
+            .unwrap();
+        assert!(config.backoffs().all(|b| b == Duration::MAX));
+    }
+
+    #[test]
+    fn it_jitters_the_backoffs() {
+        let config = RetryConfig::default()
+            .with_max_retries(5)
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_multiplier(2.0)
+            .unwrap()
+            .with_max_backoff(Duration::from_secs(10));
+        let backoffs: Vec<Duration> = config.backoffs().collect();
+        assert_eq!(backoffs, [1, 2, 4, 8, 10].map(Duration::from_secs));
+
+        let full = config
+            .clone()
+            .with_jitter(Jitter::Full)
+            .with_jitter_seed(42);
+        let jittered: Vec<Duration> = full.backoffs().collect();
+        assert_eq!(jittered, full.backoffs().collect::<Vec<_>>());
+        assert_ne!(jittered, backoffs);
+        for (jittered, backoff) in jittered.iter().zip(&backoffs) {
+            assert!(jittered <= backoff, "{jittered:?} > {backoff:?}");
+        }
+
+        let equal = config.with_jitter(Jitter::Equal).with_jitter_seed(42);
+        for (jittered, backoff) in equal.backoffs().zip(&backoffs) {
+            assert!(
+                *backoff / 2 <= jittered && jittered <= *backoff,
+                "{jittered:?} not in [{:?}, {backoff:?}]",
+                *backoff / 2
+            );
+        }
+    }
+
+    #[test]
+    fn it_bounds_the_jitter_factor() {
+        let backoff = Duration::from_secs(4);
+        for (factor, full, equal) in [
+            (0.25, 1, 2.5),
+            (f64::NAN, 4, 4.0),
+            (f64::INFINITY, 4, 4.0),
+            (-1.0, 0, 2.0),
+            (2.0, 4, 4.0),
+        ] {
+            assert_eq!(
+                Jitter::Full.scale(backoff, factor),
+                Duration::from_secs(full),
+                "{factor}"
+            );
+            assert_eq!(
+                Jitter::Equal.scale(backoff, factor),
+                Duration::from_secs_f64(equal),
+                "{factor}"
+            );
+        }
+        assert_eq!(Jitter::None.scale(backoff, f64::NAN), backoff);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_retries_while_not_found() {
+        let start = Instant::now();
+        let attempts = AtomicUsize::new(0);
+
+        let result = retry_while(
+            &RetryConfig::default(),
+            Duration::from_secs(10),
+            |s: &Status| s.code() == Code::NotFound,
+            || async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(Status::not_found("not yet")),
+                    _ => Ok("found"),
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "found");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_gives_up_after_max_wait() {
+        let start = Instant::now();
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<(), _> = retry_while(
+            &RetryConfig::default().with_max_retries(0),
+            Duration::from_secs(3),
+            |s: &Status| s.code() == Code::NotFound,
+            || failing(&attempts, Code::NotFound),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+        // Attempts at 0, 0.5s and 1.5s - the next one would be at 3.5s.
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<(), _> = retry_while(
+            &RetryConfig::default(),
+            Duration::from_secs(3),
+            |s: &Status| s.code() == Code::NotFound,
+            || failing(&attempts, Code::PermissionDenied),
+        )
+        .await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timeout of the requests.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::Error;
+
+/// Awaits `request` for at most `timeout` - indefinitely if `None`.
+///
+/// Fails with [Error::Timeout] if the request does not complete in time.
+pub(crate) async fn call<T, E>(
+    timeout: Option<Duration>,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, Error>
+where
+    Error: From<E>,
+{
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, request).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::Timeout(timeout)),
+        },
+        None => Ok(request.await?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Status;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn it_times_out_slow_requests() {
+        let timeout = Duration::from_secs(30);
+
+        let slow = std::future::pending::<Result<(), Status>>();
+        assert!(matches!(
+            call(Some(timeout), slow).await,
+            Err(Error::Timeout(t)) if t == timeout
+        ));
+
+        let fast = async {
+            tokio::time::sleep(Duration::from_secs(29)).await;
+            Ok::<_, Status>(42)
+        };
+        assert_eq!(call(Some(timeout), fast).await.unwrap(), 42);
+
+        let failing = async { Err::<(), _>(Status::unavailable("down")) };
+        assert!(matches!(
+            call(Some(timeout), failing).await,
+            Err(Error::Status(_))
+        ));
+    }
+}
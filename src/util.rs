@@ -14,9 +14,16 @@
 
 //! Utilities for the Vizier API.
 
-use prost::DecodeError;
+use prost::{DecodeError, Message};
+use prost_types::Any;
 
+use crate::google::cloud::aiplatform::v1::{
+    CheckTrialEarlyStoppingStateResponse, SuggestTrialsResponse,
+};
+pub use crate::google::rpc::bad_request::FieldViolation;
+pub use crate::google::rpc::quota_failure::Violation as QuotaViolation;
 use crate::google::rpc::Status;
+pub use crate::google::rpc::{BadRequest, QuotaFailure, RetryInfo};
 use crate::operation;
 
 /// Error from decoding operation results.
@@ -33,6 +40,57 @@ pub enum Error {
     InvalidType(String),
 }
 
+impl Error {
+    /// Returns the well-known details of the [Status] of an [Error::RPCStatus] - the raw
+    /// [Status] remains available in the variant.
+    pub fn details(&self) -> Option<ErrorDetails> {
+        match self {
+            Error::RPCStatus(status) => Some(ErrorDetails::from_status(status)),
+            _ => None,
+        }
+    }
+}
+
+/// The well-known details of a [Status].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorDetails {
+    /// The `google.rpc.BadRequest` detail, if any.
+    pub bad_request: Option<BadRequest>,
+    /// The `google.rpc.QuotaFailure` detail, if any.
+    pub quota_failure: Option<QuotaFailure>,
+    /// The `google.rpc.RetryInfo` detail, if any.
+    pub retry_info: Option<RetryInfo>,
+}
+
+impl ErrorDetails {
+    /// Decodes the well-known details of `status`.
+    ///
+    /// Details of other types, or that cannot be decoded, are ignored. When a type
+    /// appears several times, the last one is kept.
+    pub fn from_status(status: &Status) -> Self {
+        let mut details = ErrorDetails::default();
+
+        for any in &status.details {
+            let value = &any.value[..];
+            match any.type_url.as_str() {
+                "type.googleapis.com/google.rpc.BadRequest" => {
+                    details.bad_request = BadRequest::decode(value).ok().or(details.bad_request)
+                }
+                "type.googleapis.com/google.rpc.QuotaFailure" => {
+                    details.quota_failure =
+                        QuotaFailure::decode(value).ok().or(details.quota_failure)
+                }
+                "type.googleapis.com/google.rpc.RetryInfo" => {
+                    details.retry_info = RetryInfo::decode(value).ok().or(details.retry_info)
+                }
+                _ => {}
+            }
+        }
+
+        details
+    }
+}
+
 /// Decodes the result of an operation as with the specified [`type_url`](Any.type_url) as
 /// the provided (by the generic type parameter `X`) message.
 pub fn decode_operation_result_as<X>(
@@ -55,3 +113,182 @@ where
         }
     }
 }
+
+/// A message returned as the result of a long-running operation.
+pub trait OperationResponse: prost::Message + Default {
+    /// The [`type_url`](Any.type_url) of the message.
+    const TYPE_URL: &'static str;
+}
+
+impl OperationResponse for SuggestTrialsResponse {
+    const TYPE_URL: &'static str =
+        "type.googleapis.com/google.cloud.aiplatform.v1.SuggestTrialsResponse";
+}
+
+impl OperationResponse for CheckTrialEarlyStoppingStateResponse {
+    const TYPE_URL: &'static str =
+        "type.googleapis.com/google.cloud.aiplatform.v1.CheckTrialEarlyStoppingStateResponse";
+}
+
+/// Decodes the result of an operation as the [OperationResponse] `X` - see
+/// [decode_operation_result_as].
+pub fn decode_operation_result<X: OperationResponse>(
+    result: operation::Result,
+) -> Result<X, Error> {
+    decode_operation_result_as(result, X::TYPE_URL)
+}
+
+/// Result of [decode_operation_result_lenient].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded<X> {
+    /// The result has the expected type and has been decoded.
+    Expected(X),
+    /// The result has another type - the raw payload is returned as is.
+    Other(Any),
+}
+
+/// Decodes the result of an operation as [decode_operation_result_as] does, except that a
+/// result of another type than `type_url` is returned as is ([Decoded::Other]) instead
+/// of failing with [Error::InvalidType].
+///
+/// Useful to inspect an unanticipated response of the server.
+pub fn decode_operation_result_lenient<X>(
+    result: operation::Result,
+    type_url: impl AsRef<str>,
+) -> Result<Decoded<X>, Error>
+where
+    X: prost::Message + Default,
+{
+    match result {
+        operation::Result::Error(s) => Err(Error::RPCStatus(s)),
+        operation::Result::Response(resp) => {
+            if resp.type_url == type_url.as_ref() {
+                let resp: X = X::decode(&resp.value[..])?;
+                Ok(Decoded::Expected(resp))
+            } else {
+                Ok(Decoded::Other(resp))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DURATION_TYPE_URL: &str = "type.googleapis.com/google.protobuf.Duration";
+
+    fn response(type_url: &str) -> operation::Result {
+        operation::Result::Response(Any {
+            type_url: type_url.to_string(),
+            value: prost_types::Duration {
+                seconds: 3,
+                nanos: 0,
+            }
+            .encode_to_vec(),
+        })
+    }
+
+    #[test]
+    fn it_decodes_leniently() {
+        let decoded: Decoded<prost_types::Duration> =
+            decode_operation_result_lenient(response(DURATION_TYPE_URL), DURATION_TYPE_URL)
+                .unwrap();
+        assert!(matches!(decoded, Decoded::Expected(d) if d.seconds == 3));
+
+        let decoded: Decoded<prost_types::Duration> =
+            decode_operation_result_lenient(response("unexpected"), DURATION_TYPE_URL).unwrap();
+        assert!(matches!(decoded, Decoded::Other(any) if any.type_url == "unexpected"));
+
+        let strict: Result<prost_types::Duration, _> =
+            decode_operation_result_as(response("unexpected"), DURATION_TYPE_URL);
+        assert!(matches!(strict, Err(Error::InvalidType(t)) if t == "unexpected"));
+    }
+
+    #[test]
+    fn it_decodes_operation_responses() {
+        let expected = SuggestTrialsResponse {
+            trials: vec![crate::Trial {
+                name: "t".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let result = operation::Result::Response(Any {
+            type_url: SuggestTrialsResponse::TYPE_URL.to_string(),
+            value: expected.encode_to_vec(),
+        });
+
+        let decoded: SuggestTrialsResponse = decode_operation_result(result.clone()).unwrap();
+        assert_eq!(decoded, expected);
+
+        let mismatched: Result<CheckTrialEarlyStoppingStateResponse, _> =
+            decode_operation_result(result);
+        assert!(matches!(
+            mismatched,
+            Err(Error::InvalidType(t)) if t == SuggestTrialsResponse::TYPE_URL
+        ));
+    }
+
+    #[test]
+    fn it_decodes_early_stopping_responses() {
+        for should_stop in [true, false] {
+            let result = operation::Result::Response(Any {
+                type_url: CheckTrialEarlyStoppingStateResponse::TYPE_URL.to_string(),
+                value: CheckTrialEarlyStoppingStateResponse { should_stop }.encode_to_vec(),
+            });
+
+            let decoded: CheckTrialEarlyStoppingStateResponse =
+                decode_operation_result(result).unwrap();
+            assert_eq!(decoded.should_stop, should_stop);
+        }
+    }
+
+    #[test]
+    fn it_decodes_error_details() {
+        let retry_info = RetryInfo {
+            retry_delay: Some(prost_types::Duration {
+                seconds: 2,
+                nanos: 0,
+            }),
+        };
+        let bad_request = BadRequest {
+            field_violations: vec![FieldViolation {
+                field: "study_spec".to_string(),
+                description: "missing".to_string(),
+            }],
+        };
+
+        let status = Status {
+            code: 3,
+            message: "invalid".to_string(),
+            details: vec![
+                Any {
+                    type_url: "type.googleapis.com/google.rpc.RetryInfo".to_string(),
+                    value: retry_info.encode_to_vec(),
+                },
+                Any {
+                    type_url: "type.googleapis.com/google.rpc.BadRequest".to_string(),
+                    value: bad_request.encode_to_vec(),
+                },
+                Any {
+                    type_url: "type.googleapis.com/google.rpc.DebugInfo".to_string(),
+                    value: vec![],
+                },
+            ],
+        };
+
+        let err: Result<prost_types::Duration, _> =
+            decode_operation_result_as(operation::Result::Error(status), DURATION_TYPE_URL);
+        let details = err.unwrap_err().details().unwrap();
+
+        assert_eq!(
+            details,
+            ErrorDetails {
+                bad_request: Some(bad_request),
+                quota_failure: None,
+                retry_info: Some(retry_info),
+            }
+        );
+    }
+}
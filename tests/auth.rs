@@ -0,0 +1,31 @@
+// Copyright 2022 Sebastien Soudan.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authentication failures at construction time.
+//!
+//! In its own test binary, as it changes the environment of the process.
+
+use gcp_vertex_ai_vizier::{Error, VizierClient};
+
+#[tokio::test]
+async fn it_reports_missing_credentials_as_auth_errors() {
+    std::env::set_var(
+        "GOOGLE_APPLICATION_CREDENTIALS",
+        "/nonexistent/application_default_credentials.json",
+    );
+
+    let result = VizierClient::new("project".to_string(), "us-central1".to_string()).await;
+
+    assert!(matches!(result, Err(Error::Auth(_))));
+}